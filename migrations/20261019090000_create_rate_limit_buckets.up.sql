@@ -0,0 +1,16 @@
+-- Token buckets for rate limiting shared between instances
+
+CREATE TABLE
+    IF NOT EXISTS rate_limit_buckets (
+        key TEXT PRIMARY KEY NOT NULL,
+        tokens DOUBLE PRECISION NOT NULL,
+        allowed BOOLEAN NOT NULL,
+        updated_at TIMESTAMP
+        WITH
+            TIME ZONE NOT NULL,
+            expires_at TIMESTAMP
+        WITH
+            TIME ZONE NOT NULL
+    );
+
+CREATE INDEX rate_limit_buckets_expires_at_idx ON rate_limit_buckets (expires_at);
@@ -0,0 +1,3 @@
+DROP TRIGGER IF EXISTS users_notify_change ON users;
+DROP TRIGGER IF EXISTS notes_notify_change ON notes;
+DROP FUNCTION IF EXISTS notify_entity_change();
@@ -0,0 +1,20 @@
+-- Broadcasts updated and deleted notes and users on the `entity_changes`
+-- channel, so every instance can drop them from its caches
+
+CREATE OR REPLACE FUNCTION notify_entity_change() RETURNS trigger AS $$
+BEGIN
+    PERFORM pg_notify(
+        'entity_changes',
+        json_build_object('table', TG_TABLE_NAME, 'op', TG_OP, 'id', OLD.id)::text
+    );
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER notes_notify_change
+    AFTER UPDATE OR DELETE ON notes
+    FOR EACH ROW EXECUTE FUNCTION notify_entity_change();
+
+CREATE TRIGGER users_notify_change
+    AFTER UPDATE OR DELETE ON users
+    FOR EACH ROW EXECUTE FUNCTION notify_entity_change();
@@ -0,0 +1,3 @@
+-- Disabled users cannot log in; NULL while the user is active
+
+ALTER TABLE users ADD COLUMN disabled_at TIMESTAMP WITH TIME ZONE;
@@ -0,0 +1,3 @@
+-- The original case of the emails is not kept, so only the index goes
+
+DROP INDEX users_email_lower_key;
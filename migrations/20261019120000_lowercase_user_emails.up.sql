@@ -0,0 +1,20 @@
+-- Emails are lowercased before they reach the database, so rows stored
+-- before that need the same treatment to be found on login. Addresses that
+-- only differ in case belong to separate accounts and have to be merged by
+-- hand first; the migration refuses to pick one.
+
+DO $$
+DECLARE
+    duplicates TEXT;
+BEGIN
+    SELECT string_agg(address, ', ') INTO duplicates
+    FROM (SELECT lower(email) AS address FROM users GROUP BY lower(email) HAVING count(*) > 1) AS d;
+
+    IF duplicates IS NOT NULL THEN
+        RAISE EXCEPTION 'users differ only by email case, merge them before migrating: %', duplicates;
+    END IF;
+END $$;
+
+UPDATE users SET email = lower(email) WHERE email <> lower(email);
+
+CREATE UNIQUE INDEX users_email_lower_key ON users (lower(email));
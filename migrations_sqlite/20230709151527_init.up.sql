@@ -0,0 +1,10 @@
+-- SQLite has no UUID type: ids are hyphenated text. Timestamps are RFC 3339
+-- text in UTC, which sorts chronologically.
+CREATE TABLE
+    IF NOT EXISTS notes (
+        id TEXT PRIMARY KEY NOT NULL,
+        title TEXT NOT NULL UNIQUE CONSTRAINT notes_title_length CHECK (length(title) <= 255),
+        content TEXT NOT NULL,
+        created_at TEXT DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+        updated_at TEXT DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now'))
+    );
@@ -0,0 +1,13 @@
+CREATE TABLE
+    users (
+        id TEXT PRIMARY KEY NOT NULL,
+        firstname TEXT NOT NULL CONSTRAINT users_firstname_length CHECK (length(firstname) <= 100),
+        lastname TEXT NOT NULL CONSTRAINT users_lastname_length CHECK (length(lastname) <= 100),
+        email TEXT NOT NULL UNIQUE CONSTRAINT users_email_length CHECK (length(email) <= 255),
+        password TEXT NOT NULL CONSTRAINT users_password_length CHECK (length(password) <= 100),
+        role TEXT NOT NULL DEFAULT 'user' CONSTRAINT users_role_length CHECK (length(role) <= 50),
+        created_at TEXT DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+        updated_at TEXT DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now'))
+    );
+
+CREATE INDEX users_email_idx ON users (email);
@@ -0,0 +1,4 @@
+-- The original case of the emails is not kept, so only the index goes
+
+DROP TRIGGER users_email_lower_key;
+DROP INDEX users_email_lower_idx;
@@ -0,0 +1,17 @@
+-- Emails are lowercased before they reach the database, so rows stored
+-- before that need the same treatment to be found on login. Addresses that
+-- only differ in case belong to separate accounts and have to be merged by
+-- hand first: lowercasing them breaks the unique constraint on email.
+
+UPDATE users SET email = lower(email) WHERE email <> lower(email);
+
+-- Stands in for the unique index on lower(email) Postgres has. A unique
+-- expression index would be checked before users_email_key, and report exact
+-- duplicates under the wrong name, so the trigger only rejects case variants.
+CREATE INDEX users_email_lower_idx ON users (lower(email));
+
+CREATE TRIGGER users_email_lower_key BEFORE INSERT ON users
+WHEN EXISTS (SELECT 1 FROM users WHERE lower(email) = lower(NEW.email) AND email <> NEW.email)
+BEGIN
+    SELECT RAISE(ABORT, 'users_email_lower_key');
+END;
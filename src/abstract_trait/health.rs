@@ -0,0 +1,15 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+
+pub type DynHealthCheck = Arc<dyn HealthCheckTrait + Send + Sync>;
+
+/// A dependency the service needs in order to handle traffic. Register it
+/// with [`crate::service_register::ServiceRegister::register_health_check`]
+/// to have it reported by `/health/ready`.
+#[async_trait]
+pub trait HealthCheckTrait {
+    fn name(&self) -> &str;
+    /// `Err` carries a short description of what is wrong.
+    async fn check(&self) -> Result<(), String>;
+}
@@ -1,5 +1,13 @@
+mod health;
 mod note;
+mod rate_limit;
+mod unit_of_work;
 mod user;
 
+pub use health::{DynHealthCheck, HealthCheckTrait};
 pub use note::{DynNoteRepository, DynNoteService, NoteRepositoryTrait, NoteServiceTrait};
+pub use rate_limit::{DynRateLimitStore, RateLimitStoreTrait};
+pub use unit_of_work::{
+    BoxUnitOfWork, DynUnitOfWorkFactory, UnitOfWorkFactoryTrait, UnitOfWorkTrait,
+};
 pub use user::{DynUserRepository, DynUserService, UserRepositoryTrait, UserServiceTrait};
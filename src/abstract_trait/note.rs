@@ -2,7 +2,7 @@ use std::sync::Arc;
 
 use async_trait::async_trait;
 
-use crate::{models::NoteModel, response::NoteResponse};
+use crate::{error::AppError, models::NoteModel, response::NoteResponse};
 
 use sqlx::Error;
 use uuid::Uuid;
@@ -12,6 +12,7 @@ pub type DynNoteService = Arc<dyn NoteServiceTrait + Send + Sync>;
 
 #[async_trait]
 pub trait NoteRepositoryTrait {
+    /// Every note, oldest first.
     async fn get_notes(&self) -> Result<Vec<NoteModel>, Error>;
     async fn get_note_id(&self, id: Uuid) -> Result<Option<NoteModel>, Error>;
     async fn create_note(&self, title: &str, content: &str) -> Result<NoteModel, Error>;
@@ -22,18 +23,19 @@ pub trait NoteRepositoryTrait {
         content: &str,
     ) -> Result<Option<NoteModel>, Error>;
     async fn delete(&self, id: Uuid) -> Result<(), Error>;
+    async fn count(&self) -> Result<i64, Error>;
 }
 
 #[async_trait]
 pub trait NoteServiceTrait {
-    async fn get_notes(&self) -> anyhow::Result<Vec<NoteResponse>>;
-    async fn get_note_id(&self, id: Uuid) -> anyhow::Result<Option<NoteResponse>>;
-    async fn create_note(&self, title: &str, content: &str) -> anyhow::Result<NoteResponse>;
+    async fn get_notes(&self) -> Result<Vec<NoteResponse>, AppError>;
+    async fn get_note_id(&self, id: Uuid) -> Result<NoteResponse, AppError>;
+    async fn create_note(&self, title: &str, content: &str) -> Result<NoteResponse, AppError>;
     async fn update_note(
         &self,
         id: Uuid,
         title: &str,
         content: &str,
-    ) -> anyhow::Result<Option<NoteResponse>>;
-    async fn delete_note(&self, id: Uuid) -> anyhow::Result<()>;
+    ) -> Result<NoteResponse, AppError>;
+    async fn delete_note(&self, id: Uuid) -> Result<(), AppError>;
 }
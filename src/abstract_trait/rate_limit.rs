@@ -0,0 +1,24 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use sqlx::Error;
+
+use crate::models::RateLimitBucketModel;
+
+pub type DynRateLimitStore = Arc<dyn RateLimitStoreTrait + Send + Sync>;
+
+#[async_trait]
+pub trait RateLimitStoreTrait {
+    /// Refills bucket `key`, which holds at most `capacity` tokens and gains
+    /// `refill_per_sec` tokens per second, then takes one token if there is
+    /// a whole one left. Unknown keys start with a full bucket.
+    async fn acquire(
+        &self,
+        key: &str,
+        capacity: f64,
+        refill_per_sec: f64,
+    ) -> Result<RateLimitBucketModel, Error>;
+    /// Forgets buckets that have refilled completely, which behave exactly
+    /// like unknown ones. Returns how many were removed.
+    async fn purge_expired(&self) -> Result<u64, Error>;
+}
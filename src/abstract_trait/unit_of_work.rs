@@ -0,0 +1,28 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use sqlx::Error;
+
+use super::{DynNoteRepository, DynUserRepository};
+
+pub type DynUnitOfWorkFactory = Arc<dyn UnitOfWorkFactoryTrait + Send + Sync>;
+pub type BoxUnitOfWork = Box<dyn UnitOfWorkTrait + Send + Sync>;
+
+#[async_trait]
+pub trait UnitOfWorkFactoryTrait {
+    async fn begin(&self) -> Result<BoxUnitOfWork, Error>;
+}
+
+/// Repository calls that succeed or fail together. Changes are visible to
+/// the unit of work's own repositories right away and to everyone else once
+/// committed. Dropping it without committing rolls back.
+///
+/// Units of work are serializable: when one conflicts with a concurrent
+/// change, a repository call or the commit fails with SQLSTATE `40001` and
+/// the whole unit of work should be retried.
+#[async_trait]
+pub trait UnitOfWorkTrait {
+    fn notes(&self) -> &DynNoteRepository;
+    fn users(&self) -> &DynUserRepository;
+    async fn commit(self: Box<Self>) -> Result<(), Error>;
+}
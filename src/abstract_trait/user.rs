@@ -4,7 +4,7 @@ use async_trait::async_trait;
 use sqlx::Error;
 use uuid::Uuid;
 
-use crate::{models::UserModel, response::UserSchema};
+use crate::{error::AppError, models::UserModel, response::UserSchema};
 
 pub type DynUserRepository = Arc<dyn UserRepositoryTrait + Send + Sync>;
 pub type DynUserService = Arc<dyn UserServiceTrait + Send + Sync>;
@@ -28,7 +28,12 @@ pub trait UserRepositoryTrait {
         lastname: &str,
         password: &str,
     ) -> Result<Option<UserModel>, Error>;
+    #[allow(dead_code)]
     async fn delete_user(&self, email: &str) -> Result<bool, Error>;
+    async fn update_role(&self, email: &str, role: &str) -> Result<Option<UserModel>, Error>;
+    /// Disabling keeps the time a user was first disabled.
+    async fn set_disabled(&self, email: &str, disabled: bool) -> Result<Option<UserModel>, Error>;
+    async fn count(&self) -> Result<i64, Error>;
 }
 
 #[async_trait]
@@ -39,16 +44,36 @@ pub trait UserServiceTrait {
         lastname: &str,
         email: &str,
         password: &str,
-    ) -> anyhow::Result<UserSchema>;
-    async fn find_by_email_exists(&self, email: &str) -> anyhow::Result<bool>;
-    async fn find_user_by_email(&self, email: &str) -> anyhow::Result<Option<UserModel>>;
-    async fn find_by_id(&self, id: Uuid) -> anyhow::Result<Option<UserSchema>>;
+    ) -> Result<UserSchema, AppError>;
+    /// Creates a user who has `role` from the start: if setting the role
+    /// fails, the user is not created either.
+    async fn create_user_with_role(
+        &self,
+        firstname: &str,
+        lastname: &str,
+        email: &str,
+        password: &str,
+        role: &str,
+    ) -> Result<UserModel, AppError>;
+    async fn find_by_email_exists(&self, email: &str) -> Result<bool, AppError>;
+    async fn find_user_by_email(&self, email: &str) -> Result<Option<UserModel>, AppError>;
+    async fn find_by_id(&self, id: Uuid) -> Result<Option<UserSchema>, AppError>;
+    /// Like `find_by_id`, with the role, password hash and `disabled_at`.
+    async fn find_user_by_id(&self, id: Uuid) -> Result<Option<UserModel>, AppError>;
     async fn update_user(
         &self,
         email: &str,
         firstname: &str,
         lastname: &str,
         password: &str,
-    ) -> anyhow::Result<Option<UserSchema>>;
-    async fn delete_user(&self, email: &str) -> anyhow::Result<bool>;
+    ) -> Result<Option<UserSchema>, AppError>;
+    #[allow(dead_code)]
+    async fn delete_user(&self, email: &str) -> Result<bool, AppError>;
+    async fn update_role(&self, email: &str, role: &str) -> Result<Option<UserModel>, AppError>;
+    /// Disabled users cannot log in.
+    async fn set_disabled(
+        &self,
+        email: &str,
+        disabled: bool,
+    ) -> Result<Option<UserModel>, AppError>;
 }
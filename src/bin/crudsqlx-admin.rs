@@ -0,0 +1,615 @@
+use std::fmt;
+use std::io::{BufRead, IsTerminal, Write};
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use chrono::{DateTime, Utc};
+use crudsqlx::config::{
+    Config, ConnectionManager, ConnectionPool, DatabaseBackend, MigrationStatus,
+};
+use crudsqlx::error::AppError;
+use crudsqlx::metrics::Metrics;
+use crudsqlx::models::{UserModel, ADMIN_ROLE, USER_ROLES};
+use crudsqlx::schema::RegisterUserSchema;
+use crudsqlx::security::{hash_password, PasswordPolicy};
+use crudsqlx::service_register::ServiceRegister;
+use dotenv::dotenv;
+use serde::Serialize;
+use serde_json::json;
+use uuid::Uuid;
+use validator::Validate;
+
+const USAGE: &str = "\
+Usage: crudsqlx-admin [--config <file>] [--json] <command>
+
+Commands:
+  migrate run                     Apply pending migrations
+  migrate revert                  Revert the most recently applied migration,
+                                  if its down script does anything
+  migrate status                  List migrations as applied, pending or
+                                  modified since they were applied
+  user create <email> <firstname> <lastname> [--admin]
+                                  Create a user, password read from stdin
+  user reset-password <email>     Set a new password, read from stdin
+  user set-role <email> <role>    Make the user a `user` or an `admin`
+  user disable <email>            Stop the user from logging in
+  user enable <email>             Let a disabled user log in again
+  stats                           Count notes and users
+
+--json prints the result as JSON. Errors go to stderr with exit code 1.";
+
+#[derive(Debug, PartialEq, Eq)]
+enum Command {
+    MigrateRun,
+    MigrateRevert,
+    MigrateStatus,
+    CreateUser {
+        email: String,
+        firstname: String,
+        lastname: String,
+        admin: bool,
+    },
+    ResetPassword {
+        email: String,
+    },
+    SetRole {
+        email: String,
+        role: String,
+    },
+    SetDisabled {
+        email: String,
+        disabled: bool,
+    },
+    Stats,
+}
+
+impl Command {
+    fn reads_password(&self) -> bool {
+        matches!(
+            self,
+            Command::CreateUser { .. } | Command::ResetPassword { .. }
+        )
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+struct Args {
+    config_file: Option<PathBuf>,
+    json: bool,
+    command: Command,
+}
+
+fn parse_args(args: impl IntoIterator<Item = String>) -> Result<Args, String> {
+    let mut config_file = None;
+    let mut json = false;
+    let mut admin = false;
+    let mut words = Vec::new();
+
+    let mut iter = args.into_iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--config" => {
+                let path = iter.next().ok_or("--config requires a file path")?;
+                config_file = Some(PathBuf::from(path));
+            }
+            "--json" => json = true,
+            "--admin" => admin = true,
+            _ if arg.starts_with("--") => return Err(format!("unknown argument '{}'", arg)),
+            _ => words.push(arg),
+        }
+    }
+
+    let words: Vec<&str> = words.iter().map(String::as_str).collect();
+    let command = match words.as_slice() {
+        ["migrate", "run"] => Command::MigrateRun,
+        ["migrate", "revert"] => Command::MigrateRevert,
+        ["migrate", "status"] => Command::MigrateStatus,
+        ["user", "create", email, firstname, lastname] => Command::CreateUser {
+            email: email.to_string(),
+            firstname: firstname.to_string(),
+            lastname: lastname.to_string(),
+            admin,
+        },
+        ["user", "reset-password", email] => Command::ResetPassword {
+            email: normalize_email(email),
+        },
+        ["user", "set-role", email, role] => {
+            if !USER_ROLES.contains(role) {
+                return Err(format!("role must be one of: {}", USER_ROLES.join(", ")));
+            }
+            Command::SetRole {
+                email: normalize_email(email),
+                role: role.to_string(),
+            }
+        }
+        ["user", "disable", email] => Command::SetDisabled {
+            email: normalize_email(email),
+            disabled: true,
+        },
+        ["user", "enable", email] => Command::SetDisabled {
+            email: normalize_email(email),
+            disabled: false,
+        },
+        ["stats"] => Command::Stats,
+        [] => return Err("missing command".to_string()),
+        _ => return Err(format!("unknown command '{}'", words.join(" "))),
+    };
+
+    if admin && !matches!(command, Command::CreateUser { .. }) {
+        return Err("--admin only applies to user create".to_string());
+    }
+
+    Ok(Args {
+        config_file,
+        json,
+        command,
+    })
+}
+
+/// Emails are stored trimmed and lowercased, like the API does on register.
+fn normalize_email(email: &str) -> String {
+    email.trim().to_lowercase()
+}
+
+/// The first line of stdin, so passwords stay out of the shell history and
+/// the process list. The input is not hidden when typed on a terminal.
+fn read_password() -> Result<String, AppError> {
+    let stdin = std::io::stdin();
+    if stdin.is_terminal() {
+        eprint!("Password: ");
+        std::io::stderr().flush().ok();
+    }
+
+    let mut line = String::new();
+    stdin
+        .lock()
+        .read_line(&mut line)
+        .map_err(AppError::internal)?;
+    Ok(line.trim_end_matches(['\r', '\n']).to_string())
+}
+
+/// A user without the password hash.
+#[derive(Debug, Serialize)]
+struct UserSummary {
+    id: Uuid,
+    email: String,
+    firstname: String,
+    lastname: String,
+    role: String,
+    #[serde(rename = "disabledAt")]
+    disabled_at: Option<DateTime<Utc>>,
+}
+
+impl From<UserModel> for UserSummary {
+    fn from(user: UserModel) -> Self {
+        UserSummary {
+            id: user.id,
+            email: user.email,
+            firstname: user.firstname,
+            lastname: user.lastname,
+            role: user.role,
+            disabled_at: user.disabled_at,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+#[serde(untagged)]
+enum Output {
+    Migrated { applied: Vec<MigrationStatus> },
+    Reverted { reverted: Option<i64> },
+    Migrations(Vec<MigrationStatus>),
+    User(UserSummary),
+    Stats { notes: i64, users: i64 },
+}
+
+impl fmt::Display for Output {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Output::Migrated { applied } if applied.is_empty() => {
+                write!(f, "No pending migrations")
+            }
+            Output::Migrated { applied } => {
+                let lines: Vec<String> = applied
+                    .iter()
+                    .map(|m| format!("Applied {} {}", m.version, m.description))
+                    .collect();
+                write!(f, "{}", lines.join("\n"))
+            }
+            Output::Reverted { reverted: None } => write!(f, "No migration to revert"),
+            Output::Reverted {
+                reverted: Some(version),
+            } => write!(f, "Reverted {}", version),
+            Output::Migrations(migrations) => {
+                let lines: Vec<String> = migrations
+                    .iter()
+                    .map(|m| {
+                        let state = match &m.applied_checksum {
+                            None => "pending",
+                            Some(checksum) if *checksum != m.checksum => "modified",
+                            Some(_) => "applied",
+                        };
+                        format!("{}  {:<8}  {}", m.version, state, m.description)
+                    })
+                    .collect();
+                write!(f, "{}", lines.join("\n"))
+            }
+            Output::User(user) => {
+                writeln!(f, "{} ({})", user.email, user.id)?;
+                writeln!(f, "  name:     {} {}", user.firstname, user.lastname)?;
+                writeln!(f, "  role:     {}", user.role)?;
+                match user.disabled_at {
+                    Some(at) => write!(f, "  disabled: since {}", at.to_rfc3339()),
+                    None => write!(f, "  disabled: no"),
+                }
+            }
+            Output::Stats { notes, users } => write!(f, "notes: {}\nusers: {}", notes, users),
+        }
+    }
+}
+
+/// The error with every validation message, which `Display` leaves out.
+fn describe(err: &AppError) -> String {
+    let mut description = err.to_string();
+    if let AppError::Validation { errors, .. } = err {
+        for (field, messages) in errors {
+            for message in messages {
+                description.push_str(&format!("\n  {}: {}", field, message));
+            }
+        }
+    }
+    description
+}
+
+async fn run(
+    command: Command,
+    password: Option<String>,
+    pool: ConnectionPool,
+    config: Config,
+) -> Result<Output, AppError> {
+    match command {
+        Command::MigrateRun => {
+            let pending = pool.pending_migrations().await?;
+            pool.run_migrations().await?;
+            let applied = pool
+                .migration_status()
+                .await?
+                .into_iter()
+                .filter(|migration| pending.iter().any(|p| p.version == migration.version))
+                .collect();
+            return Ok(Output::Migrated { applied });
+        }
+        Command::MigrateRevert => {
+            let reverted = pool.revert_last_migration().await?;
+            return Ok(Output::Reverted { reverted });
+        }
+        Command::MigrateStatus => return Ok(Output::Migrations(pool.migration_status().await?)),
+        _ => {}
+    }
+
+    let password_policy =
+        PasswordPolicy::new(config.auth.password_policy.clone()).map_err(AppError::internal)?;
+    let metrics = Metrics::new().map_err(AppError::internal)?;
+    let register = ServiceRegister::new(pool, Vec::new(), config, password_policy, metrics);
+    let users = &register.user_service;
+    let user_not_found = || AppError::NotFound("User not found".to_string());
+
+    let user = match command {
+        Command::CreateUser {
+            email,
+            firstname,
+            lastname,
+            admin,
+        } => {
+            // Held to the same rules as registering through the API.
+            let body: RegisterUserSchema = serde_json::from_value(json!({
+                "email": email,
+                "firstname": firstname,
+                "lastname": lastname,
+                "password": password.unwrap_or_default(),
+            }))
+            .map_err(AppError::internal)?;
+            body.validate()?;
+            register
+                .password_policy
+                .validate(
+                    &body.password,
+                    &[&body.email, &body.firstname, &body.lastname],
+                )
+                .map_err(|errors| AppError::validation("password", errors))?;
+
+            if users.find_by_email_exists(&body.email).await? {
+                return Err(AppError::Conflict(
+                    "User with that email already exists".to_string(),
+                ));
+            }
+            let hashed_password = hash_password(&body.password)?;
+            if admin {
+                let user = users
+                    .create_user_with_role(
+                        &body.firstname,
+                        &body.lastname,
+                        &body.email,
+                        &hashed_password,
+                        ADMIN_ROLE,
+                    )
+                    .await?;
+                Some(user)
+            } else {
+                users
+                    .create_user(
+                        &body.firstname,
+                        &body.lastname,
+                        &body.email,
+                        &hashed_password,
+                    )
+                    .await?;
+                users.find_user_by_email(&body.email).await?
+            }
+        }
+        Command::ResetPassword { email } => {
+            let user = users
+                .find_user_by_email(&email)
+                .await?
+                .ok_or_else(user_not_found)?;
+            let password = password.unwrap_or_default();
+            register
+                .password_policy
+                .validate(&password, &[&user.email, &user.firstname, &user.lastname])
+                .map_err(|errors| AppError::validation("password", errors))?;
+
+            let hashed_password = hash_password(&password)?;
+            users
+                .update_user(&email, &user.firstname, &user.lastname, &hashed_password)
+                .await?;
+            users.find_user_by_email(&email).await?
+        }
+        Command::SetRole { email, role } => users.update_role(&email, &role).await?,
+        Command::SetDisabled { email, disabled } => users.set_disabled(&email, disabled).await?,
+        Command::Stats => {
+            let totals = register.stats_service.totals().await?;
+            return Ok(Output::Stats {
+                notes: totals.notes,
+                users: totals.users,
+            });
+        }
+        Command::MigrateRun | Command::MigrateRevert | Command::MigrateStatus => unreachable!(),
+    };
+
+    user.map(|user| Output::User(user.into()))
+        .ok_or_else(user_not_found)
+}
+
+#[actix_web::main]
+async fn main() -> ExitCode {
+    dotenv().ok();
+
+    let args = match parse_args(std::env::args().skip(1)) {
+        Ok(args) => args,
+        Err(err) => {
+            eprintln!("{}\n\n{}", err, USAGE);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let mut config = match Config::load(args.config_file.as_deref()) {
+        Ok(config) => config,
+        Err(errors) => {
+            eprint!("{}", errors);
+            return ExitCode::FAILURE;
+        }
+    };
+    if config.database.backend != DatabaseBackend::Sql {
+        eprintln!(
+            "Error: database.backend must be \"sql\", the memory backend has no data to manage"
+        );
+        return ExitCode::FAILURE;
+    }
+    // Migrations only change when asked to.
+    config.database.run_migrations = false;
+
+    let password = if args.command.reads_password() {
+        match read_password() {
+            Ok(password) => Some(password),
+            Err(err) => {
+                eprintln!("Error: {}", describe(&err));
+                return ExitCode::FAILURE;
+            }
+        }
+    } else {
+        None
+    };
+
+    let pool = match ConnectionManager::new_pool(&config.database).await {
+        Ok(pool) => pool,
+        Err(err) => {
+            eprintln!("Error connecting to the database: {}", err);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let result = run(args.command, password, pool.clone(), config).await;
+    pool.close().await;
+
+    match result {
+        Ok(output) if args.json => {
+            println!("{}", serde_json::to_string_pretty(&output).unwrap());
+            ExitCode::SUCCESS
+        }
+        Ok(output) => {
+            println!("{}", output);
+            ExitCode::SUCCESS
+        }
+        Err(err) => {
+            eprintln!("Error: {}", describe(&err));
+            ExitCode::FAILURE
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use sqlx::sqlite::SqlitePoolOptions;
+
+    use super::*;
+
+    fn parse(args: &str) -> Result<Args, String> {
+        parse_args(args.split_whitespace().map(String::from))
+    }
+
+    #[test]
+    fn parses_commands_and_options() {
+        let args = parse("--json user create Ada@Example.com Ada Lovelace --admin").unwrap();
+        assert!(args.json);
+        assert_eq!(args.config_file, None);
+        assert_eq!(
+            args.command,
+            Command::CreateUser {
+                email: "Ada@Example.com".to_string(),
+                firstname: "Ada".to_string(),
+                lastname: "Lovelace".to_string(),
+                admin: true,
+            }
+        );
+
+        let args = parse("--config admin.toml user disable Ada@Example.com").unwrap();
+        assert_eq!(args.config_file, Some(PathBuf::from("admin.toml")));
+        assert_eq!(
+            args.command,
+            Command::SetDisabled {
+                email: "ada@example.com".to_string(),
+                disabled: true,
+            }
+        );
+        assert_eq!(
+            parse("migrate status").unwrap().command,
+            Command::MigrateStatus
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_arguments() {
+        assert!(parse("").is_err());
+        assert!(parse("migrate").is_err());
+        assert!(parse("user set-role ada@example.com root").is_err());
+        assert!(parse("user disable ada@example.com --admin").is_err());
+        assert!(parse("stats --verbose").is_err());
+        assert!(parse("--config").is_err());
+    }
+
+    #[tokio::test]
+    async fn runs_and_reverts_migrations() {
+        let pool = ConnectionPool::Sqlite(
+            SqlitePoolOptions::new()
+                .max_connections(1)
+                .idle_timeout(None)
+                .max_lifetime(None)
+                .connect("sqlite::memory:")
+                .await
+                .unwrap(),
+        );
+        let config = Config::default();
+
+        let Output::Migrated { applied } =
+            run(Command::MigrateRun, None, pool.clone(), config.clone())
+                .await
+                .unwrap()
+        else {
+            panic!("expected the applied migrations");
+        };
+        let latest = applied.last().unwrap().version;
+        assert!(applied.iter().all(|migration| migration.applied));
+
+        let output = run(Command::MigrateRevert, None, pool.clone(), config.clone())
+            .await
+            .unwrap();
+        assert!(
+            matches!(output, Output::Reverted { reverted: Some(version) } if version == latest)
+        );
+
+        let Output::Migrations(migrations) =
+            run(Command::MigrateStatus, None, pool.clone(), config.clone())
+                .await
+                .unwrap()
+        else {
+            panic!("expected the migration status");
+        };
+        assert_eq!(migrations.len(), applied.len());
+        let (last, earlier) = migrations.split_last().unwrap();
+        assert!(!last.applied);
+        assert!(earlier.iter().all(|migration| migration.applied));
+    }
+
+    #[tokio::test]
+    async fn manages_users() {
+        let pool = ConnectionPool::Sqlite(
+            SqlitePoolOptions::new()
+                .max_connections(1)
+                .idle_timeout(None)
+                .max_lifetime(None)
+                .connect("sqlite::memory:")
+                .await
+                .unwrap(),
+        );
+        pool.run_migrations().await.unwrap();
+        let config = Config::default();
+        let password = || Some("Tr0ubadour-Horse".to_string());
+
+        let create = Command::CreateUser {
+            email: " Ada@Example.com".to_string(),
+            firstname: "Ada".to_string(),
+            lastname: "Lovelace".to_string(),
+            admin: true,
+        };
+        let Output::User(user) = run(create, password(), pool.clone(), config.clone())
+            .await
+            .unwrap()
+        else {
+            panic!("expected the user");
+        };
+        assert_eq!(user.email, "ada@example.com");
+        assert_eq!(user.role, "admin");
+
+        let weak = Command::ResetPassword {
+            email: "ada@example.com".to_string(),
+        };
+        let err = run(
+            weak,
+            Some("short".to_string()),
+            pool.clone(),
+            config.clone(),
+        )
+        .await
+        .unwrap_err();
+        assert!(describe(&err).contains("password:"), "{}", describe(&err));
+
+        let disable = Command::SetDisabled {
+            email: "ada@example.com".to_string(),
+            disabled: true,
+        };
+        let Output::User(user) = run(disable, None, pool.clone(), config.clone())
+            .await
+            .unwrap()
+        else {
+            panic!("expected the user");
+        };
+        assert!(user.disabled_at.is_some());
+
+        let missing = Command::SetRole {
+            email: "nobody@example.com".to_string(),
+            role: "admin".to_string(),
+        };
+        let err = run(missing, None, pool.clone(), config.clone())
+            .await
+            .unwrap_err();
+        assert!(matches!(err, AppError::NotFound(_)));
+
+        let output = run(Command::Stats, None, pool.clone(), config)
+            .await
+            .unwrap();
+        assert_eq!(
+            serde_json::to_value(&output).unwrap(),
+            json!({ "notes": 0, "users": 1 })
+        );
+    }
+}
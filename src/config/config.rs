@@ -1,38 +1,459 @@
-#[derive(Debug, Clone)]
+use serde::{de::Error as _, Deserialize, Deserializer, Serialize};
+
+use super::{IpNetwork, Secret};
+
+/// Application settings. See `config.example.toml` for every key and its
+/// default, and [`Config::load`] for where values are read from.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(default)]
 pub struct Config {
-    pub database_url: String,
-    pub jwt_secret: String,
-    pub jwt_expires_in: String,
-    pub jwt_maxage: i32,
-    pub run_migrations: bool,
+    pub server: ServerConfig,
+    pub database: DatabaseConfig,
+    pub auth: AuthConfig,
+    pub cors: CorsConfig,
+    pub rate_limit: RateLimitConfig,
+    pub cache: CacheConfig,
+    pub logging: LoggingConfig,
+    pub tracing: TracingConfig,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct ServerConfig {
+    /// Address to listen on. Use `0.0.0.0` to accept connections from other
+    /// hosts, e.g. inside a container.
+    pub host: String,
+    #[serde(deserialize_with = "port")]
     pub port: u16,
+    /// Worker threads. `None` starts one per physical CPU core.
+    pub workers: Option<usize>,
+    /// How long an idle keep-alive connection stays open. 0 disables
+    /// keep-alive.
+    pub keep_alive_secs: u64,
+    /// Time a client gets to send the request head before receiving a 408.
+    pub client_request_timeout_ms: u64,
+    /// Largest accepted request body, in bytes.
+    pub max_body_bytes: usize,
+    /// Load balancers and reverse proxies whose `Forwarded`,
+    /// `X-Forwarded-*` and `X-Real-IP` headers are believed. Requests from
+    /// anywhere else are taken at face value.
+    pub trusted_proxies: Vec<IpNetwork>,
+    pub error_format: ErrorFormat,
+    pub api_docs_enabled: bool,
+    /// How long to keep accepting requests, with readiness already failing,
+    /// after a shutdown signal so load balancers can stop routing here.
+    pub shutdown_delay_secs: u64,
+    /// How long in-flight requests and background tasks get to finish once
+    /// the server stops accepting connections.
+    pub shutdown_timeout_secs: u64,
+    pub tls: TlsConfig,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        ServerConfig {
+            host: "127.0.0.1".to_string(),
+            port: 8000,
+            workers: None,
+            keep_alive_secs: 5,
+            client_request_timeout_ms: 5000,
+            max_body_bytes: 256 * 1024,
+            trusted_proxies: Vec::new(),
+            error_format: ErrorFormat::Problem,
+            api_docs_enabled: false,
+            shutdown_delay_secs: 0,
+            shutdown_timeout_secs: 30,
+            tls: TlsConfig::default(),
+        }
+    }
+}
+
+/// Serves HTTPS, and HTTP/2 through ALPN, instead of plain HTTP when enabled.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct TlsConfig {
+    pub enabled: bool,
+    /// PEM file with the certificate chain, leaf first.
+    pub cert_path: String,
+    /// PEM file with the PKCS#8, PKCS#1 or SEC1 private key.
+    pub key_path: String,
+    /// How often to check both files for changes and reload them. 0 disables
+    /// reloading.
+    pub reload_interval_secs: u64,
+}
+
+impl Default for TlsConfig {
+    fn default() -> Self {
+        TlsConfig {
+            enabled: false,
+            cert_path: String::new(),
+            key_path: String::new(),
+            reload_interval_secs: 30,
+        }
+    }
+}
+
+// The config crate narrows integers with `as`, so range check explicitly
+// instead of letting 70000 silently become 4464.
+fn port<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u16, D::Error> {
+    let value = u64::deserialize(deserializer)?;
+    u16::try_from(value).map_err(|_| D::Error::custom(format!("port {} is out of range", value)))
+}
+
+/// Body format used for error responses.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ErrorFormat {
+    /// RFC 7807 `application/problem+json`.
+    Problem,
+    /// The original `{"status": "fail", "message": ...}` envelope.
+    Legacy,
+}
+
+/// Where notes and users are stored.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DatabaseBackend {
+    /// The database at `url`, Postgres or SQLite depending on its scheme.
+    Sql,
+    /// Process memory, emptied on restart. For demos and tests; the other
+    /// database settings are ignored.
+    Memory,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct DatabaseConfig {
+    pub backend: DatabaseBackend,
+    /// `postgres://...` or `sqlite://path/to/file.db`.
+    pub url: Secret,
+    /// Apply pending migrations at startup.
+    pub run_migrations: bool,
+    /// Refuse to start while migrations are pending, after running them
+    /// if `run_migrations` is set. On by default, so a schema that is behind
+    /// fails at startup rather than on the first query.
+    pub require_migrations: bool,
+    pub min_connections: u32,
+    pub max_connections: u32,
+    pub acquire_timeout_secs: u64,
+    /// Close connections idle for longer than this. `None` keeps them open.
+    pub idle_timeout_secs: Option<u64>,
+    /// Recycle connections older than this. `None` keeps them forever.
+    pub max_lifetime_secs: Option<u64>,
+    /// Server side `statement_timeout`. `None` uses the server default.
+    /// Postgres only.
+    pub statement_timeout_ms: Option<u64>,
+    /// Postgres only.
+    pub application_name: String,
+    /// How many times to retry the initial connect before giving up.
+    pub connect_retries: u32,
+    /// Delay before the first retry, doubled after every attempt.
+    pub connect_backoff_ms: u64,
+    /// Read replicas of a Postgres `url`. Note and user lookups by id are
+    /// spread over the healthy ones; everything else uses `url`.
+    pub replica_urls: Vec<Secret>,
+    /// How long a caller's reads stay on the primary after they wrote, so
+    /// they see their own changes despite replication lag.
+    pub read_your_writes_secs: u64,
+    /// How often replicas are checked, to take them out of rotation while
+    /// they are unreachable and back in once they recover.
+    pub replica_check_interval_secs: u64,
+    /// How long to wait for a replica connection before falling back to the
+    /// primary and taking the replica out of rotation.
+    pub replica_acquire_timeout_ms: u64,
+}
+
+impl Default for DatabaseConfig {
+    fn default() -> Self {
+        DatabaseConfig {
+            backend: DatabaseBackend::Sql,
+            url: Secret::default(),
+            run_migrations: false,
+            require_migrations: true,
+            min_connections: 0,
+            max_connections: 5,
+            acquire_timeout_secs: 30,
+            idle_timeout_secs: Some(600),
+            max_lifetime_secs: Some(1800),
+            statement_timeout_ms: None,
+            application_name: "crudsqlx".to_string(),
+            connect_retries: 5,
+            connect_backoff_ms: 500,
+            replica_urls: Vec::new(),
+            read_your_writes_secs: 5,
+            replica_check_interval_secs: 5,
+            replica_acquire_timeout_ms: 1000,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct AuthConfig {
+    pub jwt_secret: Secret,
+    /// Intended token and cookie lifetime, in minutes. Not applied yet:
+    /// logins last an hour.
+    pub jwt_maxage: i32,
+    pub password_policy: PasswordPolicyConfig,
+}
+
+impl Default for AuthConfig {
+    fn default() -> Self {
+        AuthConfig {
+            jwt_secret: Secret::default(),
+            jwt_maxage: 60,
+            password_policy: PasswordPolicyConfig::default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct PasswordPolicyConfig {
+    pub min_length: usize,
+    pub max_length: usize,
+    pub require_lowercase: bool,
+    pub require_uppercase: bool,
+    pub require_digit: bool,
+    pub require_symbol: bool,
+    pub disallow_personal_info: bool,
+    pub breached_passwords_path: Option<String>,
+}
+
+impl Default for PasswordPolicyConfig {
+    fn default() -> Self {
+        PasswordPolicyConfig {
+            min_length: 8,
+            max_length: 128,
+            require_lowercase: true,
+            require_uppercase: true,
+            require_digit: true,
+            require_symbol: false,
+            disallow_personal_info: true,
+            breached_passwords_path: None,
+        }
+    }
+}
+
+/// Cross-origin access for browser clients. Preflight `OPTIONS` requests
+/// are answered by the CORS middleware itself.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct CorsConfig {
+    /// Exact origins such as `https://app.example.com`, subdomain patterns
+    /// such as `https://*.example.com`, or `*` for any origin.
+    pub allowed_origins: Vec<String>,
+    pub allowed_methods: Vec<String>,
+    /// Request headers clients may send, or `*` for any.
+    pub allowed_headers: Vec<String>,
+    /// Response headers scripts are allowed to read.
+    pub exposed_headers: Vec<String>,
+    /// Allow cookies and `Authorization` on cross-origin requests. Cannot be
+    /// combined with a `*` origin.
+    pub allow_credentials: bool,
+    /// How long browsers may cache a preflight response. `None` leaves it to
+    /// the browser.
+    pub max_age_secs: Option<usize>,
+}
+
+impl Default for CorsConfig {
+    fn default() -> Self {
+        CorsConfig {
+            allowed_origins: vec!["http://localhost:3000".to_string()],
+            allowed_methods: ["GET", "POST", "PUT", "PATCH", "DELETE"]
+                .map(String::from)
+                .to_vec(),
+            allowed_headers: ["Content-Type", "Authorization", "Accept", "X-Request-Id"]
+                .map(String::from)
+                .to_vec(),
+            exposed_headers: [
+                "ETag",
+                "X-Request-Id",
+                "RateLimit-Limit",
+                "RateLimit-Remaining",
+                "RateLimit-Reset",
+                "Retry-After",
+            ]
+            .map(String::from)
+            .to_vec(),
+            allow_credentials: true,
+            max_age_secs: Some(3600),
+        }
+    }
+}
+
+/// Where rate limit buckets are kept.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RateLimitStore {
+    /// Per process; every instance enforces its own limits.
+    Memory,
+    /// Shared by every instance using the same database.
+    Postgres,
+}
+
+/// Who a rate limit bucket belongs to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RateLimitKey {
+    Ip,
+    /// The logged in user, or the client IP for anonymous requests.
+    User,
+    /// The API key header, or the client IP when it is missing.
+    ApiKey,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct RateLimitConfig {
+    pub enabled: bool,
+    pub store: RateLimitStore,
+    /// Header carrying the API key for `api_key` policies.
+    pub api_key_header: String,
+    /// Checked in order; a request counts against the first policy that
+    /// matches it only.
+    pub policies: Vec<RateLimitPolicyConfig>,
+}
+
+impl Default for RateLimitConfig {
+    fn default() -> Self {
+        RateLimitConfig {
+            enabled: true,
+            store: RateLimitStore::Memory,
+            api_key_header: "X-Api-Key".to_string(),
+            policies: vec![
+                RateLimitPolicyConfig {
+                    name: "auth".to_string(),
+                    path_prefix: "/api/auth".to_string(),
+                    methods: Vec::new(),
+                    key: RateLimitKey::Ip,
+                    requests: 10,
+                    per_secs: 60,
+                },
+                RateLimitPolicyConfig {
+                    name: "notes-read".to_string(),
+                    path_prefix: "/api/notes".to_string(),
+                    methods: vec!["GET".to_string()],
+                    key: RateLimitKey::User,
+                    requests: 300,
+                    per_secs: 60,
+                },
+                RateLimitPolicyConfig {
+                    name: "api".to_string(),
+                    path_prefix: "/api".to_string(),
+                    methods: Vec::new(),
+                    key: RateLimitKey::User,
+                    requests: 120,
+                    per_secs: 60,
+                },
+            ],
+        }
+    }
+}
+
+/// A token bucket holding `requests` tokens that refills completely over
+/// `per_secs`, so short bursts up to `requests` are allowed.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct RateLimitPolicyConfig {
+    pub name: String,
+    /// Matches this path and everything below it.
+    pub path_prefix: String,
+    /// Empty matches every method.
+    #[serde(default)]
+    pub methods: Vec<String>,
+    #[serde(default = "default_rate_limit_key")]
+    pub key: RateLimitKey,
+    pub requests: u32,
+    pub per_secs: u64,
+}
+
+fn default_rate_limit_key() -> RateLimitKey {
+    RateLimitKey::Ip
+}
+
+/// In-process cache of notes and users looked up by id.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct CacheConfig {
+    pub enabled: bool,
+    /// Entries kept per cache; the least recently used go first.
+    pub capacity: usize,
+    /// Entries older than this are looked up again. Bounds how stale a
+    /// cached value can be when a change notification was missed.
+    pub ttl_secs: u64,
+    /// Drop entries changed by other instances as soon as Postgres notifies
+    /// about them. Only used with a Postgres database.
+    pub listen_for_changes: bool,
+}
+
+impl Default for CacheConfig {
+    fn default() -> Self {
+        CacheConfig {
+            enabled: true,
+            capacity: 10_000,
+            ttl_secs: 60,
+            listen_for_changes: true,
+        }
+    }
+}
+
+/// Layout of each log line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    /// One JSON object per line.
+    Json,
+    /// Human readable text, for local development.
+    Text,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct LoggingConfig {
+    /// `env_logger` filter, used when `RUST_LOG` is not set.
+    pub level: String,
+    pub format: LogFormat,
+}
+
+impl Default for LoggingConfig {
+    fn default() -> Self {
+        LoggingConfig {
+            level: "info".to_string(),
+            format: LogFormat::Json,
+        }
+    }
+}
+
+/// Where finished spans are sent.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TracingExporter {
+    /// Tracing is disabled.
+    None,
+    /// OTLP over gRPC to `otlp_endpoint`.
+    Otlp,
+    /// One JSON line per span on stdout, for local development and tests.
+    Stdout,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct TracingConfig {
+    pub exporter: TracingExporter,
+    pub otlp_endpoint: String,
+    pub service_name: String,
+    /// Fraction of new traces to record. Traces started upstream follow the
+    /// caller's sampling decision.
+    pub sample_ratio: f64,
 }
 
-impl Config {
-    pub fn init() -> Config {
-        let database_url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
-        let jwt_secret = std::env::var("JWT_SECRET").expect("JWT_SECRET must be set");
-        let jwt_expires_in = std::env::var("JWT_EXPIRED_IN").expect("JWT_EXPIRED_IN must be set");
-        let jwt_maxage = std::env::var("JWT_MAXAGE").expect("JWT_MAXAGE must be set");
-        let run_migrations_str =
-            std::env::var("RUN_MIGRATIONS").expect("RUN_MIGRATIONS must be set");
-        let port_str = std::env::var("PORT").expect("PORT must be set");
-
-        let run_migrations = match run_migrations_str.as_str() {
-            "true" => true,
-            "false" => false,
-            _ => panic!("RUN_MIGRATIONS must be either 'true' or 'false'"),
-        };
-
-        let port = port_str.parse().expect("Invalid value for PORT");
-
-        Config {
-            database_url,
-            jwt_secret,
-            jwt_expires_in,
-            jwt_maxage: jwt_maxage.parse().expect("Invalid value for JWT_MAXAGE"),
-            run_migrations,
-            port,
+impl Default for TracingConfig {
+    fn default() -> Self {
+        TracingConfig {
+            exporter: TracingExporter::None,
+            otlp_endpoint: "http://localhost:4317".to_string(),
+            service_name: "crudsqlx".to_string(),
+            sample_ratio: 1.0,
         }
     }
 }
@@ -1,23 +1,514 @@
-use sqlx::{postgres::PgPoolOptions, Pool, Postgres};
+use std::str::FromStr;
+use std::time::Duration;
 
-pub type ConnectionPool = Pool<Postgres>;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sqlx::{
+    migrate::Migrator,
+    pool::PoolOptions,
+    postgres::{PgConnectOptions, PgPool},
+    sqlite::{SqliteConnectOptions, SqliteJournalMode, SqlitePool},
+    Database, FromRow, Pool,
+};
+use utoipa::ToSchema;
+
+use super::config::DatabaseConfig;
+
+static POSTGRES_MIGRATOR: Migrator = sqlx::migrate!();
+static SQLITE_MIGRATOR: Migrator = sqlx::migrate!("./migrations_sqlite");
+
+/// The database driver, chosen by the scheme of `database.url`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DatabaseDriver {
+    /// `postgres://` or `postgresql://`
+    Postgres,
+    /// `sqlite:`, e.g. `sqlite://crudsqlx.db` or `sqlite::memory:`
+    Sqlite,
+}
+
+impl DatabaseDriver {
+    pub fn from_url(url: &str) -> Option<Self> {
+        if url.starts_with("postgres://") || url.starts_with("postgresql://") {
+            Some(DatabaseDriver::Postgres)
+        } else if url.starts_with("sqlite:") {
+            Some(DatabaseDriver::Sqlite)
+        } else {
+            None
+        }
+    }
+}
+
+/// Connections to the application database. Only the repositories of a
+/// driver look inside; everything else goes through the methods here.
+#[derive(Debug, Clone)]
+pub enum ConnectionPool {
+    Postgres(PgPool),
+    Sqlite(SqlitePool),
+}
+
+impl ConnectionPool {
+    /// Migrations embedded in the binary for this driver.
+    pub fn migrator(&self) -> &'static Migrator {
+        match self {
+            ConnectionPool::Postgres(_) => &POSTGRES_MIGRATOR,
+            ConnectionPool::Sqlite(_) => &SQLITE_MIGRATOR,
+        }
+    }
+
+    pub async fn run_migrations(&self) -> Result<(), sqlx::Error> {
+        match self {
+            ConnectionPool::Postgres(pool) => POSTGRES_MIGRATOR.run(pool).await?,
+            ConnectionPool::Sqlite(pool) => SQLITE_MIGRATOR.run(pool).await?,
+        }
+        Ok(())
+    }
+
+    /// Reverts the most recently applied migration, returning its version,
+    /// or `None` when none is applied. Refuses to when its down script is
+    /// missing or only has comments, since it would be marked as reverted
+    /// without undoing anything.
+    pub async fn revert_last_migration(&self) -> Result<Option<i64>, sqlx::Error> {
+        let mut applied = self.applied_migrations().await?;
+        applied.sort_unstable();
+        let Some(last) = applied.pop() else {
+            return Ok(None);
+        };
+        let target = applied.last().copied().unwrap_or(0);
+
+        let reversible = self.migrator().iter().any(|migration| {
+            migration.version == last
+                && migration.migration_type.is_down_migration()
+                && has_statements(&migration.sql)
+        });
+        if !reversible {
+            return Err(sqlx::Error::Configuration(
+                format!("migration {} has no down script to revert it with", last).into(),
+            ));
+        }
+
+        match self {
+            ConnectionPool::Postgres(pool) => POSTGRES_MIGRATOR.undo(pool, target).await?,
+            ConnectionPool::Sqlite(pool) => SQLITE_MIGRATOR.undo(pool, target).await?,
+        }
+        Ok(Some(last))
+    }
+
+    /// Every migration embedded in the binary, oldest first, and whether it
+    /// has been applied. Only reads, so a database never migrated reports
+    /// nothing applied rather than getting the bookkeeping table.
+    pub async fn migration_status(&self) -> Result<Vec<MigrationStatus>, sqlx::Error> {
+        const QUERY: &str =
+            "SELECT version, checksum, installed_on FROM _sqlx_migrations WHERE success";
+        let applied: Vec<AppliedMigration> = if !self.has_migrations_table().await? {
+            Vec::new()
+        } else {
+            match self {
+                ConnectionPool::Postgres(pool) => sqlx::query_as(QUERY).fetch_all(pool).await?,
+                ConnectionPool::Sqlite(pool) => sqlx::query_as(QUERY).fetch_all(pool).await?,
+            }
+        };
+
+        Ok(self
+            .migrator()
+            .iter()
+            .filter(|migration| !migration.migration_type.is_down_migration())
+            .map(|migration| {
+                let applied = applied.iter().find(|a| a.version == migration.version);
+                MigrationStatus {
+                    version: migration.version,
+                    description: migration.description.to_string(),
+                    checksum: hex(&migration.checksum),
+                    applied: applied.is_some(),
+                    applied_checksum: applied.map(|a| hex(&a.checksum)),
+                    installed_on: applied.map(|a| a.installed_on),
+                }
+            })
+            .collect())
+    }
+
+    /// The migrations embedded in the binary that have not been applied.
+    pub async fn pending_migrations(&self) -> Result<Vec<MigrationStatus>, sqlx::Error> {
+        let mut migrations = self.migration_status().await?;
+        migrations.retain(|migration| !migration.applied);
+        Ok(migrations)
+    }
+
+    /// Whether `_sqlx_migrations` exists, i.e. migrations were ever run.
+    async fn has_migrations_table(&self) -> Result<bool, sqlx::Error> {
+        match self {
+            ConnectionPool::Postgres(pool) => {
+                sqlx::query_scalar("SELECT to_regclass('_sqlx_migrations') IS NOT NULL")
+                    .fetch_one(pool)
+                    .await
+            }
+            ConnectionPool::Sqlite(pool) => {
+                sqlx::query_scalar(
+                    "SELECT EXISTS (SELECT 1 FROM sqlite_master \
+                     WHERE type = 'table' AND name = '_sqlx_migrations')",
+                )
+                .fetch_one(pool)
+                .await
+            }
+        }
+    }
+
+    /// Versions of the migrations applied successfully.
+    pub async fn applied_migrations(&self) -> Result<Vec<i64>, sqlx::Error> {
+        const QUERY: &str = "SELECT version FROM _sqlx_migrations WHERE success";
+        if !self.has_migrations_table().await? {
+            return Ok(Vec::new());
+        }
+        match self {
+            ConnectionPool::Postgres(pool) => sqlx::query_scalar(QUERY).fetch_all(pool).await,
+            ConnectionPool::Sqlite(pool) => sqlx::query_scalar(QUERY).fetch_all(pool).await,
+        }
+    }
+
+    /// Round-trips a trivial query.
+    pub async fn ping(&self) -> Result<(), sqlx::Error> {
+        match self {
+            ConnectionPool::Postgres(pool) => {
+                sqlx::query("SELECT 1").execute(pool).await.map(|_| ())
+            }
+            ConnectionPool::Sqlite(pool) => sqlx::query("SELECT 1").execute(pool).await.map(|_| ()),
+        }
+    }
+
+    pub async fn close(&self) {
+        match self {
+            ConnectionPool::Postgres(pool) => pool.close().await,
+            ConnectionPool::Sqlite(pool) => pool.close().await,
+        }
+    }
+}
+
+/// A migration embedded in the binary.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct MigrationStatus {
+    #[schema(example = 20230709151527_i64)]
+    pub version: i64,
+    pub description: String,
+    /// SHA-384 of the migration, hex encoded.
+    pub checksum: String,
+    pub applied: bool,
+    /// Checksum recorded when the migration was applied. Differs from
+    /// `checksum` if the migration was edited afterwards.
+    pub applied_checksum: Option<String>,
+    pub installed_on: Option<DateTime<Utc>>,
+}
+
+#[derive(FromRow)]
+struct AppliedMigration {
+    version: i64,
+    checksum: Vec<u8>,
+    installed_on: DateTime<Utc>,
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+/// Whether `sql` has anything besides whitespace, comments and `;`.
+fn has_statements(sql: &str) -> bool {
+    let mut rest = sql.trim_start();
+    while !rest.is_empty() {
+        if let Some(comment) = rest.strip_prefix("--") {
+            rest = comment.split_once('\n').map_or("", |(_, after)| after);
+        } else if let Some(comment) = rest.strip_prefix("/*") {
+            rest = comment.split_once("*/").map_or("", |(_, after)| after);
+        } else if let Some(after) = rest.strip_prefix(';') {
+            rest = after;
+        } else {
+            return true;
+        }
+        rest = rest.trim_start();
+    }
+    false
+}
+
+/// Upper bound for the delay between connect attempts.
+const MAX_CONNECT_BACKOFF: Duration = Duration::from_secs(30);
+
+/// Point-in-time view of the connection pool.
+#[derive(Debug, Clone, Copy, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct PoolStats {
+    /// Open connections, idle or in use.
+    pub size: u32,
+    pub idle: u32,
+    pub in_use: u32,
+    pub max_size: u32,
+}
 
 pub struct ConnectionManager;
 
 impl ConnectionManager {
-    pub async fn new_pool(
-        connection_string: &str,
-        run_migrations: bool,
-    ) -> Result<ConnectionPool, sqlx::Error> {
-        let pool = PgPoolOptions::new()
-            .max_connections(5)
-            .connect(connection_string)
-            .await?;
+    /// Builds the pool from `config`, retrying the first connection with
+    /// exponential backoff so startup tolerates a database that is still
+    /// coming up. The driver follows the scheme of `database.url`.
+    pub async fn new_pool(config: &DatabaseConfig) -> Result<ConnectionPool, sqlx::Error> {
+        let url = config.url.expose();
+        let pool = match DatabaseDriver::from_url(url) {
+            Some(DatabaseDriver::Postgres) => {
+                let connect_options = pg_connect_options(config, url)?;
+                ConnectionPool::Postgres(connect_with_retry(config, connect_options).await?)
+            }
+            Some(DatabaseDriver::Sqlite) => {
+                let connect_options = SqliteConnectOptions::from_str(url)?
+                    .create_if_missing(true)
+                    .journal_mode(SqliteJournalMode::Wal);
+                ConnectionPool::Sqlite(connect_with_retry(config, connect_options).await?)
+            }
+            None => {
+                return Err(sqlx::Error::Configuration(
+                    "database.url must be a postgres:// or sqlite: URL".into(),
+                ))
+            }
+        };
 
-        if run_migrations {
-            sqlx::migrate!().run(&pool).await?;
+        if config.run_migrations {
+            pool.run_migrations().await?;
         }
 
         Ok(pool)
     }
+
+    /// Pools for `database.replica_urls`, with the same settings as the
+    /// primary except for the shorter `replica_acquire_timeout_ms`. They
+    /// connect lazily: a replica that is down at startup is only taken out
+    /// of rotation, it does not stop the app from starting.
+    pub fn new_replicas(config: &DatabaseConfig) -> Result<Vec<PgPool>, sqlx::Error> {
+        config
+            .replica_urls
+            .iter()
+            .map(|url| {
+                let connect_options = pg_connect_options(config, url.expose())?;
+                Ok(pool_options(config)
+                    .acquire_timeout(Duration::from_millis(config.replica_acquire_timeout_ms))
+                    .connect_lazy_with(connect_options))
+            })
+            .collect()
+    }
+
+    pub fn stats(pool: &ConnectionPool) -> PoolStats {
+        match pool {
+            ConnectionPool::Postgres(pool) => pool_stats(pool),
+            ConnectionPool::Sqlite(pool) => pool_stats(pool),
+        }
+    }
+}
+
+fn pg_connect_options(config: &DatabaseConfig, url: &str) -> Result<PgConnectOptions, sqlx::Error> {
+    let mut connect_options =
+        PgConnectOptions::from_str(url)?.application_name(&config.application_name);
+    if let Some(timeout) = config.statement_timeout_ms {
+        connect_options = connect_options.options([("statement_timeout", timeout.to_string())]);
+    }
+    Ok(connect_options)
+}
+
+async fn connect_with_retry<DB: Database>(
+    config: &DatabaseConfig,
+    connect_options: <DB::Connection as sqlx::Connection>::Options,
+) -> Result<Pool<DB>, sqlx::Error> {
+    let mut backoff = Duration::from_millis(config.connect_backoff_ms);
+    let mut attempt = 0;
+    loop {
+        match pool_options(config)
+            .connect_with(connect_options.clone())
+            .await
+        {
+            Ok(pool) => return Ok(pool),
+            Err(err) if attempt < config.connect_retries && is_transient(&err) => {
+                attempt += 1;
+                log::warn!(
+                    "Database not reachable ({}), retry {}/{} in {:?}",
+                    err,
+                    attempt,
+                    config.connect_retries,
+                    backoff
+                );
+                actix_web::rt::time::sleep(backoff).await;
+                backoff = (backoff * 2).min(MAX_CONNECT_BACKOFF);
+            }
+            Err(err) => return Err(err),
+        }
+    }
+}
+
+fn pool_stats<DB: Database>(pool: &Pool<DB>) -> PoolStats {
+    let size = pool.size();
+    let idle = pool.num_idle() as u32;
+
+    PoolStats {
+        size,
+        idle,
+        in_use: size.saturating_sub(idle),
+        max_size: pool.options().get_max_connections(),
+    }
+}
+
+fn pool_options<DB: Database>(config: &DatabaseConfig) -> PoolOptions<DB> {
+    PoolOptions::new()
+        .min_connections(config.min_connections)
+        .max_connections(config.max_connections)
+        .acquire_timeout(Duration::from_secs(config.acquire_timeout_secs))
+        .idle_timeout(config.idle_timeout_secs.map(Duration::from_secs))
+        .max_lifetime(config.max_lifetime_secs.map(Duration::from_secs))
+}
+
+/// Errors worth retrying at startup: the server is unreachable, still
+/// starting up (57P03) or out of connection slots (53300).
+fn is_transient(err: &sqlx::Error) -> bool {
+    match err {
+        sqlx::Error::Io(_) | sqlx::Error::PoolTimedOut => true,
+        sqlx::Error::Database(db_err) => {
+            matches!(db_err.code().as_deref(), Some("57P03") | Some("53300"))
+        }
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repository::contract_tests::{with_postgres, with_sqlite};
+
+    /// Every table except the migration bookkeeping.
+    async fn tables(pool: &ConnectionPool) -> Vec<String> {
+        match pool {
+            ConnectionPool::Postgres(pool) => sqlx::query_scalar(
+                "SELECT tablename::text FROM pg_tables \
+                 WHERE schemaname = 'public' AND tablename <> '_sqlx_migrations'",
+            )
+            .fetch_all(pool)
+            .await
+            .unwrap(),
+            ConnectionPool::Sqlite(pool) => sqlx::query_scalar(
+                "SELECT name FROM sqlite_master WHERE type = 'table' \
+                 AND name <> '_sqlx_migrations' AND name NOT LIKE 'sqlite_%'",
+            )
+            .fetch_all(pool)
+            .await
+            .unwrap(),
+        }
+    }
+
+    async fn status_of_a_database_never_migrated(pool: ConnectionPool) {
+        match &pool {
+            ConnectionPool::Postgres(pool) => sqlx::query("DROP TABLE _sqlx_migrations")
+                .execute(pool)
+                .await
+                .map(drop),
+            ConnectionPool::Sqlite(pool) => sqlx::query("DROP TABLE _sqlx_migrations")
+                .execute(pool)
+                .await
+                .map(drop),
+        }
+        .unwrap();
+
+        let status = pool.migration_status().await.unwrap();
+        assert!(!status.is_empty());
+        assert!(status.iter().all(|migration| !migration.applied));
+        assert!(pool.applied_migrations().await.unwrap().is_empty());
+        assert_eq!(pool.revert_last_migration().await.unwrap(), None);
+        assert!(!pool.has_migrations_table().await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn postgres_status_of_a_database_never_migrated() {
+        with_postgres(|pool| status_of_a_database_never_migrated(ConnectionPool::Postgres(pool)))
+            .await;
+    }
+
+    #[tokio::test]
+    async fn sqlite_status_of_a_database_never_migrated() {
+        with_sqlite(|pool| status_of_a_database_never_migrated(ConnectionPool::Sqlite(pool))).await;
+    }
+
+    #[test]
+    fn down_scripts_need_statements() {
+        assert!(!has_statements(""));
+        assert!(!has_statements("  \n;\n"));
+        assert!(!has_statements("-- Irreversible\n/* nothing\n to do */\n"));
+        assert!(has_statements(
+            "-- Only the index\n\nDROP INDEX users_email_lower_key;"
+        ));
+        assert!(has_statements("/* first */ DROP TABLE notes"));
+    }
+
+    async fn revert_everything_and_reapply(pool: ConnectionPool) {
+        let status = pool.migration_status().await.unwrap();
+        assert!(status.iter().all(|migration| migration.applied
+            && migration.applied_checksum.as_ref() == Some(&migration.checksum)
+            && migration.installed_on.is_some()));
+        assert!(!tables(&pool).await.is_empty());
+
+        let mut reverted = Vec::new();
+        while let Some(version) = pool.revert_last_migration().await.unwrap() {
+            reverted.push(version);
+        }
+        let newest_first: Vec<i64> = status.iter().rev().map(|m| m.version).collect();
+        assert_eq!(reverted, newest_first);
+        assert_eq!(tables(&pool).await, Vec::<String>::new());
+        assert_eq!(pool.pending_migrations().await.unwrap().len(), status.len());
+
+        pool.run_migrations().await.unwrap();
+        assert!(pool.pending_migrations().await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn postgres_migrations_are_reversible() {
+        with_postgres(|pool| revert_everything_and_reapply(ConnectionPool::Postgres(pool))).await;
+    }
+
+    #[tokio::test]
+    async fn sqlite_migrations_are_reversible() {
+        with_sqlite(|pool| revert_everything_and_reapply(ConnectionPool::Sqlite(pool))).await;
+    }
+
+    async fn emails_differing_in_case_block_lowercasing(pool: ConnectionPool) {
+        const LOWERCASE_EMAILS_MIGRATION: i64 = 20261019120000;
+        const INSERT: &str = "INSERT INTO users (id, firstname, lastname, email, password) VALUES \
+             ('00000000-0000-0000-0000-000000000001', 'Ada', 'Lovelace', 'Ada@Example.com', 'x'), \
+             ('00000000-0000-0000-0000-000000000002', 'Ada', 'Lovelace', 'ada@example.com', 'x')";
+
+        while pool
+            .applied_migrations()
+            .await
+            .unwrap()
+            .contains(&LOWERCASE_EMAILS_MIGRATION)
+        {
+            pool.revert_last_migration().await.unwrap();
+        }
+        match &pool {
+            ConnectionPool::Postgres(pool) => sqlx::query(INSERT).execute(pool).await.map(drop),
+            ConnectionPool::Sqlite(pool) => sqlx::query(INSERT).execute(pool).await.map(drop),
+        }
+        .unwrap();
+
+        assert!(pool.run_migrations().await.is_err());
+        assert!(!pool
+            .applied_migrations()
+            .await
+            .unwrap()
+            .contains(&LOWERCASE_EMAILS_MIGRATION));
+    }
+
+    #[tokio::test]
+    async fn postgres_emails_differing_in_case_block_lowercasing() {
+        with_postgres(|pool| {
+            emails_differing_in_case_block_lowercasing(ConnectionPool::Postgres(pool))
+        })
+        .await;
+    }
+
+    #[tokio::test]
+    async fn sqlite_emails_differing_in_case_block_lowercasing() {
+        with_sqlite(|pool| {
+            emails_differing_in_case_block_lowercasing(ConnectionPool::Sqlite(pool))
+        })
+        .await;
+    }
 }
@@ -0,0 +1,130 @@
+use std::fmt;
+use std::net::IpAddr;
+use std::str::FromStr;
+
+use serde::{de::Error as _, Deserialize, Deserializer, Serialize, Serializer};
+
+/// An IP network in CIDR notation (`10.0.0.0/8`, `fd00::/8`). A bare address
+/// is a network holding just that address.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IpNetwork {
+    address: IpAddr,
+    prefix: u8,
+}
+
+impl IpNetwork {
+    pub fn contains(&self, ip: IpAddr) -> bool {
+        match (self.address, ip.to_canonical()) {
+            (IpAddr::V4(network), IpAddr::V4(ip)) => {
+                masked(u32::from(network).into(), self.prefix, 32)
+                    == masked(u32::from(ip).into(), self.prefix, 32)
+            }
+            (IpAddr::V6(network), IpAddr::V6(ip)) => {
+                masked(network.into(), self.prefix, 128) == masked(ip.into(), self.prefix, 128)
+            }
+            _ => false,
+        }
+    }
+}
+
+/// The leading `prefix` bits of a `width` bit address.
+fn masked(bits: u128, prefix: u8, width: u8) -> u128 {
+    bits.checked_shr(u32::from(width - prefix)).unwrap_or(0)
+}
+
+impl FromStr for IpNetwork {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let (address, prefix) = match value.split_once('/') {
+            Some((address, prefix)) => (address, Some(prefix)),
+            None => (value, None),
+        };
+        let address = IpAddr::from_str(address.trim())
+            .map_err(|_| format!("'{}' is not an IP address or CIDR range", value))?
+            .to_canonical();
+        let max_prefix = if address.is_ipv4() { 32 } else { 128 };
+        let prefix = match prefix {
+            Some(prefix) => prefix
+                .trim()
+                .parse::<u8>()
+                .ok()
+                .filter(|prefix| *prefix <= max_prefix)
+                .ok_or_else(|| format!("'{}' has an invalid prefix length", value))?,
+            None => max_prefix,
+        };
+
+        Ok(IpNetwork { address, prefix })
+    }
+}
+
+impl fmt::Display for IpNetwork {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.address, self.prefix)
+    }
+}
+
+impl Serialize for IpNetwork {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for IpNetwork {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let value = String::deserialize(deserializer)?;
+        value.parse().map_err(D::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn network(value: &str) -> IpNetwork {
+        value.parse().unwrap()
+    }
+
+    fn ip(value: &str) -> IpAddr {
+        value.parse().unwrap()
+    }
+
+    #[test]
+    fn parses_cidr_ranges_and_bare_addresses() {
+        assert_eq!(network("10.0.0.0/8").to_string(), "10.0.0.0/8");
+        assert_eq!(network(" 192.0.2.1 ").to_string(), "192.0.2.1/32");
+        assert_eq!(network("fd00::/8").to_string(), "fd00::/8");
+        assert_eq!(network("2001:db8::1").to_string(), "2001:db8::1/128");
+        assert_eq!(network("::ffff:10.0.0.0/8").to_string(), "10.0.0.0/8");
+
+        for invalid in [
+            "",
+            "10.0.0",
+            "10.0.0.0/33",
+            "fd00::/129",
+            "10.0.0.0/x",
+            "host/8",
+        ] {
+            assert!(invalid.parse::<IpNetwork>().is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn contains_addresses_in_the_prefix() {
+        assert!(network("10.0.0.0/8").contains(ip("10.255.0.1")));
+        assert!(!network("10.0.0.0/8").contains(ip("11.0.0.1")));
+        assert!(network("192.0.2.1").contains(ip("192.0.2.1")));
+        assert!(!network("192.0.2.1").contains(ip("192.0.2.2")));
+        assert!(network("0.0.0.0/0").contains(ip("203.0.113.9")));
+        assert!(network("fd00::/8").contains(ip("fd12::1")));
+        assert!(!network("fd00::/8").contains(ip("fe80::1")));
+        assert!(network("::/0").contains(ip("2001:db8::1")));
+    }
+
+    #[test]
+    fn ipv4_mapped_addresses_match_ipv4_networks_only() {
+        assert!(network("10.0.0.0/8").contains(ip("::ffff:10.1.2.3")));
+        assert!(!network("::/0").contains(ip("10.1.2.3")));
+        assert!(!network("0.0.0.0/0").contains(ip("2001:db8::1")));
+    }
+}
@@ -0,0 +1,447 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::path::Path;
+
+use ::config::{ConfigError, Environment, File, Map, Value};
+use actix_web::http::{header::HeaderName, Method};
+use serde::de::DeserializeOwned;
+use tracing_subscriber::EnvFilter;
+
+use super::{
+    Config, CorsConfig, DatabaseBackend, DatabaseDriver, RateLimitConfig, RateLimitStore,
+    TracingExporter,
+};
+
+const ENV_PREFIX: &str = "APP";
+const ENV_SEPARATOR: &str = "__";
+const DEFAULT_CONFIG_FILE: &str = "config";
+
+/// Environment variables from before the config was split into sections,
+/// still honoured so existing `.env` files keep working. `APP__*` variables
+/// take precedence over them.
+const LEGACY_ENV: [(&str, &str); 15] = [
+    ("DATABASE_URL", "database.url"),
+    ("RUN_MIGRATIONS", "database.run_migrations"),
+    ("JWT_SECRET", "auth.jwt_secret"),
+    ("JWT_MAXAGE", "auth.jwt_maxage"),
+    ("PORT", "server.port"),
+    ("ERROR_FORMAT", "server.error_format"),
+    ("API_DOCS_ENABLED", "server.api_docs_enabled"),
+    ("PASSWORD_MIN_LENGTH", "auth.password_policy.min_length"),
+    ("PASSWORD_MAX_LENGTH", "auth.password_policy.max_length"),
+    (
+        "PASSWORD_REQUIRE_LOWERCASE",
+        "auth.password_policy.require_lowercase",
+    ),
+    (
+        "PASSWORD_REQUIRE_UPPERCASE",
+        "auth.password_policy.require_uppercase",
+    ),
+    (
+        "PASSWORD_REQUIRE_DIGIT",
+        "auth.password_policy.require_digit",
+    ),
+    (
+        "PASSWORD_REQUIRE_SYMBOL",
+        "auth.password_policy.require_symbol",
+    ),
+    (
+        "PASSWORD_DISALLOW_PERSONAL_INFO",
+        "auth.password_policy.disallow_personal_info",
+    ),
+    (
+        "BREACHED_PASSWORDS_PATH",
+        "auth.password_policy.breached_passwords_path",
+    ),
+];
+
+/// Keys whose environment value is a comma separated list.
+const LIST_KEYS: [&str; 6] = [
+    "server.trusted_proxies",
+    "database.replica_urls",
+    "cors.allowed_origins",
+    "cors.allowed_methods",
+    "cors.allowed_headers",
+    "cors.exposed_headers",
+];
+
+/// Every problem found while loading the configuration.
+#[derive(Debug)]
+pub struct ConfigErrors(pub Vec<String>);
+
+impl fmt::Display for ConfigErrors {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "invalid configuration:")?;
+        for error in &self.0 {
+            writeln!(f, "  - {}", error)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for ConfigErrors {}
+
+impl Config {
+    /// Loads settings from, in increasing order of precedence: built-in
+    /// defaults, `file` (or `config.{toml,yaml}` in the working directory
+    /// when no file is given), the legacy environment variables and
+    /// `APP__SECTION__KEY` environment variables.
+    ///
+    /// Instead of stopping at the first problem, every section is read and
+    /// validated so all errors can be reported at once.
+    pub fn load(file: Option<&Path>) -> Result<Config, ConfigErrors> {
+        let file_source = match file {
+            Some(path) => File::from(path).required(true),
+            None => File::with_name(DEFAULT_CONFIG_FILE).required(false),
+        };
+
+        let mut app_env = Environment::with_prefix(ENV_PREFIX)
+            .separator(ENV_SEPARATOR)
+            .list_separator(",")
+            .try_parsing(true);
+        for key in LIST_KEYS {
+            app_env = app_env.with_list_parse_key(key);
+        }
+
+        let source = ::config::Config::builder()
+            .add_source(file_source)
+            .add_source(legacy_env())
+            .add_source(app_env)
+            .build()
+            .map_err(|err| ConfigErrors(vec![err.to_string()]))?;
+
+        let mut errors = Vec::new();
+        let config = Config {
+            server: section(&source, "server", &mut errors),
+            database: section(&source, "database", &mut errors),
+            auth: section(&source, "auth", &mut errors),
+            cors: section(&source, "cors", &mut errors),
+            rate_limit: section(&source, "rate_limit", &mut errors),
+            cache: section(&source, "cache", &mut errors),
+            logging: section(&source, "logging", &mut errors),
+            tracing: section(&source, "tracing", &mut errors),
+        };
+
+        errors.extend(config.validate());
+
+        if errors.is_empty() {
+            Ok(config)
+        } else {
+            Err(ConfigErrors(errors))
+        }
+    }
+
+    fn validate(&self) -> Vec<String> {
+        let mut errors = Vec::new();
+
+        let server = &self.server;
+        if server.host.trim().is_empty() {
+            errors.push("server.host must not be empty".to_string());
+        }
+        if server.workers == Some(0) {
+            errors.push("server.workers must be greater than 0".to_string());
+        }
+        if server.max_body_bytes == 0 {
+            errors.push("server.max_body_bytes must be greater than 0".to_string());
+        }
+        if server.tls.enabled {
+            if server.tls.cert_path.is_empty() {
+                errors.push("server.tls.cert_path must be set when TLS is enabled".to_string());
+            }
+            if server.tls.key_path.is_empty() {
+                errors.push("server.tls.key_path must be set when TLS is enabled".to_string());
+            }
+        }
+
+        if self.database.backend == DatabaseBackend::Sql {
+            let database_url = self.database.url.expose();
+            let driver = DatabaseDriver::from_url(database_url);
+            if database_url.is_empty() {
+                errors.push("database.url must be set".to_string());
+            } else if driver.is_none() {
+                errors.push("database.url must be a postgres:// or sqlite: URL".to_string());
+            }
+            if self.rate_limit.store == RateLimitStore::Postgres
+                && driver == Some(DatabaseDriver::Sqlite)
+            {
+                errors.push(
+                    "rate_limit.store = \"postgres\" needs a postgres:// database.url".to_string(),
+                );
+            }
+            if self.database.max_connections == 0 {
+                errors.push("database.max_connections must be greater than 0".to_string());
+            }
+            if self.database.min_connections > self.database.max_connections {
+                errors.push(
+                    "database.min_connections must not be greater than max_connections".to_string(),
+                );
+            }
+            if self.database.acquire_timeout_secs == 0 {
+                errors.push("database.acquire_timeout_secs must be greater than 0".to_string());
+            }
+            if !self.database.replica_urls.is_empty() {
+                if driver == Some(DatabaseDriver::Sqlite) {
+                    errors
+                        .push("database.replica_urls needs a postgres:// database.url".to_string());
+                }
+                let invalid = self.database.replica_urls.iter().any(|url| {
+                    DatabaseDriver::from_url(url.expose()) != Some(DatabaseDriver::Postgres)
+                });
+                if invalid {
+                    errors.push("database.replica_urls must be postgres:// URLs".to_string());
+                }
+                if self.database.replica_check_interval_secs == 0 {
+                    errors.push(
+                        "database.replica_check_interval_secs must be greater than 0".to_string(),
+                    );
+                }
+                if self.database.replica_acquire_timeout_ms == 0 {
+                    errors.push(
+                        "database.replica_acquire_timeout_ms must be greater than 0".to_string(),
+                    );
+                }
+            }
+        } else if self.rate_limit.store == RateLimitStore::Postgres {
+            errors.push(
+                "rate_limit.store = \"postgres\" needs database.backend = \"sql\"".to_string(),
+            );
+        }
+
+        if self.auth.jwt_secret.expose().is_empty() {
+            errors.push("auth.jwt_secret must be set".to_string());
+        }
+        if self.auth.jwt_maxage <= 0 {
+            errors.push("auth.jwt_maxage must be greater than 0".to_string());
+        }
+
+        let policy = &self.auth.password_policy;
+        if policy.min_length == 0 {
+            errors.push("auth.password_policy.min_length must be greater than 0".to_string());
+        }
+        if policy.min_length > policy.max_length {
+            errors.push(
+                "auth.password_policy.min_length must not be greater than max_length".to_string(),
+            );
+        }
+
+        errors.extend(self.cors.validate());
+        errors.extend(self.rate_limit.validate());
+
+        if self.cache.enabled {
+            if self.cache.capacity == 0 {
+                errors.push("cache.capacity must be greater than 0".to_string());
+            }
+            if self.cache.ttl_secs == 0 {
+                errors.push("cache.ttl_secs must be greater than 0".to_string());
+            }
+        }
+
+        if self.logging.level.trim().is_empty() {
+            errors.push("logging.level must not be empty".to_string());
+        } else if let Err(err) = EnvFilter::try_new(&self.logging.level) {
+            errors.push(format!("logging.level: {}", err));
+        }
+
+        let tracing = &self.tracing;
+        if !(0.0..=1.0).contains(&tracing.sample_ratio) {
+            errors.push("tracing.sample_ratio must be between 0 and 1".to_string());
+        }
+        if tracing.exporter == TracingExporter::Otlp
+            && !tracing.otlp_endpoint.starts_with("http://")
+            && !tracing.otlp_endpoint.starts_with("https://")
+        {
+            errors.push("tracing.otlp_endpoint must start with http:// or https://".to_string());
+        }
+
+        errors
+    }
+}
+
+impl CorsConfig {
+    fn validate(&self) -> Vec<String> {
+        let mut errors = Vec::new();
+
+        for origin in &self.allowed_origins {
+            if origin == "*" {
+                if self.allow_credentials {
+                    errors.push(
+                        "cors.allowed_origins: '*' cannot be combined with allow_credentials"
+                            .to_string(),
+                    );
+                }
+                continue;
+            }
+
+            let Some(host) = origin
+                .strip_prefix("http://")
+                .or_else(|| origin.strip_prefix("https://"))
+            else {
+                errors.push(format!(
+                    "cors.allowed_origins: '{}' must start with http:// or https://",
+                    origin
+                ));
+                continue;
+            };
+            let host = host.strip_prefix("*.").unwrap_or(host);
+            if host.is_empty() || host.contains(['*', '/']) {
+                errors.push(format!(
+                    "cors.allowed_origins: '{}' must be an origin, optionally with a leading '*.' \
+                     subdomain wildcard",
+                    origin
+                ));
+            }
+        }
+
+        for method in &self.allowed_methods {
+            if Method::from_bytes(method.as_bytes()).is_err() {
+                errors.push(format!(
+                    "cors.allowed_methods: '{}' is not an HTTP method",
+                    method
+                ));
+            }
+        }
+
+        let headers = [
+            ("allowed_headers", &self.allowed_headers),
+            ("exposed_headers", &self.exposed_headers),
+        ];
+        for (key, values) in headers {
+            for header in values {
+                if (key == "allowed_headers" && header == "*")
+                    || header.parse::<HeaderName>().is_ok()
+                {
+                    continue;
+                }
+                errors.push(format!(
+                    "cors.{}: '{}' is not a valid header name",
+                    key, header
+                ));
+            }
+        }
+
+        errors
+    }
+}
+
+impl RateLimitConfig {
+    fn validate(&self) -> Vec<String> {
+        let mut errors = Vec::new();
+
+        if self.api_key_header.parse::<HeaderName>().is_err() {
+            errors.push(format!(
+                "rate_limit.api_key_header: '{}' is not a valid header name",
+                self.api_key_header
+            ));
+        }
+
+        let mut names = HashSet::new();
+        for (index, policy) in self.policies.iter().enumerate() {
+            let prefix = format!("rate_limit.policies[{}]", index);
+
+            if policy.name.trim().is_empty() {
+                errors.push(format!("{}.name must not be empty", prefix));
+            } else if !names.insert(policy.name.as_str()) {
+                errors.push(format!(
+                    "{}.name: '{}' is used by another policy",
+                    prefix, policy.name
+                ));
+            }
+            if !policy.path_prefix.starts_with('/') {
+                errors.push(format!("{}.path_prefix must start with /", prefix));
+            }
+            for method in &policy.methods {
+                if Method::from_bytes(method.as_bytes()).is_err() {
+                    errors.push(format!(
+                        "{}.methods: '{}' is not an HTTP method",
+                        prefix, method
+                    ));
+                }
+            }
+            if policy.requests == 0 {
+                errors.push(format!("{}.requests must be greater than 0", prefix));
+            }
+            if policy.per_secs == 0 {
+                errors.push(format!("{}.per_secs must be greater than 0", prefix));
+            }
+        }
+
+        errors
+    }
+}
+
+fn legacy_env() -> Environment {
+    let values: HashMap<String, String> = LEGACY_ENV
+        .iter()
+        .filter_map(|(name, key)| {
+            let value = std::env::var(name).ok()?;
+            Some((key.replace('.', ENV_SEPARATOR), value))
+        })
+        .collect();
+
+    Environment::default()
+        .separator(ENV_SEPARATOR)
+        .source(Some(values))
+}
+
+/// Deserializes one section, falling back to its defaults (and recording the
+/// errors) when it is malformed.
+fn section<T>(source: &::config::Config, key: &str, errors: &mut Vec<String>) -> T
+where
+    T: DeserializeOwned + Default,
+{
+    match source.get::<T>(key) {
+        Ok(value) => value,
+        Err(ConfigError::NotFound(_)) => T::default(),
+        Err(err) => {
+            // serde stops at the first bad field, so check each key on its
+            // own (every section has defaults) to report all of them.
+            let before = errors.len();
+            if let Ok(table) = source.get_table(key) {
+                field_errors::<T>(key, None, table, errors);
+            }
+            if errors.len() == before {
+                errors.push(format!("{}: {}", key, err));
+            }
+            T::default()
+        }
+    }
+}
+
+fn field_errors<T>(
+    section: &str,
+    parent: Option<&str>,
+    table: Map<String, Value>,
+    errors: &mut Vec<String>,
+) where
+    T: DeserializeOwned,
+{
+    for (name, value) in table {
+        let path = match parent {
+            Some(parent) => format!("{}.{}", parent, name),
+            None => name,
+        };
+
+        if let Ok(nested) = value.clone().into_table() {
+            field_errors::<T>(section, Some(&path), nested, errors);
+            continue;
+        }
+
+        let result = ::config::Config::builder()
+            .set_override(path.as_str(), value)
+            .and_then(|builder| builder.build())
+            .and_then(|config| config.try_deserialize::<T>());
+
+        if let Err(err) = result {
+            errors.push(format!(
+                "{}.{}: {}",
+                section,
+                path,
+                strip_key_suffix(&err.to_string())
+            ));
+        }
+    }
+}
+
+fn strip_key_suffix(message: &str) -> &str {
+    message.split(" for key ").next().unwrap_or(message)
+}
@@ -1,5 +1,17 @@
+#[allow(clippy::module_inception)]
 mod config;
 mod connection_pool;
+mod ip_network;
+mod loader;
+mod secret;
 
-pub use config::Config;
-pub use connection_pool::{ConnectionManager, ConnectionPool};
+pub use config::{
+    CacheConfig, Config, CorsConfig, DatabaseBackend, ErrorFormat, LogFormat, LoggingConfig,
+    PasswordPolicyConfig, RateLimitConfig, RateLimitKey, RateLimitPolicyConfig, RateLimitStore,
+    TlsConfig, TracingConfig, TracingExporter,
+};
+pub use connection_pool::{
+    ConnectionManager, ConnectionPool, DatabaseDriver, MigrationStatus, PoolStats,
+};
+pub use ip_network::IpNetwork;
+pub use secret::Secret;
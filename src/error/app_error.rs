@@ -0,0 +1,178 @@
+use std::collections::BTreeMap;
+
+use actix_web::{http::StatusCode, HttpResponse, ResponseError};
+
+use crate::response::{ProblemDetails, PROBLEM_JSON};
+
+/// Validation messages keyed by the name of the offending field.
+pub type FieldErrors = BTreeMap<String, Vec<String>>;
+
+#[derive(Debug, thiserror::Error)]
+pub enum AppError {
+    #[error("{0}")]
+    NotFound(String),
+    #[error("{0}")]
+    Conflict(String),
+    #[error("{message}")]
+    Validation {
+        message: String,
+        errors: FieldErrors,
+    },
+    #[error("{0}")]
+    BadRequest(String),
+    #[error("{0}")]
+    Unauthorized(String),
+    #[error("{0}")]
+    Forbidden(String),
+    #[error("{0}")]
+    TooManyRequests(String),
+    #[error("database error: {0}")]
+    Database(sqlx::Error),
+    #[error("internal error: {0}")]
+    Internal(String),
+}
+
+impl AppError {
+    pub fn validation(field: &str, messages: Vec<String>) -> Self {
+        AppError::Validation {
+            message: "Validation failed".to_string(),
+            errors: FieldErrors::from([(field.to_string(), messages)]),
+        }
+    }
+
+    pub fn internal(err: impl std::fmt::Display) -> Self {
+        AppError::Internal(err.to_string())
+    }
+}
+
+impl From<sqlx::Error> for AppError {
+    fn from(err: sqlx::Error) -> Self {
+        let db_err = match &err {
+            sqlx::Error::RowNotFound => {
+                return AppError::NotFound("Resource not found".to_string())
+            }
+            sqlx::Error::Database(db_err) => db_err,
+            _ => return AppError::Database(err),
+        };
+
+        // SQLSTATE codes, see https://www.postgresql.org/docs/current/errcodes-appendix.html
+        match db_err.code().as_deref() {
+            Some("23505") => AppError::Conflict(conflict_message(db_err.constraint())),
+            Some("23503") => AppError::Conflict("Referenced resource does not exist".to_string()),
+            Some("22001") => AppError::BadRequest("Value too long for field".to_string()),
+            Some("23502") => AppError::BadRequest("Missing required field".to_string()),
+            Some("40001") => {
+                AppError::Conflict("Changed concurrently by another request, retry".to_string())
+            }
+            _ => AppError::Database(err),
+        }
+    }
+}
+
+impl From<validator::ValidationErrors> for AppError {
+    fn from(err: validator::ValidationErrors) -> Self {
+        let errors = err
+            .field_errors()
+            .into_iter()
+            .map(|(field, errors)| {
+                let messages = errors
+                    .iter()
+                    .map(|error| match &error.message {
+                        Some(message) => message.to_string(),
+                        None => format!("Invalid value ({})", error.code),
+                    })
+                    .collect();
+                (camel_case(field), messages)
+            })
+            .collect();
+
+        AppError::Validation {
+            message: "Validation failed".to_string(),
+            errors,
+        }
+    }
+}
+
+// Field names are reported the way clients send them in JSON bodies.
+fn camel_case(field: &str) -> String {
+    let mut parts = field.split('_');
+    let first = parts.next().unwrap_or_default().to_string();
+
+    parts.fold(first, |mut name, part| {
+        let mut chars = part.chars();
+        if let Some(c) = chars.next() {
+            name.extend(c.to_uppercase());
+            name.push_str(chars.as_str());
+        }
+        name
+    })
+}
+
+fn conflict_message(constraint: Option<&str>) -> String {
+    match constraint {
+        Some("notes_title_key") => "Note with that title already exists",
+        Some("users_email_key" | "users_email_lower_key") => "User with that email already exists",
+        _ => "Resource already exists",
+    }
+    .to_string()
+}
+
+impl ResponseError for AppError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            AppError::NotFound(_) => StatusCode::NOT_FOUND,
+            AppError::Conflict(_) => StatusCode::CONFLICT,
+            AppError::Validation { .. } => StatusCode::UNPROCESSABLE_ENTITY,
+            AppError::BadRequest(_) => StatusCode::BAD_REQUEST,
+            AppError::Unauthorized(_) => StatusCode::UNAUTHORIZED,
+            AppError::Forbidden(_) => StatusCode::FORBIDDEN,
+            AppError::TooManyRequests(_) => StatusCode::TOO_MANY_REQUESTS,
+            AppError::Database(_) | AppError::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        if let AppError::Database(_) | AppError::Internal(_) = self {
+            log::error!("{}", self);
+        }
+
+        HttpResponse::build(self.status_code())
+            .content_type(PROBLEM_JSON)
+            .json(self.problem())
+    }
+}
+
+impl AppError {
+    /// Problem details for this error. `instance` and `requestId` are filled
+    /// in later by the error handling middleware, which has the request.
+    pub fn problem(&self) -> ProblemDetails {
+        let status = self.status_code();
+
+        match self {
+            AppError::NotFound(message) => {
+                ProblemDetails::new(status, message).with_type("not-found")
+            }
+            AppError::Conflict(message) => {
+                ProblemDetails::new(status, message).with_type("conflict")
+            }
+            AppError::Validation { message, errors } => ProblemDetails::new(status, message)
+                .with_type("validation-error")
+                .with_errors(errors.clone()),
+            AppError::BadRequest(message) => {
+                ProblemDetails::new(status, message).with_type("bad-request")
+            }
+            AppError::Unauthorized(message) => {
+                ProblemDetails::new(status, message).with_type("unauthorized")
+            }
+            AppError::Forbidden(message) => {
+                ProblemDetails::new(status, message).with_type("forbidden")
+            }
+            AppError::TooManyRequests(message) => {
+                ProblemDetails::new(status, message).with_type("rate-limited")
+            }
+            AppError::Database(_) | AppError::Internal(_) => {
+                ProblemDetails::new(status, "Internal server error").with_type("internal-error")
+            }
+        }
+    }
+}
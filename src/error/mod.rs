@@ -0,0 +1,3 @@
+mod app_error;
+
+pub use app_error::{AppError, FieldErrors};
@@ -0,0 +1,89 @@
+use actix_web::{get, web, HttpResponse};
+use tracing::instrument;
+
+use crate::{
+    error::AppError, middleware::AdminUser, response::MigrationListResponse,
+    service_register::ServiceRegister,
+};
+
+#[utoipa::path(
+    get,
+    path = "/api/admin/migrations",
+    tag = "admin",
+    responses(
+        (status = 200, description = "Migrations of this version of the app, oldest first. Empty with the memory backend", body = MigrationListResponse),
+        (status = 401, description = "Not logged in", body = ProblemDetails),
+        (status = 403, description = "Not an admin", body = ProblemDetails)
+    ),
+    security(("bearer_auth" = []), ("cookie_auth" = []))
+)]
+#[get("/admin/migrations")]
+#[instrument(skip_all)]
+async fn migrations_handler(
+    _: AdminUser,
+    data: web::Data<ServiceRegister>,
+) -> Result<HttpResponse, AppError> {
+    let migrations = match &data.pool {
+        Some(pool) => pool.migration_status().await?,
+        None => Vec::new(),
+    };
+
+    Ok(HttpResponse::Ok().json(MigrationListResponse::success(migrations)))
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::http::{header, StatusCode};
+    use actix_web::test::{self, TestRequest};
+    use serde_json::{json, Value};
+
+    use crate::handler::test_support::{init_app, register};
+
+    const PASSWORD: &str = "Tr0ubadour-Horse";
+
+    fn migrations(token: &str) -> TestRequest {
+        TestRequest::get()
+            .uri("/api/admin/migrations")
+            .insert_header((header::AUTHORIZATION, format!("Bearer {}", token)))
+    }
+
+    #[actix_web::test]
+    async fn only_enabled_admins_list_migrations() {
+        let register = register(|_| {});
+        let app = init_app!(register.clone());
+
+        let req = TestRequest::post()
+            .uri("/api/auth/register")
+            .set_json(json!({
+                "firstname": "Ada",
+                "lastname": "Lovelace",
+                "email": "ada@example.com",
+                "password": PASSWORD,
+            }))
+            .to_request();
+        test::call_service(&app, req).await;
+        let req = TestRequest::post()
+            .uri("/api/auth/login")
+            .set_json(json!({ "email": "ada@example.com", "password": PASSWORD }))
+            .to_request();
+        let body: Value = test::call_and_read_body_json(&app, req).await;
+        let token = body["token"].as_str().unwrap().to_string();
+
+        let req = TestRequest::get().uri("/api/admin/migrations").to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+        let res = test::call_service(&app, migrations(&token).to_request()).await;
+        assert_eq!(res.status(), StatusCode::FORBIDDEN);
+
+        let users = &register.user_service;
+        users.update_role("ada@example.com", "admin").await.unwrap();
+        let res = test::call_service(&app, migrations(&token).to_request()).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        let body: Value = test::read_body_json(res).await;
+        assert_eq!(body["results"], 0);
+
+        users.set_disabled("ada@example.com", true).await.unwrap();
+        let res = test::call_service(&app, migrations(&token).to_request()).await;
+        assert_eq!(res.status(), StatusCode::FORBIDDEN);
+    }
+}
@@ -1,111 +1,98 @@
+use crate::{
+    error::AppError,
+    middleware::{JwtMiddleware, ValidatedJson},
+    response::{StatusResponse, TokenResponse, UserDataResponse},
+    schema::{ChangePasswordSchema, LoginUserSchema, RegisterUserSchema, TokenClaims},
+    security::{hash_password, verify_password},
+    service_register::ServiceRegister,
+};
 use actix_web::{
     cookie::{time::Duration as ActixWebDuration, Cookie},
-    get, post, web, HttpMessage, HttpRequest, HttpResponse, Responder,
-};
-use argon2::{
-    password_hash::{rand_core::OsRng, PasswordHash, PasswordHasher, PasswordVerifier, SaltString},
-    Argon2,
+    get, patch, post, web, HttpResponse, Responder,
 };
 use chrono::{prelude::*, Duration};
 use jsonwebtoken::{encode, EncodingKey, Header};
-use serde_json::json;
-
-use crate::{
-    middleware::JwtMiddleware,
-    response::UserSchema,
-    schema::{LoginUserSchema, RegisterUserSchema, TokenClaims},
-    service_register::ServiceRegister,
-};
+use tracing::instrument;
 
+#[utoipa::path(
+    post,
+    path = "/api/auth/register",
+    tag = "auth",
+    request_body = RegisterUserSchema,
+    responses(
+        (status = 200, description = "User registered", body = UserDataResponse),
+        (status = 409, description = "A user with that email already exists", body = ProblemDetails),
+        (status = 422, description = "Invalid request body or weak password", body = ProblemDetails)
+    )
+)]
 #[post("/auth/register")]
+#[instrument(skip_all)]
 async fn register_user_handler(
-    body: web::Json<RegisterUserSchema>,
+    body: ValidatedJson<RegisterUserSchema>,
     data: web::Data<ServiceRegister>,
-) -> impl Responder {
-    let exists = data
-        .user_service
-        .find_by_email_exists(&body.email.to_owned())
-        .await;
+) -> Result<HttpResponse, AppError> {
+    data.password_policy
+        .validate(
+            &body.password,
+            &[&body.email, &body.firstname, &body.lastname],
+        )
+        .map_err(|errors| AppError::validation("password", errors))?;
 
-    if let Err(_err) = exists {
-        return HttpResponse::Conflict().json(
-            serde_json::json!({"status": "fail","message": "User with that email already exists"}),
-        );
+    if data.user_service.find_by_email_exists(&body.email).await? {
+        return Err(AppError::Conflict(
+            "User with that email already exists".to_string(),
+        ));
     }
 
-    let salt = SaltString::generate(&mut OsRng);
-    let hashed_password = Argon2::default()
-        .hash_password(body.password.as_bytes(), &salt)
-        .expect("Error while hashing password")
-        .to_string();
+    let hashed_password = hash_password(&body.password)?;
 
-    let query_result = data
+    let user = data
         .user_service
         .create_user(
-            &body.firstname.to_owned(),
-            &body.lastname.to_owned(),
-            &body.email.to_owned(),
+            &body.firstname,
+            &body.lastname,
+            &body.email,
             &hashed_password,
         )
-        .await;
+        .await?;
 
-    match query_result {
-        Ok(user) => {
-            let user_response = serde_json::json!({"status": "success","data": serde_json::json!({
-                "user": UserSchema::from(user)
-            })});
-
-            return HttpResponse::Ok().json(user_response);
-        }
-        Err(e) => {
-            return HttpResponse::InternalServerError()
-                .json(serde_json::json!({"status": "error","message": format!("{:?}", e)}));
-        }
-    }
+    Ok(HttpResponse::Ok().json(UserDataResponse::success(user)))
 }
 
+#[utoipa::path(
+    post,
+    path = "/api/auth/login",
+    tag = "auth",
+    request_body = LoginUserSchema,
+    responses(
+        (status = 200, description = "Logged in, token also set as a cookie", body = TokenResponse),
+        (status = 400, description = "Invalid email or password", body = ProblemDetails),
+        (status = 403, description = "The account is disabled", body = ProblemDetails),
+        (status = 422, description = "Invalid request body", body = ProblemDetails)
+    )
+)]
 #[post("/auth/login")]
+#[instrument(skip_all)]
 async fn login_user_handler(
-    body: web::Json<LoginUserSchema>,
+    body: ValidatedJson<LoginUserSchema>,
     data: web::Data<ServiceRegister>,
-) -> impl Responder {
-    let query_result = data
-        .user_service
-        .find_user_by_email(&body.email.to_owned())
-        .await;
+) -> Result<HttpResponse, AppError> {
+    let user = data.user_service.find_user_by_email(&body.email).await?;
 
-    let user = match query_result {
-        Ok(Some(user)) => user,
-        Ok(None) => {
-            return HttpResponse::BadRequest().json(json!({
-                "status": "fail",
-                "message": "Invalid email or password"
-            }));
-        }
-        Err(err) => {
-            return HttpResponse::InternalServerError().json(json!({
-                "status": "error",
-                "message": format!("Error retrieving user: {}", err)
-            }));
+    let user = match user {
+        Some(user) if verify_password(&body.password, &user.password)? => user,
+        _ => {
+            data.metrics.record_login(false);
+            return Err(AppError::BadRequest(
+                "Invalid email or password".to_string(),
+            ));
         }
     };
-
-    let parsed_hash = PasswordHash::new(&user.password).unwrap();
-    let is_valid = Argon2::default()
-        .verify_password(body.password.as_bytes(), &parsed_hash)
-        .map_err(|err| {
-            HttpResponse::InternalServerError().json(json!({
-                "status": "error",
-                "message": format!("Error verifying password: {}", err)
-            }))
-        });
-
-    if let Err(err) = is_valid {
-        return HttpResponse::Conflict().json(serde_json::json!({
-            "status": "fail",
-            "message": format!("Invalid email or password: {:?}", err)
-        }));
+    if user.disabled_at.is_some() {
+        data.metrics.record_login(false);
+        return Err(AppError::Forbidden("This account is disabled".to_string()));
     }
+    data.metrics.record_login(true);
 
     let now = Utc::now();
     let iat = now.timestamp() as usize;
@@ -119,9 +106,9 @@ async fn login_user_handler(
     let token = encode(
         &Header::default(),
         &claims,
-        &EncodingKey::from_secret(data.env.jwt_secret.as_ref()),
+        &EncodingKey::from_secret(data.env.auth.jwt_secret.expose().as_ref()),
     )
-    .unwrap();
+    .map_err(AppError::internal)?;
 
     let cookie = Cookie::build("token", token.to_owned())
         .path("/")
@@ -129,12 +116,24 @@ async fn login_user_handler(
         .http_only(true)
         .finish();
 
-    HttpResponse::Ok()
+    Ok(HttpResponse::Ok()
         .cookie(cookie)
-        .json(json!({"status": "success", "token": token}))
+        .json(TokenResponse::success(token)))
 }
 
+#[utoipa::path(
+    get,
+    path = "/api/auth/logout",
+    tag = "auth",
+    responses(
+        (status = 200, description = "Logged out, token cookie cleared", body = StatusResponse),
+        (status = 401, description = "Not logged in", body = ProblemDetails),
+        (status = 403, description = "The account is disabled", body = ProblemDetails)
+    ),
+    security(("bearer_auth" = []), ("cookie_auth" = []))
+)]
 #[get("/auth/logout")]
+#[instrument(skip_all)]
 async fn logout_handler(_: JwtMiddleware) -> impl Responder {
     let cookie = Cookie::build("token", "")
         .path("/")
@@ -144,48 +143,397 @@ async fn logout_handler(_: JwtMiddleware) -> impl Responder {
 
     HttpResponse::Ok()
         .cookie(cookie)
-        .json(json!({"status": "success"}))
+        .json(StatusResponse::success())
 }
 
+#[utoipa::path(
+    get,
+    path = "/api/users/me",
+    tag = "users",
+    responses(
+        (status = 200, description = "The logged in user", body = UserDataResponse),
+        (status = 401, description = "Not logged in", body = ProblemDetails),
+        (status = 403, description = "The account is disabled", body = ProblemDetails)
+    ),
+    security(("bearer_auth" = []), ("cookie_auth" = []))
+)]
 #[get("/users/me")]
+#[instrument(skip_all)]
 async fn get_me_handler(
-    req: HttpRequest,
     data: web::Data<ServiceRegister>,
-    _: JwtMiddleware,
-) -> impl Responder {
-    let ext = req.extensions();
-    let user_id = ext.get::<uuid::Uuid>().unwrap();
+    jwt: JwtMiddleware,
+) -> Result<HttpResponse, AppError> {
+    let user = data
+        .user_service
+        .find_by_id(jwt.user_id)
+        .await?
+        .ok_or_else(|| AppError::NotFound("User not found".to_string()))?;
+
+    Ok(HttpResponse::Ok().json(UserDataResponse::success(user)))
+}
+
+#[utoipa::path(
+    patch,
+    path = "/api/users/me/password",
+    tag = "users",
+    request_body = ChangePasswordSchema,
+    responses(
+        (status = 200, description = "Password changed", body = StatusResponse),
+        (status = 400, description = "Current password is incorrect", body = ProblemDetails),
+        (status = 401, description = "Not logged in", body = ProblemDetails),
+        (status = 403, description = "The account is disabled", body = ProblemDetails),
+        (status = 422, description = "New password does not meet the policy", body = ProblemDetails)
+    ),
+    security(("bearer_auth" = []), ("cookie_auth" = []))
+)]
+#[patch("/users/me/password")]
+#[instrument(skip_all)]
+async fn change_password_handler(
+    body: ValidatedJson<ChangePasswordSchema>,
+    data: web::Data<ServiceRegister>,
+    jwt: JwtMiddleware,
+) -> Result<HttpResponse, AppError> {
+    let user_not_found = || AppError::NotFound("User not found".to_string());
+
+    let user = data
+        .user_service
+        .find_user_by_id(jwt.user_id)
+        .await?
+        .ok_or_else(user_not_found)?;
+
+    if !verify_password(&body.current_password, &user.password)? {
+        return Err(AppError::BadRequest(
+            "Current password is incorrect".to_string(),
+        ));
+    }
+
+    data.password_policy
+        .validate(
+            &body.new_password,
+            &[&user.email, &user.firstname, &user.lastname],
+        )
+        .map_err(|errors| AppError::validation("newPassword", errors))?;
+
+    let hashed_password = hash_password(&body.new_password)?;
+
+    data.user_service
+        .update_user(
+            &user.email,
+            &user.firstname,
+            &user.lastname,
+            &hashed_password,
+        )
+        .await?;
+
+    Ok(HttpResponse::Ok().json(StatusResponse::success()))
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::http::{header, StatusCode};
+    use actix_web::test::{self, TestRequest};
+    use serde_json::{json, Value};
+
+    use crate::config::ConnectionPool;
+    use crate::handler::test_support::{init_app, register, sql_register};
+    use crate::repository::contract_tests::{with_postgres, with_sqlite};
+    use crate::security::hash_password;
+    use crate::service_register::ServiceRegister;
+
+    const PASSWORD: &str = "Tr0ubadour-Horse";
+
+    fn register_user(email: &str, password: &str) -> TestRequest {
+        TestRequest::post()
+            .uri("/api/auth/register")
+            .set_json(json!({
+                "firstname": "Ada",
+                "lastname": "Lovelace",
+                "email": email,
+                "password": password,
+            }))
+    }
+
+    fn login(email: &str, password: &str) -> TestRequest {
+        TestRequest::post()
+            .uri("/api/auth/login")
+            .set_json(json!({ "email": email, "password": password }))
+    }
+
+    #[actix_web::test]
+    async fn registers_logs_in_and_reads_the_current_user() {
+        let app = init_app!(register(|_| {}));
+
+        let req = register_user("Ada@Example.com", PASSWORD).to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        let body: Value = test::read_body_json(res).await;
+        assert_eq!(body["data"]["user"]["email"], "ada@example.com");
+        assert!(body["data"]["user"].get("password").is_none());
 
-    let user_result = data.user_service.find_by_id(*user_id).await;
+        let res = test::call_service(&app, login("ada@example.com", PASSWORD).to_request()).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        let cookie = res.response().cookies().find(|c| c.name() == "token");
+        assert!(cookie.is_some_and(|cookie| cookie.http_only() == Some(true)));
+        let body: Value = test::read_body_json(res).await;
+        let token = body["token"].as_str().unwrap().to_string();
 
-    match user_result {
-        Ok(Some(user)) => {
-            let filtered_user = UserSchema::from(user);
+        let req = TestRequest::get()
+            .uri("/api/users/me")
+            .insert_header((header::AUTHORIZATION, format!("Bearer {}", token)))
+            .to_request();
+        let body: Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(body["data"]["user"]["firstname"], "Ada");
 
-            let json_response = serde_json::json!({
-                "status": "success",
-                "data": serde_json::json!({
-                    "user": filtered_user
-                })
-            });
+        let req = TestRequest::get().uri("/api/users/me").to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
 
-            HttpResponse::Ok().json(json_response)
+        let req = TestRequest::get()
+            .uri("/api/auth/logout")
+            .insert_header((header::AUTHORIZATION, format!("Bearer {}", token)))
+            .to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        let cookie = res.response().cookies().find(|c| c.name() == "token");
+        assert!(cookie.is_some_and(|cookie| cookie.value().is_empty()));
+    }
+
+    #[actix_web::test]
+    async fn rejects_a_duplicate_email_and_a_weak_password() {
+        let app = init_app!(register(|_| {}));
+
+        let res = test::call_service(
+            &app,
+            register_user("ada@example.com", PASSWORD).to_request(),
+        )
+        .await;
+        assert_eq!(res.status(), StatusCode::OK);
+
+        let res = test::call_service(
+            &app,
+            register_user("ada@example.com", PASSWORD).to_request(),
+        )
+        .await;
+        assert_eq!(res.status(), StatusCode::CONFLICT);
+
+        let req = register_user("grace@example.com", "short").to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::UNPROCESSABLE_ENTITY);
+        let body: Value = test::read_body_json(res).await;
+        assert!(body["errors"]["password"].is_array());
+    }
+
+    #[actix_web::test]
+    async fn rejects_wrong_credentials() {
+        let app = init_app!(register(|_| {}));
+
+        let req = register_user("ada@example.com", PASSWORD).to_request();
+        test::call_service(&app, req).await;
+
+        let req = login("ada@example.com", "Wr0ng-Password").to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+
+        let req = login("nobody@example.com", PASSWORD).to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[actix_web::test]
+    async fn disabled_users_cannot_log_in() {
+        let register = register(|_| {});
+        let app = init_app!(register.clone());
+
+        let req = register_user("ada@example.com", PASSWORD).to_request();
+        test::call_service(&app, req).await;
+        register
+            .user_service
+            .set_disabled("ada@example.com", true)
+            .await
+            .unwrap();
+
+        let res = test::call_service(&app, login("ada@example.com", PASSWORD).to_request()).await;
+        assert_eq!(res.status(), StatusCode::FORBIDDEN);
+        assert!(res.response().cookies().next().is_none());
+
+        register
+            .user_service
+            .set_disabled("ada@example.com", false)
+            .await
+            .unwrap();
+        let res = test::call_service(&app, login("ada@example.com", PASSWORD).to_request()).await;
+        assert_eq!(res.status(), StatusCode::OK);
+    }
+
+    #[actix_web::test]
+    async fn disabled_users_are_logged_out() {
+        let register = register(|_| {});
+        let app = init_app!(register.clone());
+
+        test::call_service(
+            &app,
+            register_user("ada@example.com", PASSWORD).to_request(),
+        )
+        .await;
+        let res = test::call_service(&app, login("ada@example.com", PASSWORD).to_request()).await;
+        let body: Value = test::read_body_json(res).await;
+        let token = body["token"].as_str().unwrap().to_string();
+        let me = || {
+            TestRequest::get()
+                .uri("/api/users/me")
+                .insert_header((header::AUTHORIZATION, format!("Bearer {}", token)))
+                .to_request()
+        };
+        let res = test::call_service(&app, me()).await;
+        assert_eq!(res.status(), StatusCode::OK);
+
+        register
+            .user_service
+            .set_disabled("ada@example.com", true)
+            .await
+            .unwrap();
+        let res = test::call_service(&app, me()).await;
+        assert_eq!(res.status(), StatusCode::FORBIDDEN);
+
+        register
+            .user_service
+            .delete_user("ada@example.com")
+            .await
+            .unwrap();
+        let res = test::call_service(&app, me()).await;
+        assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    /// How many times the user repository behind the cache ran `method`.
+    fn user_queries(register: &ServiceRegister, method: &str) -> u64 {
+        let line = format!(
+            "db_query_duration_seconds_count{{method=\"{}\",repository=\"user\"}} ",
+            method
+        );
+        register
+            .metrics
+            .render()
+            .unwrap()
+            .lines()
+            .find_map(|l| l.strip_prefix(&line))
+            .map_or(0, |count| count.parse().unwrap())
+    }
+
+    #[actix_web::test]
+    async fn authenticated_requests_look_the_user_up_in_the_cache() {
+        let register = register(|config| config.cache.enabled = true);
+        let app = init_app!(register.clone());
+
+        test::call_service(
+            &app,
+            register_user("ada@example.com", PASSWORD).to_request(),
+        )
+        .await;
+        let res = test::call_service(&app, login("ada@example.com", PASSWORD).to_request()).await;
+        let body: Value = test::read_body_json(res).await;
+        let token = body["token"].as_str().unwrap().to_string();
+        let by_email = user_queries(&register, "find_by_email");
+
+        for _ in 0..2 {
+            let req = TestRequest::get()
+                .uri("/api/users/me")
+                .insert_header((header::AUTHORIZATION, format!("Bearer {}", token)))
+                .to_request();
+            let res = test::call_service(&app, req).await;
+            assert_eq!(res.status(), StatusCode::OK);
         }
-        Ok(None) => {
-            let json_response = serde_json::json!({
-                "status": "fail",
-                "message": "User not found"
-            });
 
-            HttpResponse::NotFound().json(json_response)
+        // Only the first lookup reached the repository.
+        assert_eq!(user_queries(&register, "find_by_id"), 1);
+        assert_eq!(user_queries(&register, "find_by_email"), by_email);
+    }
+
+    #[actix_web::test]
+    async fn changes_the_password() {
+        let app = init_app!(register(|_| {}));
+        let new_password = "N3w-Secret-Phrase";
+
+        test::call_service(
+            &app,
+            register_user("ada@example.com", PASSWORD).to_request(),
+        )
+        .await;
+        let req = login("ada@example.com", PASSWORD).to_request();
+        let body: Value = test::call_and_read_body_json(&app, req).await;
+        let bearer = format!("Bearer {}", body["token"].as_str().unwrap());
+
+        let req = TestRequest::patch()
+            .uri("/api/users/me/password")
+            .insert_header((header::AUTHORIZATION, bearer.clone()))
+            .set_json(json!({ "currentPassword": "Wr0ng-Password", "newPassword": new_password }))
+            .to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+
+        let req = TestRequest::patch()
+            .uri("/api/users/me/password")
+            .insert_header((header::AUTHORIZATION, bearer))
+            .set_json(json!({ "currentPassword": PASSWORD, "newPassword": new_password }))
+            .to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+
+        let res = test::call_service(&app, login("ada@example.com", PASSWORD).to_request()).await;
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+        let res =
+            test::call_service(&app, login("ada@example.com", new_password).to_request()).await;
+        assert_eq!(res.status(), StatusCode::OK);
+    }
+
+    const LOWERCASE_EMAILS_MIGRATION: i64 = 20261019120000;
+
+    async fn logs_in_with_an_email_stored_in_mixed_case(pool: ConnectionPool) {
+        // Store the user the way it was before emails were lowercased.
+        while pool
+            .applied_migrations()
+            .await
+            .unwrap()
+            .contains(&LOWERCASE_EMAILS_MIGRATION)
+        {
+            pool.revert_last_migration().await.unwrap();
         }
-        Err(err) => {
-            let json_response = serde_json::json!({
-                "status": "error",
-                "message": format!("Error retrieving user: {}", err)
-            });
+        let register = sql_register(pool.clone(), |_| {});
+        register
+            .user_service
+            .create_user(
+                "Ada",
+                "Lovelace",
+                "Ada.Lovelace@Example.com",
+                &hash_password(PASSWORD).unwrap(),
+            )
+            .await
+            .unwrap();
+        pool.run_migrations().await.unwrap();
 
-            HttpResponse::InternalServerError().json(json_response)
+        let app = init_app!(register);
+        for email in ["ada.lovelace@example.com", "Ada.Lovelace@Example.com"] {
+            let res = test::call_service(&app, login(email, PASSWORD).to_request()).await;
+            assert_eq!(res.status(), StatusCode::OK, "{}", email);
         }
+
+        let req = register_user("ADA.LOVELACE@example.com", PASSWORD).to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::CONFLICT);
+    }
+
+    #[actix_web::test]
+    async fn postgres_logs_in_with_an_email_stored_in_mixed_case() {
+        with_postgres(|pool| {
+            logs_in_with_an_email_stored_in_mixed_case(ConnectionPool::Postgres(pool))
+        })
+        .await;
+    }
+
+    #[actix_web::test]
+    async fn sqlite_logs_in_with_an_email_stored_in_mixed_case() {
+        with_sqlite(|pool| {
+            logs_in_with_an_email_stored_in_mixed_case(ConnectionPool::Sqlite(pool))
+        })
+        .await;
     }
 }
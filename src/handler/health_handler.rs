@@ -0,0 +1,57 @@
+use actix_web::{get, web, HttpResponse, Responder};
+
+use crate::{
+    response::{HealthReport, HealthStatus},
+    service_register::ServiceRegister,
+};
+
+#[utoipa::path(
+    get,
+    path = "/health/live",
+    tag = "health",
+    responses((status = 200, description = "The process is running", body = HealthReport))
+)]
+#[get("/live")]
+async fn liveness_handler() -> impl Responder {
+    HttpResponse::Ok().json(HealthReport::up())
+}
+
+#[utoipa::path(
+    get,
+    path = "/health/ready",
+    tag = "health",
+    responses(
+        (status = 200, description = "Every dependency is available", body = HealthReport),
+        (status = 503, description = "At least one dependency check failed", body = HealthReport),
+    )
+)]
+#[get("/ready")]
+async fn readiness_handler(data: web::Data<ServiceRegister>) -> impl Responder {
+    let report = data.health_service.readiness().await;
+
+    match report.status {
+        HealthStatus::Up => HttpResponse::Ok().json(report),
+        HealthStatus::Down => HttpResponse::ServiceUnavailable().json(report),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::http::StatusCode;
+    use actix_web::test::{self, TestRequest};
+    use serde_json::Value;
+
+    use crate::handler::test_support::{init_app, register};
+
+    #[actix_web::test]
+    async fn reports_live_and_ready_without_a_database() {
+        let app = init_app!(register(|_| {}));
+
+        for uri in ["/health/live", "/health/ready"] {
+            let res = test::call_service(&app, TestRequest::get().uri(uri).to_request()).await;
+            assert_eq!(res.status(), StatusCode::OK, "{}", uri);
+            let body: Value = test::read_body_json(res).await;
+            assert_eq!(body["status"], "up");
+        }
+    }
+}
@@ -0,0 +1,26 @@
+use actix_web::{get, web, HttpResponse};
+
+use crate::{config::ConnectionManager, error::AppError, service_register::ServiceRegister};
+
+const PROMETHEUS_TEXT: &str = "text/plain; version=0.0.4; charset=utf-8";
+
+/// Prometheus scrape endpoint. Pool gauges are refreshed here; note and user
+/// totals are kept current by a background job.
+#[utoipa::path(
+    get,
+    path = "/metrics",
+    tag = "metrics",
+    responses(
+        (status = 200, description = "Metrics in the Prometheus text format", body = String, content_type = "text/plain"),
+    )
+)]
+#[get("/metrics")]
+async fn prometheus_handler(data: web::Data<ServiceRegister>) -> Result<HttpResponse, AppError> {
+    if let Some(pool) = &data.pool {
+        data.metrics.set_pool_stats(ConnectionManager::stats(pool));
+    }
+
+    let body = data.metrics.render().map_err(AppError::internal)?;
+
+    Ok(HttpResponse::Ok().content_type(PROMETHEUS_TEXT).body(body))
+}
@@ -1,17 +1,34 @@
 use actix_web::web;
 
+use crate::middleware::{json_error_handler, path_error_handler, query_error_handler};
+
+use self::admin_handler::migrations_handler;
 use self::auth_handler::{
-    get_me_handler, login_user_handler, logout_handler, register_user_handler,
+    change_password_handler, get_me_handler, login_user_handler, logout_handler,
+    register_user_handler,
 };
+use self::docs_handler::openapi_json_handler;
+use self::health_handler::{liveness_handler, readiness_handler};
+use self::metrics_handler::prometheus_handler;
 use self::note_handler::{
     create_note_handler, delete_note_handler, edit_note_handler, get_note_handler, get_notes,
     health_checker_handler,
 };
 
+mod admin_handler;
 mod auth_handler;
+mod docs_handler;
+mod health_handler;
+mod metrics_handler;
 mod note_handler;
+#[cfg(test)]
+pub(crate) mod test_support;
+
+pub use docs_handler::docs_ui_config;
 
-pub fn config(conf: &mut web::ServiceConfig) {
+/// Mounts every route. Request bodies larger than `max_body_bytes` are
+/// rejected with 413.
+pub fn config(conf: &mut web::ServiceConfig, max_body_bytes: usize) {
     let scope = web::scope("/api")
         .service(health_checker_handler)
         .service(get_notes)
@@ -22,7 +39,24 @@ pub fn config(conf: &mut web::ServiceConfig) {
         .service(login_user_handler)
         .service(register_user_handler)
         .service(get_me_handler)
-        .service(logout_handler);
+        .service(change_password_handler)
+        .service(logout_handler)
+        .service(migrations_handler)
+        .service(openapi_json_handler);
+
+    let health = web::scope("/health")
+        .service(liveness_handler)
+        .service(readiness_handler);
 
-    conf.service(scope);
+    conf.app_data(
+        web::JsonConfig::default()
+            .limit(max_body_bytes)
+            .error_handler(json_error_handler),
+    )
+    .app_data(web::PayloadConfig::new(max_body_bytes))
+    .app_data(web::PathConfig::default().error_handler(path_error_handler))
+    .app_data(web::QueryConfig::default().error_handler(query_error_handler))
+    .service(scope)
+    .service(health)
+    .service(prometheus_handler);
 }
@@ -1,150 +1,312 @@
-use actix_web::{delete, get, patch, post, web, HttpResponse, Responder};
-
-use serde_json::json;
+use actix_web::{delete, get, http::header, patch, post, web, HttpResponse, Responder};
+use tracing::instrument;
 
 use crate::{
+    config::ConnectionManager,
+    error::AppError,
+    middleware::{ClientInfo, ValidatedJson},
+    response::{HealthResponse, NoteDataResponse, NoteListResponse},
     schema::{CreateNoteSchema, UpdateNoteSchema},
     service_register::ServiceRegister,
 };
 
+#[utoipa::path(
+    get,
+    path = "/api/healthchecker",
+    tag = "health",
+    responses((status = 200, description = "Service is up", body = HealthResponse))
+)]
 #[get("/healthchecker")]
-async fn health_checker_handler() -> impl Responder {
+#[instrument(skip_all)]
+async fn health_checker_handler(data: web::Data<ServiceRegister>) -> impl Responder {
     const MESSAGE: &str = "Build Simple CRUD API with Rust, SQLX, Postgres,and Actix Web";
 
-    HttpResponse::Ok().json(json!({"status": "success","message": MESSAGE}))
+    let pool_stats = data.pool.as_ref().map(ConnectionManager::stats);
+    HttpResponse::Ok().json(HealthResponse::success(MESSAGE, pool_stats))
 }
 
+#[utoipa::path(
+    get,
+    path = "/api/notes",
+    tag = "notes",
+    responses((status = 200, description = "All notes", body = NoteListResponse))
+)]
 #[get("/notes")]
-async fn get_notes(state: web::Data<ServiceRegister>) -> impl Responder {
-    let query_result = state.note_service.get_notes().await;
-
-    if query_result.is_err() {
-        let message = "Something bad happened while fetching all note items";
-        return HttpResponse::InternalServerError()
-            .json(json!({"status": "error","message": message}));
-    }
-
-    let notes = query_result.unwrap();
+#[instrument(skip_all)]
+async fn get_notes(state: web::Data<ServiceRegister>) -> Result<HttpResponse, AppError> {
+    let notes = state.note_service.get_notes().await?;
 
-    let json_response = serde_json::json!({
-        "status": "success",
-        "results": notes.len(),
-        "notes": notes
-    });
-    HttpResponse::Ok().json(json_response)
+    Ok(HttpResponse::Ok().json(NoteListResponse::success(notes)))
 }
 
+#[utoipa::path(
+    post,
+    path = "/api/notes",
+    tag = "notes",
+    request_body = CreateNoteSchema,
+    responses(
+        (status = 200, description = "Note created", body = NoteDataResponse,
+            headers(("Location" = String, description = "Absolute URL of the new note"))),
+        (status = 409, description = "A note with that title already exists", body = ProblemDetails),
+        (status = 422, description = "Invalid request body", body = ProblemDetails)
+    )
+)]
 #[post("/notes")]
+#[instrument(skip_all)]
 async fn create_note_handler(
-    body: web::Json<CreateNoteSchema>,
+    body: ValidatedJson<CreateNoteSchema>,
+    client: ClientInfo,
     state: web::Data<ServiceRegister>,
-) -> impl Responder {
-    let query_result = state
+) -> Result<HttpResponse, AppError> {
+    let note = state
         .note_service
-        .create_note(&body.title.to_string(), &body.content.to_string())
-        .await;
-
-    match query_result {
-        Ok(note) => {
-            let note_response = serde_json::json!({"status": "success","data": serde_json::json!({
-                "note": note
-            })});
+        .create_note(&body.title, &body.content)
+        .await?;
+    let location = format!("{}/api/notes/{}", client.base_url(), note.id);
 
-            return HttpResponse::Ok().json(note_response);
-        }
-        Err(e) => {
-            if e.to_string()
-                .contains("duplicate key value violates unique constraint")
-            {
-                return HttpResponse::BadRequest()
-                    .json(serde_json::json!({"status": "fail","message": "Note with that title already exists"}));
-            }
-
-            return HttpResponse::InternalServerError()
-                .json(serde_json::json!({"status": "error","message": format!("{:?}", e)}));
-        }
-    }
+    Ok(HttpResponse::Ok()
+        .insert_header((header::LOCATION, location))
+        .json(NoteDataResponse::success(note)))
 }
 
+#[utoipa::path(
+    get,
+    path = "/api/notes/{id}",
+    tag = "notes",
+    params(("id" = Uuid, Path, description = "Note ID")),
+    responses(
+        (status = 200, description = "The note", body = NoteDataResponse),
+        (status = 404, description = "Note not found", body = ProblemDetails)
+    )
+)]
 #[get("/notes/{id}")]
+#[instrument(skip_all)]
 async fn get_note_handler(
     path: web::Path<uuid::Uuid>,
     state: web::Data<ServiceRegister>,
-) -> impl Responder {
-    let note_id = path.into_inner();
-
-    let query_result = state.note_service.get_note_id(note_id).await;
-
-    match query_result {
-        Ok(note) => {
-            let note_response = serde_json::json!({"status": "success","data": serde_json::json!({
-                "note": note
-            })});
+) -> Result<HttpResponse, AppError> {
+    let note = state.note_service.get_note_id(path.into_inner()).await?;
 
-            return HttpResponse::Ok().json(note_response);
-        }
-        Err(_) => {
-            let message = format!("Note with ID: {} not found", note_id);
-            return HttpResponse::NotFound()
-                .json(serde_json::json!({"status": "fail","message": message}));
-        }
-    }
+    Ok(HttpResponse::Ok().json(NoteDataResponse::success(note)))
 }
 
+#[utoipa::path(
+    patch,
+    path = "/api/notes/{id}",
+    tag = "notes",
+    params(("id" = Uuid, Path, description = "Note ID")),
+    request_body = UpdateNoteSchema,
+    responses(
+        (status = 200, description = "Note updated", body = NoteDataResponse),
+        (status = 404, description = "Note not found", body = ProblemDetails),
+        (status = 409, description = "A note with that title already exists", body = ProblemDetails),
+        (status = 422, description = "Invalid request body", body = ProblemDetails)
+    )
+)]
 #[patch("/notes/{id}")]
+#[instrument(skip_all)]
 async fn edit_note_handler(
     path: web::Path<uuid::Uuid>,
-    body: web::Json<UpdateNoteSchema>,
+    body: ValidatedJson<UpdateNoteSchema>,
     state: web::Data<ServiceRegister>,
-) -> impl Responder {
-    let note_id = path.into_inner();
-
-    let query_result = state.note_service.get_note_id(note_id).await;
-
-    if query_result.is_err() {
-        let message = format!("Note with ID: {} not found", note_id);
-        return HttpResponse::NotFound()
-            .json(serde_json::json!({"status": "fail","message": message}));
-    }
-
-    let _note = query_result.unwrap();
-
-    let query_result = state
+) -> Result<HttpResponse, AppError> {
+    let note = state
         .note_service
-        .update_note(
-            note_id,
-            &body.title.to_owned().to_string(),
-            &body.content.to_owned().to_string(),
-        )
-        .await;
+        .update_note(path.into_inner(), &body.title, &body.content)
+        .await?;
 
-    match query_result {
-        Ok(note) => {
-            let note_response = serde_json::json!({"status": "success","data": serde_json::json!({
-                "note": note
-            })});
-
-            return HttpResponse::Ok().json(note_response);
-        }
-        Err(err) => {
-            let message = format!("Error: {:?}", err);
-            return HttpResponse::InternalServerError()
-                .json(serde_json::json!({"status": "error","message": message}));
-        }
-    }
+    Ok(HttpResponse::Ok().json(NoteDataResponse::success(note)))
 }
 
+#[utoipa::path(
+    delete,
+    path = "/api/notes/{id}",
+    tag = "notes",
+    params(("id" = Uuid, Path, description = "Note ID")),
+    responses((status = 204, description = "Note deleted"))
+)]
 #[delete("/notes/{id}")]
+#[instrument(skip_all)]
 async fn delete_note_handler(
     path: web::Path<uuid::Uuid>,
     state: web::Data<ServiceRegister>,
-) -> impl Responder {
+) -> Result<HttpResponse, AppError> {
     let note_id = path.into_inner();
 
     if let Err(err) = state.note_service.delete_note(note_id).await {
         log::error!("Failed to delete note: {:?}", err);
-        return HttpResponse::InternalServerError().finish();
+        return Err(err);
     }
 
-    HttpResponse::NoContent().finish()
+    Ok(HttpResponse::NoContent().finish())
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::http::{header, Method, StatusCode};
+    use actix_web::test::{self, TestRequest};
+    use serde_json::{json, Value};
+
+    use crate::config::{ConnectionPool, RateLimitKey, RateLimitPolicyConfig};
+    use crate::handler::test_support::{init_app, register, sql_register};
+    use crate::repository::contract_tests::with_sqlite;
+
+    fn create_note(title: &str) -> TestRequest {
+        TestRequest::post()
+            .uri("/api/notes")
+            .set_json(json!({ "title": title, "content": "Some content" }))
+    }
+
+    #[actix_web::test]
+    async fn creates_reads_updates_and_deletes_a_note() {
+        let app = init_app!(register(|_| {}));
+
+        let res = test::call_service(&app, create_note("First").to_request()).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        let location = res
+            .headers()
+            .get(header::LOCATION)
+            .unwrap()
+            .to_str()
+            .unwrap()
+            .to_string();
+        let body: Value = test::read_body_json(res).await;
+        let id = body["data"]["note"]["id"].as_str().unwrap().to_string();
+        assert!(location.ends_with(&format!("/api/notes/{}", id)));
+        assert_eq!(body["data"]["note"]["title"], "First");
+
+        let req = TestRequest::get()
+            .uri(&format!("/api/notes/{}", id))
+            .to_request();
+        let body: Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(body["data"]["note"]["content"], "Some content");
+
+        let req = TestRequest::patch()
+            .uri(&format!("/api/notes/{}", id))
+            .set_json(json!({ "title": "Renamed", "content": "Other content" }))
+            .to_request();
+        let body: Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(body["data"]["note"]["title"], "Renamed");
+
+        let req = TestRequest::get().uri("/api/notes").to_request();
+        let body: Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(body["results"], 1);
+        assert_eq!(body["notes"][0]["title"], "Renamed");
+
+        let req = TestRequest::delete()
+            .uri(&format!("/api/notes/{}", id))
+            .to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::NO_CONTENT);
+
+        let req = TestRequest::get()
+            .uri(&format!("/api/notes/{}", id))
+            .to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+        assert_eq!(
+            res.headers().get(header::CONTENT_TYPE).unwrap(),
+            "application/problem+json"
+        );
+        let body: Value = test::read_body_json(res).await;
+        assert_eq!(body["type"], "/problems/not-found");
+        assert_eq!(body["instance"], format!("/api/notes/{}", id));
+    }
+
+    #[actix_web::test]
+    async fn rejects_a_duplicate_title() {
+        let app = init_app!(register(|_| {}));
+
+        let res = test::call_service(&app, create_note("Same").to_request()).await;
+        assert_eq!(res.status(), StatusCode::OK);
+
+        let res = test::call_service(&app, create_note("Same").to_request()).await;
+        assert_eq!(res.status(), StatusCode::CONFLICT);
+        let body: Value = test::read_body_json(res).await;
+        assert_eq!(body["type"], "/problems/conflict");
+    }
+
+    #[actix_web::test]
+    async fn updating_a_missing_note_is_not_found() {
+        let app = init_app!(register(|_| {}));
+
+        let req = TestRequest::patch()
+            .uri(&format!("/api/notes/{}", uuid::Uuid::new_v4()))
+            .set_json(json!({ "title": "Title", "content": "Content" }))
+            .to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[actix_web::test]
+    async fn rejects_invalid_input() {
+        let app = init_app!(register(|_| {}));
+
+        let req = TestRequest::post()
+            .uri("/api/notes")
+            .set_json(json!({ "title": "  ", "content": "" }))
+            .to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::UNPROCESSABLE_ENTITY);
+        let body: Value = test::read_body_json(res).await;
+        assert!(body["errors"]["title"].is_array());
+        assert!(body["errors"]["content"].is_array());
+
+        let req = TestRequest::get().uri("/api/notes/not-a-uuid").to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[actix_web::test]
+    async fn limits_requests_per_policy() {
+        let app = init_app!(register(|config| {
+            config.rate_limit.enabled = true;
+            config.rate_limit.policies = vec![RateLimitPolicyConfig {
+                name: "notes".to_string(),
+                path_prefix: "/api/notes".to_string(),
+                methods: vec![Method::GET.to_string()],
+                key: RateLimitKey::Ip,
+                requests: 2,
+                per_secs: 60,
+            }];
+        }));
+
+        for remaining in ["1", "0"] {
+            let req = TestRequest::get().uri("/api/notes").to_request();
+            let res = test::call_service(&app, req).await;
+            assert_eq!(res.status(), StatusCode::OK);
+            assert_eq!(res.headers().get("ratelimit-remaining").unwrap(), remaining);
+        }
+
+        let req = TestRequest::get().uri("/api/notes").to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert!(res.headers().contains_key(header::RETRY_AFTER));
+        let body: Value = test::read_body_json(res).await;
+        assert_eq!(body["type"], "/problems/rate-limited");
+
+        let res = test::call_service(&app, create_note("Not limited").to_request()).await;
+        assert_eq!(res.status(), StatusCode::OK);
+    }
+
+    #[actix_web::test]
+    async fn reports_pool_stats_only_with_a_database() {
+        let app = init_app!(register(|_| {}));
+        let req = TestRequest::get().uri("/api/healthchecker").to_request();
+        let body: Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(body["status"], "success");
+        assert!(body.get("database").is_none());
+
+        let req = TestRequest::get().uri("/metrics").to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+
+        with_sqlite(|pool| async move {
+            let app = init_app!(sql_register(ConnectionPool::Sqlite(pool), |_| {}));
+            let req = TestRequest::get().uri("/api/healthchecker").to_request();
+            let body: Value = test::call_and_read_body_json(&app, req).await;
+            assert!(body["database"]["maxSize"].as_u64().unwrap() > 0);
+        })
+        .await;
+    }
 }
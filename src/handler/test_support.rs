@@ -0,0 +1,55 @@
+use crate::config::{Config, ConnectionPool};
+use crate::metrics::Metrics;
+use crate::security::PasswordPolicy;
+use crate::service_register::ServiceRegister;
+
+/// Services backed by the in-memory repositories, with rate limiting off
+/// unless `configure` turns it back on.
+pub fn register(configure: impl FnOnce(&mut Config)) -> ServiceRegister {
+    let (config, password_policy) = config(configure);
+    ServiceRegister::in_memory(config, password_policy, Metrics::new().unwrap())
+}
+
+/// Services backed by `pool`, configured like [`register`].
+pub fn sql_register(pool: ConnectionPool, configure: impl FnOnce(&mut Config)) -> ServiceRegister {
+    let (config, password_policy) = config(configure);
+    ServiceRegister::new(
+        pool,
+        Vec::new(),
+        config,
+        password_policy,
+        Metrics::new().unwrap(),
+    )
+}
+
+fn config(configure: impl FnOnce(&mut Config)) -> (Config, PasswordPolicy) {
+    let mut config = Config::default();
+    config.auth.jwt_secret = "test-secret".into();
+    config.rate_limit.enabled = false;
+    configure(&mut config);
+
+    let password_policy = PasswordPolicy::new(config.auth.password_policy.clone()).unwrap();
+    (config, password_policy)
+}
+
+/// Initializes the routes and the request-handling middleware the way `main`
+/// does, for use with `actix_web::test`.
+macro_rules! init_app {
+    ($register:expr) => {{
+        let register = $register;
+        let max_body_bytes = register.env.server.max_body_bytes;
+
+        actix_web::test::init_service(
+            actix_web::App::new()
+                .configure(|conf| $crate::handler::config(conf, max_body_bytes))
+                .app_data(actix_web::web::Data::new(register))
+                .wrap($crate::middleware::ReadConsistencyMiddleware)
+                .wrap($crate::middleware::RateLimitMiddleware)
+                .wrap($crate::middleware::error_handlers())
+                .wrap($crate::middleware::RequestIdMiddleware),
+        )
+        .await
+    }};
+}
+
+pub(crate) use init_app;
@@ -0,0 +1,27 @@
+use std::time::Duration;
+
+use actix_web::rt::time::interval;
+
+use crate::{service::ShutdownSignal, service_register::ServiceRegister};
+
+const REFRESH_INTERVAL: Duration = Duration::from_secs(15);
+
+/// Keeps the note and user totals exported on `/metrics` up to date, so a
+/// scrape does not have to count whole tables.
+pub async fn run(register: ServiceRegister, mut shutdown: ShutdownSignal) {
+    let mut ticks = interval(REFRESH_INTERVAL);
+
+    loop {
+        tokio::select! {
+            _ = ticks.tick() => refresh(&register).await,
+            _ = shutdown.cancelled() => break,
+        }
+    }
+}
+
+async fn refresh(register: &ServiceRegister) {
+    match register.stats_service.totals().await {
+        Ok(totals) => register.metrics.set_totals(totals.notes, totals.users),
+        Err(err) => log::warn!("Failed to refresh business gauges: {}", err),
+    }
+}
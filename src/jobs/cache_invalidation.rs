@@ -0,0 +1,35 @@
+use crate::{
+    config::ConnectionPool,
+    repository::{ChangeFeedEvent, PgChangeFeed},
+    service::ShutdownSignal,
+    service_register::ServiceRegister,
+};
+
+/// Drops cache entries for notes and users changed by any instance, as
+/// Postgres notifies about them. After (re)connecting everything is
+/// dropped, since changes made while not listening were missed. Does
+/// nothing without caches or a Postgres database.
+pub async fn run(register: ServiceRegister, mut shutdown: ShutdownSignal) {
+    let (Some(caches), Some(ConnectionPool::Postgres(pool))) = (&register.caches, &register.pool)
+    else {
+        return;
+    };
+    let mut feed = PgChangeFeed::new(pool.clone());
+    let mut connected_before = false;
+
+    loop {
+        tokio::select! {
+            event = feed.next() => match event {
+                ChangeFeedEvent::Changed(change) => caches.invalidate(&change),
+                ChangeFeedEvent::Resumed => {
+                    if connected_before {
+                        log::info!("Listening for changes again, clearing the caches");
+                    }
+                    connected_before = true;
+                    caches.clear();
+                }
+            },
+            _ = shutdown.cancelled() => break,
+        }
+    }
+}
@@ -0,0 +1,31 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use actix_web::rt::time::interval;
+
+use crate::{security::CertificateResolver, service::ShutdownSignal};
+
+/// Polls the certificate and key files and swaps in new ones when they
+/// change, e.g. after a renewal.
+pub async fn run(
+    resolver: Arc<CertificateResolver>,
+    every: Duration,
+    mut shutdown: ShutdownSignal,
+) {
+    let mut ticks = interval(every);
+
+    loop {
+        tokio::select! {
+            _ = ticks.tick() => reload(&resolver),
+            _ = shutdown.cancelled() => break,
+        }
+    }
+}
+
+fn reload(resolver: &CertificateResolver) {
+    match resolver.reload_if_changed() {
+        Ok(true) => log::info!("Reloaded TLS certificate"),
+        Ok(false) => {}
+        Err(err) => log::warn!("Failed to reload TLS certificate: {}", err),
+    }
+}
@@ -0,0 +1,64 @@
+mod business_gauges;
+mod cache_invalidation;
+mod certificate_reload;
+mod rate_limit_purge;
+mod replica_health;
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::{
+    config::DatabaseBackend, security::CertificateResolver, service_register::ServiceRegister,
+};
+
+/// Starts every built-in background job under the register's supervisor.
+pub fn start(register: &ServiceRegister) {
+    let gauges_register = register.clone();
+    register
+        .task_supervisor
+        .spawn("business-gauges", move |shutdown| {
+            business_gauges::run(gauges_register, shutdown)
+        });
+
+    if register.env.rate_limit.enabled {
+        let purge_register = register.clone();
+        register
+            .task_supervisor
+            .spawn("rate-limit-purge", move |shutdown| {
+                rate_limit_purge::run(purge_register, shutdown)
+            });
+    }
+
+    if register.replicas.is_some() {
+        let replica_register = register.clone();
+        register
+            .task_supervisor
+            .spawn("replica-health", move |shutdown| {
+                replica_health::run(replica_register, shutdown)
+            });
+    }
+
+    if register.env.cache.listen_for_changes
+        && register.env.database.backend == DatabaseBackend::Sql
+    {
+        let cache_register = register.clone();
+        register
+            .task_supervisor
+            .spawn("cache-invalidation", move |shutdown| {
+                cache_invalidation::run(cache_register, shutdown)
+            });
+    }
+}
+
+/// Reloads the TLS certificate from disk every `every` when it changes.
+pub fn watch_certificate(
+    register: &ServiceRegister,
+    resolver: Arc<CertificateResolver>,
+    every: Duration,
+) {
+    register
+        .task_supervisor
+        .spawn("certificate-reload", move |shutdown| {
+            certificate_reload::run(resolver, every, shutdown)
+        });
+}
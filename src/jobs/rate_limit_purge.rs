@@ -0,0 +1,28 @@
+use std::time::Duration;
+
+use actix_web::rt::time::interval;
+
+use crate::{service::ShutdownSignal, service_register::ServiceRegister};
+
+const PURGE_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Drops rate limit buckets that have refilled, so the store only holds
+/// clients that were active recently.
+pub async fn run(register: ServiceRegister, mut shutdown: ShutdownSignal) {
+    let mut ticks = interval(PURGE_INTERVAL);
+
+    loop {
+        tokio::select! {
+            _ = ticks.tick() => purge(&register).await,
+            _ = shutdown.cancelled() => break,
+        }
+    }
+}
+
+async fn purge(register: &ServiceRegister) {
+    match register.rate_limiter.purge_expired().await {
+        Ok(0) => {}
+        Ok(purged) => log::debug!("Purged {} rate limit buckets", purged),
+        Err(err) => log::warn!("Failed to purge rate limit buckets: {}", err),
+    }
+}
@@ -0,0 +1,29 @@
+use std::time::Duration;
+
+use actix_web::rt::time::interval;
+
+use crate::{service::ShutdownSignal, service_register::ServiceRegister};
+
+/// Pings the read replicas every `database.replica_check_interval_secs`,
+/// taking unreachable ones out of rotation and putting recovered ones back.
+/// Also forgets read-your-writes entries that have expired.
+pub async fn run(register: ServiceRegister, mut shutdown: ShutdownSignal) {
+    let Some(replicas) = register.replicas.clone() else {
+        return;
+    };
+    let every = Duration::from_secs(register.env.database.replica_check_interval_secs);
+    let mut ticks = interval(every);
+
+    loop {
+        tokio::select! {
+            _ = ticks.tick() => {
+                replicas.check_replicas().await;
+                register.metrics.set_healthy_replicas(replicas.healthy_replicas());
+                if let Some(read_your_writes) = &register.read_your_writes {
+                    read_your_writes.purge_expired();
+                }
+            }
+            _ = shutdown.cancelled() => break,
+        }
+    }
+}
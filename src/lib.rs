@@ -0,0 +1,16 @@
+pub mod config;
+pub mod error;
+pub mod handler;
+pub mod jobs;
+pub mod metrics;
+pub mod middleware;
+pub mod response;
+
+pub mod abstract_trait;
+pub mod models;
+pub mod repository;
+pub mod schema;
+pub mod security;
+pub mod service;
+pub mod service_register;
+pub mod telemetry;
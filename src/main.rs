@@ -1,63 +1,262 @@
-mod config;
-mod handler;
-mod middleware;
-mod response;
-
-mod abstract_trait;
-mod models;
-mod repository;
-mod schema;
-mod service;
-mod service_register;
-
-use crate::service_register::ServiceRegister;
-use actix_cors::Cors;
-use actix_web::middleware::Logger;
+use std::path::PathBuf;
+use std::process::ExitCode;
+use std::sync::Arc;
+use std::time::Duration;
+
+use actix_web::http::KeepAlive;
 use actix_web::web::Data;
-use actix_web::{http::header, App, HttpServer};
-use config::{Config, ConnectionManager};
+use actix_web::{App, HttpServer};
+use crudsqlx::config::{Config, ConnectionManager, DatabaseBackend};
+use crudsqlx::security::{CertificateResolver, PasswordPolicy};
+use crudsqlx::service_register::ServiceRegister;
+use crudsqlx::{handler, jobs, metrics, middleware, telemetry};
 use dotenv::dotenv;
 
-#[actix_web::main]
-async fn main() -> std::io::Result<()> {
-    if std::env::var_os("RUST_LOG").is_none() {
-        std::env::set_var("RUST_LOG", "actix_web=info");
+const USAGE: &str = "Usage: crudsqlx [--config <file>] [--print-config]";
+
+struct Args {
+    config_file: Option<PathBuf>,
+    print_config: bool,
+}
+
+fn parse_args() -> Result<Args, String> {
+    let mut args = Args {
+        config_file: None,
+        print_config: false,
+    };
+
+    let mut iter = std::env::args().skip(1);
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--config" => {
+                let path = iter.next().ok_or("--config requires a file path")?;
+                args.config_file = Some(PathBuf::from(path));
+            }
+            "--print-config" => args.print_config = true,
+            _ => return Err(format!("unknown argument '{}'", arg)),
+        }
     }
 
+    Ok(args)
+}
+
+/// Resolves on SIGTERM or SIGINT. If the handlers cannot be installed the
+/// server keeps running and can only be stopped forcefully.
+async fn shutdown_signal() {
+    if let Err(err) = wait_for_signal().await {
+        log::error!("Error listening for shutdown signals: {}", err);
+        std::future::pending::<()>().await;
+    }
+}
+
+#[cfg(unix)]
+async fn wait_for_signal() -> std::io::Result<()> {
+    use actix_web::rt::signal::unix::{signal, SignalKind};
+
+    let mut terminate = signal(SignalKind::terminate())?;
+    tokio::select! {
+        result = actix_web::rt::signal::ctrl_c() => result,
+        _ = terminate.recv() => Ok(()),
+    }
+}
+
+#[cfg(not(unix))]
+async fn wait_for_signal() -> std::io::Result<()> {
+    actix_web::rt::signal::ctrl_c().await
+}
+
+#[actix_web::main]
+async fn main() -> ExitCode {
     dotenv().ok();
-    env_logger::init();
-    let config = Config::init();
 
-    let db_pool =
-        match ConnectionManager::new_pool(&config.database_url, config.run_migrations).await {
-            Ok(pool) => pool,
+    let args = match parse_args() {
+        Ok(args) => args,
+        Err(err) => {
+            eprintln!("{}\n{}", err, USAGE);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let config = match Config::load(args.config_file.as_deref()) {
+        Ok(config) => config,
+        Err(errors) => {
+            eprint!("{}", errors);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    if args.print_config {
+        println!("{}", serde_json::to_string_pretty(&config).unwrap());
+        return ExitCode::SUCCESS;
+    }
+
+    if let Err(err) = telemetry::init(&config.logging, &config.tracing) {
+        eprintln!("Error initializing telemetry: {}", err);
+        return ExitCode::FAILURE;
+    }
+
+    let password_policy = match PasswordPolicy::new(config.auth.password_policy.clone()) {
+        Ok(policy) => policy,
+        Err(err) => {
+            log::error!("Error loading password policy: {}", err);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let metrics = match metrics::Metrics::new() {
+        Ok(metrics) => metrics,
+        Err(err) => {
+            log::error!("Error registering metrics: {}", err);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let certificate_resolver = if config.server.tls.enabled {
+        match CertificateResolver::new(&config.server.tls) {
+            Ok(resolver) => Some(Arc::new(resolver)),
             Err(err) => {
-                eprintln!("Error initializing database connection pool: {}", err);
-                return Ok(());
+                log::error!("Error loading TLS certificate: {}", err);
+                return ExitCode::FAILURE;
             }
-        };
-
-    let port = config.port;
-    let service_register = ServiceRegister::new(db_pool, config);
-
-    HttpServer::new(move || {
-        let cors = Cors::default()
-            .allowed_origin("http://localhost:3000")
-            .allowed_methods(vec!["GET", "POST", "PATCH", "DELETE"])
-            .allowed_headers(vec![
-                header::CONTENT_TYPE,
-                header::AUTHORIZATION,
-                header::ACCEPT,
-            ])
-            .supports_credentials();
-
-        App::new()
-            .configure(handler::config)
-            .app_data(Data::new(service_register.clone()))
-            .wrap(cors)
-            .wrap(Logger::default())
+        }
+    } else {
+        None
+    };
+
+    let server_config = config.server.clone();
+    let max_body_bytes = server_config.max_body_bytes;
+    let api_docs_enabled = server_config.api_docs_enabled;
+    let cors_config = config.cors.clone();
+    let shutdown_delay = Duration::from_secs(server_config.shutdown_delay_secs);
+    let shutdown_timeout = server_config.shutdown_timeout_secs;
+    let service_register = match config.database.backend {
+        DatabaseBackend::Sql => {
+            let db_pool = match ConnectionManager::new_pool(&config.database).await {
+                Ok(pool) => pool,
+                Err(err) => {
+                    log::error!("Error initializing database connection pool: {}", err);
+                    return ExitCode::FAILURE;
+                }
+            };
+            if config.database.require_migrations {
+                match db_pool.pending_migrations().await {
+                    Ok(pending) if pending.is_empty() => {}
+                    Ok(pending) => {
+                        let versions: Vec<String> =
+                            pending.iter().map(|m| m.version.to_string()).collect();
+                        log::error!(
+                            "Refusing to start with pending migrations: {}",
+                            versions.join(", ")
+                        );
+                        return ExitCode::FAILURE;
+                    }
+                    Err(err) => {
+                        log::error!("Error checking for pending migrations: {}", err);
+                        return ExitCode::FAILURE;
+                    }
+                }
+            }
+            let replicas = match ConnectionManager::new_replicas(&config.database) {
+                Ok(replicas) => replicas,
+                Err(err) => {
+                    log::error!("Error initializing read replica pools: {}", err);
+                    return ExitCode::FAILURE;
+                }
+            };
+            ServiceRegister::new(db_pool, replicas, config, password_policy, metrics)
+        }
+        DatabaseBackend::Memory => {
+            log::warn!("Using the in-memory database backend, data is lost on restart");
+            ServiceRegister::in_memory(config, password_policy, metrics)
+        }
+    };
+    let app_register = service_register.clone();
+
+    let keep_alive = match server_config.keep_alive_secs {
+        0 => KeepAlive::Disabled,
+        secs => KeepAlive::Timeout(Duration::from_secs(secs)),
+    };
+
+    let mut server = HttpServer::new(move || {
+        let mut app = App::new().configure(|conf| handler::config(conf, max_body_bytes));
+        if api_docs_enabled {
+            app = app.configure(handler::docs_ui_config);
+        }
+
+        app.app_data(Data::new(app_register.clone()))
+            .wrap(middleware::ReadConsistencyMiddleware)
+            .wrap(middleware::RateLimitMiddleware)
+            .wrap(middleware::error_handlers())
+            .wrap(middleware::cors(&cors_config))
+            .wrap(middleware::AccessLogMiddleware)
+            .wrap(middleware::TracingMiddleware)
+            .wrap(middleware::RequestIdMiddleware)
+            .wrap(middleware::MetricsMiddleware)
     })
-    .bind(("127.0.0.1", port))?
-    .run()
-    .await
+    .disable_signals()
+    .shutdown_timeout(shutdown_timeout)
+    .keep_alive(keep_alive)
+    .client_request_timeout(Duration::from_millis(
+        server_config.client_request_timeout_ms,
+    ));
+    if let Some(workers) = server_config.workers {
+        server = server.workers(workers);
+    }
+
+    let address = (server_config.host.as_str(), server_config.port);
+    let (server, scheme) = match &certificate_resolver {
+        Some(resolver) => (
+            server.bind_rustls(address, resolver.server_config()),
+            "https",
+        ),
+        None => (server.bind(address), "http"),
+    };
+
+    let server = match server {
+        Ok(server) => server.run(),
+        Err(err) => {
+            log::error!("Error binding server: {}", err);
+            return ExitCode::FAILURE;
+        }
+    };
+    log::info!(
+        "Listening on {}://{}:{}",
+        scheme,
+        server_config.host,
+        server_config.port
+    );
+
+    jobs::start(&service_register);
+    if let Some(resolver) = certificate_resolver {
+        if server_config.tls.reload_interval_secs > 0 {
+            let every = Duration::from_secs(server_config.tls.reload_interval_secs);
+            jobs::watch_certificate(&service_register, resolver, every);
+        }
+    }
+
+    let server_handle = server.handle();
+    let health_service = service_register.health_service.clone();
+    actix_web::rt::spawn(async move {
+        shutdown_signal().await;
+        log::info!("Shutdown signal received, draining");
+        health_service.begin_shutdown();
+        actix_web::rt::time::sleep(shutdown_delay).await;
+        server_handle.stop(true).await;
+    });
+
+    let result = server.await;
+
+    service_register
+        .shutdown(Duration::from_secs(shutdown_timeout))
+        .await;
+    log::info!("Shutdown complete");
+    telemetry::shutdown_tracing();
+
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            log::error!("Server error: {}", err);
+            ExitCode::FAILURE
+        }
+    }
 }
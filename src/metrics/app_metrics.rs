@@ -0,0 +1,178 @@
+use std::future::Future;
+use std::time::Instant;
+
+use prometheus::{
+    Encoder, HistogramOpts, HistogramVec, IntCounterVec, IntGauge, IntGaugeVec, Opts, Registry,
+    TextEncoder,
+};
+
+use crate::config::PoolStats;
+
+/// Every metric the service exports, registered on its own registry so
+/// tests can create as many instances as they like.
+pub struct Metrics {
+    registry: Registry,
+    http_requests: IntCounterVec,
+    http_request_duration: HistogramVec,
+    db_query_duration: HistogramVec,
+    db_pool_connections: IntGaugeVec,
+    db_pool_max_connections: IntGauge,
+    db_replicas_healthy: IntGauge,
+    login_attempts: IntCounterVec,
+    rate_limited_requests: IntCounterVec,
+    cache_lookups: IntCounterVec,
+    notes: IntGauge,
+    users: IntGauge,
+}
+
+impl Metrics {
+    pub fn new() -> Result<Self, prometheus::Error> {
+        let registry = Registry::new();
+
+        let http_requests = IntCounterVec::new(
+            Opts::new("http_requests_total", "HTTP requests handled"),
+            &["method", "route", "status"],
+        )?;
+        let http_request_duration = HistogramVec::new(
+            HistogramOpts::new(
+                "http_request_duration_seconds",
+                "Time spent handling HTTP requests",
+            ),
+            &["method", "route", "status"],
+        )?;
+        let db_query_duration = HistogramVec::new(
+            HistogramOpts::new(
+                "db_query_duration_seconds",
+                "Time spent in repository methods",
+            ),
+            &["repository", "method"],
+        )?;
+        let db_pool_connections = IntGaugeVec::new(
+            Opts::new("db_pool_connections", "Open database connections"),
+            &["state"],
+        )?;
+        let db_pool_max_connections = IntGauge::new(
+            "db_pool_max_connections",
+            "Maximum size of the database pool",
+        )?;
+        let db_replicas_healthy =
+            IntGauge::new("db_replicas_healthy", "Read replicas currently in rotation")?;
+        let login_attempts = IntCounterVec::new(
+            Opts::new("auth_login_attempts_total", "Login attempts"),
+            &["result"],
+        )?;
+        let rate_limited_requests = IntCounterVec::new(
+            Opts::new(
+                "rate_limited_requests_total",
+                "Requests rejected by a rate limit policy",
+            ),
+            &["policy"],
+        )?;
+        let cache_lookups = IntCounterVec::new(
+            Opts::new("cache_lookups_total", "Lookups by id through the cache"),
+            &["cache", "result"],
+        )?;
+        let notes = IntGauge::new("notes_total", "Notes stored")?;
+        let users = IntGauge::new("users_total", "Registered users")?;
+
+        registry.register(Box::new(http_requests.clone()))?;
+        registry.register(Box::new(http_request_duration.clone()))?;
+        registry.register(Box::new(db_query_duration.clone()))?;
+        registry.register(Box::new(db_pool_connections.clone()))?;
+        registry.register(Box::new(db_pool_max_connections.clone()))?;
+        registry.register(Box::new(db_replicas_healthy.clone()))?;
+        registry.register(Box::new(login_attempts.clone()))?;
+        registry.register(Box::new(rate_limited_requests.clone()))?;
+        registry.register(Box::new(cache_lookups.clone()))?;
+        registry.register(Box::new(notes.clone()))?;
+        registry.register(Box::new(users.clone()))?;
+
+        Ok(Metrics {
+            registry,
+            http_requests,
+            http_request_duration,
+            db_query_duration,
+            db_pool_connections,
+            db_pool_max_connections,
+            db_replicas_healthy,
+            login_attempts,
+            rate_limited_requests,
+            cache_lookups,
+            notes,
+            users,
+        })
+    }
+
+    /// `route` must be the matched pattern (`/api/notes/{id}`), never the raw
+    /// path, to keep the number of series bounded.
+    pub fn observe_request(&self, method: &str, route: &str, status: u16, seconds: f64) {
+        let status = status.to_string();
+        let labels = [method, route, status.as_str()];
+
+        self.http_requests.with_label_values(&labels).inc();
+        self.http_request_duration
+            .with_label_values(&labels)
+            .observe(seconds);
+    }
+
+    /// Awaits `query`, recording how long it took under `repository` and
+    /// `method`.
+    pub async fn time_query<F: Future>(
+        &self,
+        repository: &str,
+        method: &str,
+        query: F,
+    ) -> F::Output {
+        let started = Instant::now();
+        let output = query.await;
+
+        self.db_query_duration
+            .with_label_values(&[repository, method])
+            .observe(started.elapsed().as_secs_f64());
+
+        output
+    }
+
+    pub fn record_login(&self, success: bool) {
+        let result = if success { "success" } else { "failure" };
+        self.login_attempts.with_label_values(&[result]).inc();
+    }
+
+    pub fn record_rate_limited(&self, policy: &str) {
+        self.rate_limited_requests
+            .with_label_values(&[policy])
+            .inc();
+    }
+
+    pub fn record_cache_lookup(&self, cache: &str, hit: bool) {
+        let result = if hit { "hit" } else { "miss" };
+        self.cache_lookups.with_label_values(&[cache, result]).inc();
+    }
+
+    pub fn set_pool_stats(&self, stats: PoolStats) {
+        self.db_pool_connections
+            .with_label_values(&["idle"])
+            .set(stats.idle as i64);
+        self.db_pool_connections
+            .with_label_values(&["in_use"])
+            .set(stats.in_use as i64);
+        self.db_pool_max_connections.set(stats.max_size as i64);
+    }
+
+    pub fn set_healthy_replicas(&self, healthy: usize) {
+        self.db_replicas_healthy.set(healthy as i64);
+    }
+
+    pub fn set_totals(&self, notes: i64, users: i64) {
+        self.notes.set(notes);
+        self.users.set(users);
+    }
+
+    /// Renders every metric in the Prometheus text exposition format.
+    pub fn render(&self) -> Result<String, prometheus::Error> {
+        let mut buffer = Vec::new();
+        TextEncoder::new().encode(&self.registry.gather(), &mut buffer)?;
+
+        String::from_utf8(buffer).map_err(|err| prometheus::Error::Msg(err.to_string()))
+    }
+}
@@ -0,0 +1,3 @@
+mod app_metrics;
+
+pub use app_metrics::Metrics;
@@ -0,0 +1,78 @@
+use std::future::{ready, Ready};
+use std::time::Instant;
+
+use actix_web::dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform};
+use actix_web::Error as ActixWebError;
+use futures_util::future::LocalBoxFuture;
+
+use super::ClientInfo;
+
+/// Logs one line per request once the response is ready. Unlike actix's
+/// `Logger`, which logs after the body has been sent, this runs inside the
+/// request, so the line carries the request ID.
+pub struct AccessLogMiddleware;
+
+impl<S, B> Transform<S, ServiceRequest> for AccessLogMiddleware
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = ActixWebError>,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = ActixWebError;
+    type Transform = AccessLogService<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(AccessLogService { service }))
+    }
+}
+
+pub struct AccessLogService<S> {
+    service: S,
+}
+
+impl<S, B> Service<ServiceRequest> for AccessLogService<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = ActixWebError>,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = ActixWebError;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let started = Instant::now();
+        let method = req.method().clone();
+        let target = req
+            .uri()
+            .path_and_query()
+            .map(|target| target.to_string())
+            .unwrap_or_default();
+        let client = ClientInfo::of(req.request())
+            .ip
+            .map_or_else(|| "-".to_string(), |ip| ip.to_string());
+
+        let fut = self.service.call(req);
+
+        Box::pin(async move {
+            let res = fut.await?;
+
+            log::info!(
+                target: "access",
+                "{} \"{} {}\" {} {:.3}ms",
+                client,
+                method,
+                target,
+                res.status().as_u16(),
+                started.elapsed().as_secs_f64() * 1000.0
+            );
+
+            Ok(res)
+        })
+    }
+}
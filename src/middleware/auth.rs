@@ -1,11 +1,10 @@
-use std::future::{ready, Ready};
-
-use actix_web::error::ErrorUnauthorized;
-use actix_web::{dev::Payload, Error as ActixWebError};
+use actix_web::dev::Payload;
 use actix_web::{http, web, FromRequest, HttpMessage, HttpRequest};
+use futures_util::future::LocalBoxFuture;
 use jsonwebtoken::{decode, DecodingKey, Validation};
 
-use crate::response::ErrorResponse;
+use crate::error::AppError;
+use crate::models::{UserModel, ADMIN_ROLE};
 use crate::schema::TokenClaims;
 use crate::service_register::ServiceRegister;
 
@@ -14,47 +13,96 @@ pub struct JwtMiddleware {
 }
 
 impl FromRequest for JwtMiddleware {
-    type Error = ActixWebError;
-    type Future = Ready<Result<Self, Self::Error>>;
+    type Error = AppError;
+    type Future = LocalBoxFuture<'static, Result<Self, Self::Error>>;
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        let data = req
+            .app_data::<web::Data<ServiceRegister>>()
+            .unwrap()
+            .clone();
+        let user_id = authenticated_user(req, data.env.auth.jwt_secret.expose());
+        let req = req.clone();
+
+        Box::pin(async move {
+            let user_id = user_id?;
+            active_user(&data, user_id).await?;
+            req.extensions_mut().insert::<uuid::Uuid>(user_id);
+
+            Ok(JwtMiddleware { user_id })
+        })
+    }
+}
+
+/// Like [`JwtMiddleware`], but only lets through users with the `admin`
+/// role that are not disabled; anyone else logged in gets 403.
+pub struct AdminUser {
+    pub user_id: uuid::Uuid,
+}
+
+impl FromRequest for AdminUser {
+    type Error = AppError;
+    type Future = LocalBoxFuture<'static, Result<Self, Self::Error>>;
     fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
-        let data = req.app_data::<web::Data<ServiceRegister>>().unwrap();
-
-        let token = req
-            .cookie("token")
-            .map(|c| c.value().to_string())
-            .or_else(|| {
-                req.headers()
-                    .get(http::header::AUTHORIZATION)
-                    .map(|h| h.to_str().unwrap().split_at(7).1.to_string())
-            });
-
-        if token.is_none() {
-            let json_error = ErrorResponse {
-                status: "fail".to_string(),
-                message: "You are not logged in, please provide token".to_string(),
-            };
-            return ready(Err(ErrorUnauthorized(json_error)));
-        }
-
-        let claims = match decode::<TokenClaims>(
-            &token.unwrap(),
-            &DecodingKey::from_secret(data.env.jwt_secret.as_ref()),
-            &Validation::default(),
-        ) {
-            Ok(c) => c.claims,
-            Err(_) => {
-                let json_error = ErrorResponse {
-                    status: "fail".to_string(),
-                    message: "Invalid token".to_string(),
-                };
-                return ready(Err(ErrorUnauthorized(json_error)));
+        let data = req
+            .app_data::<web::Data<ServiceRegister>>()
+            .unwrap()
+            .clone();
+        let user_id = authenticated_user(req, data.env.auth.jwt_secret.expose());
+
+        Box::pin(async move {
+            let user = active_user(&data, user_id?).await?;
+            if user.role != ADMIN_ROLE {
+                return Err(AppError::Forbidden("Admin access required".to_string()));
             }
-        };
 
-        let user_id = uuid::Uuid::parse_str(claims.sub.as_str()).unwrap();
-        req.extensions_mut()
-            .insert::<uuid::Uuid>(user_id.to_owned());
+            Ok(AdminUser { user_id: user.id })
+        })
+    }
+}
 
-        ready(Ok(JwtMiddleware { user_id }))
+/// The user a token was issued to, unless they were deleted or disabled
+/// since. A token stays valid until it expires, so this is checked on
+/// every request, through the user cache when it is enabled.
+async fn active_user(data: &ServiceRegister, user_id: uuid::Uuid) -> Result<UserModel, AppError> {
+    let gone = || AppError::Unauthorized("The user of this token no longer exists".to_string());
+
+    let user = data
+        .user_service
+        .find_user_by_id(user_id)
+        .await?
+        .ok_or_else(gone)?;
+    if user.disabled_at.is_some() {
+        return Err(AppError::Forbidden("This account is disabled".to_string()));
     }
+
+    Ok(user)
+}
+
+/// The user behind the `token` cookie or bearer token, if it is valid.
+pub fn authenticated_user(req: &HttpRequest, jwt_secret: &str) -> Result<uuid::Uuid, AppError> {
+    let token = req
+        .cookie("token")
+        .map(|c| c.value().to_string())
+        .or_else(|| {
+            req.headers()
+                .get(http::header::AUTHORIZATION)
+                .and_then(|h| h.to_str().ok())
+                .and_then(|h| h.strip_prefix("Bearer "))
+                .map(|token| token.to_string())
+        })
+        .ok_or_else(|| {
+            AppError::Unauthorized("You are not logged in, please provide token".to_string())
+        })?;
+
+    let invalid_token = || AppError::Unauthorized("Invalid token".to_string());
+
+    let claims = decode::<TokenClaims>(
+        &token,
+        &DecodingKey::from_secret(jwt_secret.as_ref()),
+        &Validation::default(),
+    )
+    .map_err(|_| invalid_token())?
+    .claims;
+
+    uuid::Uuid::parse_str(claims.sub.as_str()).map_err(|_| invalid_token())
 }
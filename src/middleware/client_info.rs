@@ -0,0 +1,381 @@
+use std::convert::Infallible;
+use std::future::{ready, Ready};
+use std::net::{IpAddr, SocketAddr};
+
+use actix_web::http::header::{self, HeaderMap};
+use actix_web::{dev::Payload, web, FromRequest, HttpMessage, HttpRequest};
+
+use crate::config::IpNetwork;
+use crate::service_register::ServiceRegister;
+
+const X_FORWARDED_FOR: &str = "x-forwarded-for";
+const X_FORWARDED_PROTO: &str = "x-forwarded-proto";
+const X_FORWARDED_HOST: &str = "x-forwarded-host";
+const X_REAL_IP: &str = "x-real-ip";
+
+/// Where a request really came from and which URL the client used.
+///
+/// Forwarding headers are only read when the connection comes from one of
+/// `server.trusted_proxies`; anyone else could set them to anything. The
+/// forwarded chain is then walked back from the nearest proxy until the
+/// first address that is not a trusted proxy, which is the client.
+/// `Forwarded` takes precedence over `X-Forwarded-For`, which takes
+/// precedence over `X-Real-IP`.
+#[derive(Debug, Clone)]
+pub struct ClientInfo {
+    /// `None` only when the peer address is unknown, e.g. in some tests.
+    pub ip: Option<IpAddr>,
+    pub scheme: String,
+    pub host: String,
+}
+
+impl ClientInfo {
+    /// Resolves the client of `req`, caching the result in the request
+    /// extensions.
+    pub fn of(req: &HttpRequest) -> ClientInfo {
+        if let Some(info) = req.extensions().get::<ClientInfo>() {
+            return info.clone();
+        }
+
+        let trusted_proxies = req
+            .app_data::<web::Data<ServiceRegister>>()
+            .map(|data| data.env.server.trusted_proxies.as_slice())
+            .unwrap_or_default();
+        let info = resolve(req, trusted_proxies);
+        req.extensions_mut().insert(info.clone());
+
+        info
+    }
+
+    /// `scheme://host` as seen by the client, for building absolute links.
+    pub fn base_url(&self) -> String {
+        format!("{}://{}", self.scheme, self.host)
+    }
+}
+
+impl FromRequest for ClientInfo {
+    type Error = Infallible;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        ready(Ok(ClientInfo::of(req)))
+    }
+}
+
+/// One proxy hop: the address it received the request from, and the scheme
+/// and host that request used.
+#[derive(Debug, Default)]
+struct Hop {
+    ip: Option<IpAddr>,
+    proto: Option<String>,
+    host: Option<String>,
+}
+
+fn resolve(req: &HttpRequest, trusted_proxies: &[IpNetwork]) -> ClientInfo {
+    let peer = req.peer_addr().map(|addr| addr.ip().to_canonical());
+    let direct = ClientInfo {
+        ip: peer,
+        scheme: if req.app_config().secure() {
+            "https"
+        } else {
+            "http"
+        }
+        .to_string(),
+        host: direct_host(req),
+    };
+
+    let is_trusted = |ip: IpAddr| trusted_proxies.iter().any(|network| network.contains(ip));
+    if !peer.is_some_and(is_trusted) {
+        return direct;
+    }
+
+    let hops = forwarded_hops(req.headers());
+    let mut client = None;
+    for hop in hops.iter().rev() {
+        // An unknown or obfuscated address ends the chain we can follow.
+        let Some(ip) = hop.ip else { break };
+        client = Some(hop);
+        if !is_trusted(ip) {
+            break;
+        }
+    }
+
+    match client {
+        Some(hop) => ClientInfo {
+            ip: hop.ip,
+            scheme: hop.proto.clone().unwrap_or(direct.scheme),
+            host: hop.host.clone().unwrap_or(direct.host),
+        },
+        None => direct,
+    }
+}
+
+fn direct_host(req: &HttpRequest) -> String {
+    req.headers()
+        .get(header::HOST)
+        .and_then(|host| host.to_str().ok())
+        .map(str::to_string)
+        .or_else(|| req.uri().authority().map(|authority| authority.to_string()))
+        .unwrap_or_else(|| req.app_config().host().to_string())
+}
+
+fn forwarded_hops(headers: &HeaderMap) -> Vec<Hop> {
+    let forwarded = header_list(headers, header::FORWARDED.as_str());
+    if !forwarded.is_empty() {
+        return forwarded
+            .iter()
+            .map(|element| parse_forwarded(element))
+            .collect();
+    }
+
+    let mut addresses = header_list(headers, X_FORWARDED_FOR);
+    if addresses.is_empty() {
+        addresses = header_list(headers, X_REAL_IP);
+    }
+    let protos = header_list(headers, X_FORWARDED_PROTO);
+    let hosts = header_list(headers, X_FORWARDED_HOST);
+
+    // Proxies that append to X-Forwarded-For may or may not append to the
+    // proto and host lists, so line them up from the right. A hop without an
+    // entry of its own gets none: the leftmost values may come from the
+    // client.
+    let aligned = |values: &[String], from_right: usize| {
+        let index = values.len().checked_sub(from_right + 1)?;
+        values.get(index).cloned()
+    };
+
+    let count = addresses.len();
+    addresses
+        .iter()
+        .enumerate()
+        .map(|(index, address)| Hop {
+            ip: parse_node(address),
+            proto: aligned(&protos, count - index - 1)
+                .filter(|proto| is_valid_proto(proto))
+                .map(|proto| proto.to_ascii_lowercase()),
+            host: aligned(&hosts, count - index - 1).filter(|host| is_valid_host(host)),
+        })
+        .collect()
+}
+
+/// Comma separated values of every `name` header, in order.
+fn header_list(headers: &HeaderMap, name: &str) -> Vec<String> {
+    headers
+        .get_all(name)
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .map(|value| value.trim().to_string())
+        .filter(|value| !value.is_empty())
+        .collect()
+}
+
+/// One `Forwarded` element, e.g. `for=192.0.2.60;proto=https;host=example.com`.
+fn parse_forwarded(element: &str) -> Hop {
+    let mut hop = Hop::default();
+
+    for pair in element.split(';') {
+        let Some((key, value)) = pair.split_once('=') else {
+            continue;
+        };
+        let value = value.trim().trim_matches('"');
+        match key.trim().to_ascii_lowercase().as_str() {
+            "for" => hop.ip = parse_node(value),
+            "proto" if is_valid_proto(value) => hop.proto = Some(value.to_ascii_lowercase()),
+            "host" if is_valid_host(value) => hop.host = Some(value.to_string()),
+            _ => {}
+        }
+    }
+
+    hop
+}
+
+/// An address with an optional port: `192.0.2.60`, `192.0.2.60:4711`,
+/// `2001:db8::1` or `[2001:db8::1]:4711`. `unknown` and obfuscated
+/// identifiers give `None`.
+fn parse_node(node: &str) -> Option<IpAddr> {
+    let node = node.trim().trim_matches('"');
+
+    node.parse::<IpAddr>()
+        .or_else(|_| node.parse::<SocketAddr>().map(|addr| addr.ip()))
+        .or_else(|_| {
+            node.trim_start_matches('[')
+                .trim_end_matches(']')
+                .parse::<IpAddr>()
+        })
+        .ok()
+        .map(|ip| ip.to_canonical())
+}
+
+fn is_valid_proto(proto: &str) -> bool {
+    proto.eq_ignore_ascii_case("http") || proto.eq_ignore_ascii_case("https")
+}
+
+fn is_valid_host(host: &str) -> bool {
+    !host.is_empty()
+        && host
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '.' | ':' | '[' | ']'))
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::test::TestRequest;
+
+    use super::*;
+
+    fn ip(value: &str) -> IpAddr {
+        value.parse().unwrap()
+    }
+
+    fn networks(values: &[&str]) -> Vec<IpNetwork> {
+        values.iter().map(|value| value.parse().unwrap()).collect()
+    }
+
+    /// Resolves a request from `peer` with `headers`, trusting `trusted`.
+    fn client(peer: &str, headers: &[(&str, &str)], trusted: &[&str]) -> ClientInfo {
+        let mut req = TestRequest::get()
+            .uri("/api/notes")
+            .insert_header((header::HOST, "internal:8000"))
+            .peer_addr(SocketAddr::new(ip(peer), 40000));
+        for (name, value) in headers {
+            req = req.append_header((*name, *value));
+        }
+
+        resolve(&req.to_http_request(), &networks(trusted))
+    }
+
+    #[test]
+    fn parses_forwarded_elements() {
+        let hop = parse_forwarded(r#"for="[2001:db8::1]:4711";Proto=HTTPS;host="api.example.com""#);
+        assert_eq!(hop.ip, Some(ip("2001:db8::1")));
+        assert_eq!(hop.proto.as_deref(), Some("https"));
+        assert_eq!(hop.host.as_deref(), Some("api.example.com"));
+
+        let hop = parse_forwarded("for=192.0.2.60:8080;proto=gopher;host=bad/host");
+        assert_eq!(hop.ip, Some(ip("192.0.2.60")));
+        assert_eq!((hop.proto, hop.host), (None, None));
+
+        assert_eq!(parse_forwarded("for=unknown").ip, None);
+        assert_eq!(parse_forwarded("for=_hidden").ip, None);
+        assert_eq!(parse_forwarded("by=10.0.0.1").ip, None);
+    }
+
+    #[test]
+    fn parses_nodes_with_and_without_ports() {
+        assert_eq!(parse_node("192.0.2.60"), Some(ip("192.0.2.60")));
+        assert_eq!(parse_node("192.0.2.60:4711"), Some(ip("192.0.2.60")));
+        assert_eq!(parse_node("2001:db8::1"), Some(ip("2001:db8::1")));
+        assert_eq!(parse_node("[2001:db8::1]"), Some(ip("2001:db8::1")));
+        assert_eq!(
+            parse_node("\"[2001:db8::1]:4711\""),
+            Some(ip("2001:db8::1"))
+        );
+        assert_eq!(parse_node("::ffff:192.0.2.60"), Some(ip("192.0.2.60")));
+        assert_eq!(parse_node("unknown"), None);
+        assert_eq!(parse_node("_gazonk"), None);
+    }
+
+    #[test]
+    fn ignores_forwarding_headers_from_untrusted_peers() {
+        let info = client(
+            "203.0.113.9",
+            &[
+                ("x-forwarded-for", "198.51.100.1"),
+                ("x-forwarded-host", "evil.example"),
+            ],
+            &["10.0.0.0/8"],
+        );
+
+        assert_eq!(info.ip, Some(ip("203.0.113.9")));
+        assert_eq!(info.base_url(), "http://internal:8000");
+    }
+
+    #[test]
+    fn walks_the_chain_back_to_the_first_untrusted_address() {
+        // The client prepended a spoofed address; the walk stops at the
+        // address the outer proxy saw.
+        let info = client(
+            "10.0.0.2",
+            &[
+                ("x-forwarded-for", "1.1.1.1, 198.51.100.1, 10.0.0.1"),
+                ("x-forwarded-proto", "https"),
+            ],
+            &["10.0.0.0/8"],
+        );
+        assert_eq!(info.ip, Some(ip("198.51.100.1")));
+
+        let info = client(
+            "10.0.0.2",
+            &[(
+                "forwarded",
+                r#"for=1.1.1.1;host=evil.example, for=198.51.100.1;proto=https;host=api.example.com"#,
+            )],
+            &["10.0.0.0/8"],
+        );
+        assert_eq!(info.ip, Some(ip("198.51.100.1")));
+        assert_eq!(info.base_url(), "https://api.example.com");
+    }
+
+    #[test]
+    fn forwarded_takes_precedence_over_x_forwarded_for_and_x_real_ip() {
+        let headers = [
+            ("forwarded", "for=198.51.100.1"),
+            ("x-forwarded-for", "198.51.100.2"),
+            ("x-real-ip", "198.51.100.3"),
+        ];
+        let trusted = ["10.0.0.1"];
+
+        assert_eq!(
+            client("10.0.0.1", &headers, &trusted).ip,
+            Some(ip("198.51.100.1"))
+        );
+        assert_eq!(
+            client("10.0.0.1", &headers[1..], &trusted).ip,
+            Some(ip("198.51.100.2"))
+        );
+        assert_eq!(
+            client("10.0.0.1", &headers[2..], &trusted).ip,
+            Some(ip("198.51.100.3"))
+        );
+    }
+
+    #[test]
+    fn an_unknown_hop_ends_the_chain() {
+        let info = client(
+            "10.0.0.2",
+            &[("forwarded", "for=198.51.100.1, for=unknown, for=10.0.0.1")],
+            &["10.0.0.0/8"],
+        );
+
+        assert_eq!(info.ip, Some(ip("10.0.0.1")));
+    }
+
+    #[test]
+    fn hops_without_their_own_proto_or_host_use_the_connection() {
+        // Only the client sent X-Forwarded-Host; the trusted proxy appended
+        // to X-Forwarded-For alone.
+        let info = client(
+            "10.0.0.2",
+            &[
+                ("x-forwarded-for", "198.51.100.1, 10.0.0.1"),
+                ("x-forwarded-host", "evil.example"),
+                ("x-forwarded-proto", "https"),
+            ],
+            &["10.0.0.0/8"],
+        );
+
+        assert_eq!(info.ip, Some(ip("198.51.100.1")));
+        assert_eq!(info.base_url(), "http://internal:8000");
+
+        let info = client(
+            "10.0.0.2",
+            &[
+                ("x-forwarded-for", "198.51.100.1"),
+                ("x-forwarded-host", "api.example.com"),
+                ("x-forwarded-proto", "https"),
+            ],
+            &["10.0.0.0/8"],
+        );
+        assert_eq!(info.base_url(), "https://api.example.com");
+    }
+}
@@ -0,0 +1,82 @@
+use actix_cors::Cors;
+
+use crate::config::CorsConfig;
+
+/// Builds the CORS middleware from `config`, which has already been
+/// validated by [`Config::load`](crate::config::Config::load).
+pub fn cors(config: &CorsConfig) -> Cors {
+    let mut cors = Cors::default();
+
+    if config.allowed_origins.iter().any(|origin| origin == "*") {
+        cors = cors.allow_any_origin();
+    } else {
+        let patterns: Vec<OriginPattern> = config
+            .allowed_origins
+            .iter()
+            .map(|origin| OriginPattern::new(origin))
+            .collect();
+        cors = cors.allowed_origin_fn(move |origin, _| {
+            let Ok(origin) = origin.to_str() else {
+                return false;
+            };
+            let origin = origin.to_ascii_lowercase();
+            patterns.iter().any(|pattern| pattern.matches(&origin))
+        });
+    }
+
+    cors = cors.allowed_methods(config.allowed_methods.iter().map(String::as_str));
+
+    if config.allowed_headers.iter().any(|header| header == "*") {
+        cors = cors.allow_any_header();
+    } else {
+        cors = cors.allowed_headers(config.allowed_headers.iter().map(String::as_str));
+    }
+
+    if !config.exposed_headers.is_empty() {
+        cors = cors.expose_headers(config.exposed_headers.iter().map(String::as_str));
+    }
+    if config.allow_credentials {
+        cors = cors.supports_credentials();
+    }
+
+    cors.max_age(config.max_age_secs)
+}
+
+/// An allowed origin, compared case-insensitively.
+enum OriginPattern {
+    Exact(String),
+    /// `https://*.example.com` matches any subdomain of `example.com`, at any
+    /// depth, but not `example.com` itself.
+    Subdomain {
+        scheme: String,
+        suffix: String,
+    },
+}
+
+impl OriginPattern {
+    fn new(origin: &str) -> Self {
+        let origin = origin.to_ascii_lowercase();
+        match origin.split_once("://*.") {
+            Some((scheme, domain)) => OriginPattern::Subdomain {
+                scheme: format!("{}://", scheme),
+                suffix: format!(".{}", domain),
+            },
+            None => OriginPattern::Exact(origin),
+        }
+    }
+
+    fn matches(&self, origin: &str) -> bool {
+        match self {
+            OriginPattern::Exact(allowed) => origin == allowed,
+            OriginPattern::Subdomain { scheme, suffix } => origin
+                .strip_prefix(scheme.as_str())
+                .and_then(|host| host.strip_suffix(suffix.as_str()))
+                .is_some_and(|subdomain| {
+                    !subdomain.is_empty()
+                        && subdomain
+                            .chars()
+                            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '.')
+                }),
+        }
+    }
+}
@@ -0,0 +1,94 @@
+use actix_web::body::BoxBody;
+use actix_web::dev::ServiceResponse;
+use actix_web::error::{JsonPayloadError, PathError, QueryPayloadError};
+use actix_web::http::header::{self, HeaderValue};
+use actix_web::middleware::{ErrorHandlerResponse, ErrorHandlers};
+use actix_web::{web, Error as ActixWebError, HttpMessage, HttpRequest};
+
+use crate::config::ErrorFormat;
+use crate::error::AppError;
+use crate::response::{ProblemDetails, PROBLEM_JSON};
+use crate::service_register::ServiceRegister;
+
+use super::RequestId;
+
+/// Rewrites every 4xx/5xx response into the configured error format, adding
+/// the request path and ID. Errors raised as [`AppError`] keep their type and
+/// detail; anything else (unmatched routes, actix built-in errors) is
+/// described from its status code. Responses a handler built on purpose,
+/// such as the readiness report, already have a content type and are left
+/// alone.
+pub fn error_handlers<B: 'static>() -> ErrorHandlers<B> {
+    ErrorHandlers::new().default_handler(render_error)
+}
+
+fn render_error<B>(res: ServiceResponse<B>) -> actix_web::Result<ErrorHandlerResponse<B>> {
+    let status = res.status();
+
+    if res.response().error().is_none() && res.headers().contains_key(header::CONTENT_TYPE) {
+        return Ok(ErrorHandlerResponse::Response(res.map_into_left_body()));
+    }
+
+    let mut problem = match res.response().error() {
+        Some(err) => match err.as_error::<AppError>() {
+            Some(app_error) => app_error.problem(),
+            None if status.is_client_error() => ProblemDetails::new(status, err.to_string()),
+            None => ProblemDetails::new(status, "Internal server error"),
+        },
+        None => {
+            let title = status.canonical_reason().unwrap_or("Error");
+            ProblemDetails::new(status, title)
+        }
+    };
+
+    let req = res.request();
+    problem.instance = Some(req.path().to_string());
+    problem.request_id = req.extensions().get::<RequestId>().map(|id| id.0.clone());
+
+    let error_format = req
+        .app_data::<web::Data<ServiceRegister>>()
+        .map(|data| data.env.server.error_format)
+        .unwrap_or(ErrorFormat::Problem);
+
+    let (body, content_type) = match error_format {
+        ErrorFormat::Problem => (serde_json::to_string(&problem), PROBLEM_JSON),
+        ErrorFormat::Legacy => (
+            serde_json::to_string(&problem.into_legacy()),
+            "application/json",
+        ),
+    };
+    let body = body.map_err(actix_web::error::ErrorInternalServerError)?;
+
+    let (req, mut res) = res.into_parts();
+    res.headers_mut()
+        .insert(header::CONTENT_TYPE, HeaderValue::from_static(content_type));
+    let res = res.set_body(BoxBody::new(body));
+
+    Ok(ErrorHandlerResponse::Response(
+        ServiceResponse::new(req, res).map_into_right_body(),
+    ))
+}
+
+pub fn json_error_handler(err: JsonPayloadError, _: &HttpRequest) -> ActixWebError {
+    match err {
+        JsonPayloadError::Deserialize(err) => {
+            AppError::BadRequest(format!("Invalid JSON body: {}", err)).into()
+        }
+        JsonPayloadError::Serialize(err) => AppError::internal(err).into(),
+        err => err.into(),
+    }
+}
+
+pub fn path_error_handler(err: PathError, _: &HttpRequest) -> ActixWebError {
+    let PathError::Deserialize(err) = err else {
+        return err.into();
+    };
+    AppError::BadRequest(format!("Invalid path parameter: {}", err)).into()
+}
+
+pub fn query_error_handler(err: QueryPayloadError, _: &HttpRequest) -> ActixWebError {
+    let QueryPayloadError::Deserialize(err) = err else {
+        return err.into();
+    };
+    AppError::BadRequest(format!("Invalid query string: {}", err)).into()
+}
@@ -0,0 +1,72 @@
+use std::future::{ready, Ready};
+use std::time::Instant;
+
+use actix_web::dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform};
+use actix_web::{web, Error as ActixWebError};
+use futures_util::future::LocalBoxFuture;
+
+use crate::service_register::ServiceRegister;
+
+/// Route label for requests that matched no route, so scanners probing
+/// random paths cannot create new series.
+const UNMATCHED_ROUTE: &str = "unmatched";
+
+/// Counts requests and records their latency, labelled by method, matched
+/// route pattern and status.
+pub struct MetricsMiddleware;
+
+impl<S, B> Transform<S, ServiceRequest> for MetricsMiddleware
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = ActixWebError>,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = ActixWebError;
+    type Transform = MetricsService<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(MetricsService { service }))
+    }
+}
+
+pub struct MetricsService<S> {
+    service: S,
+}
+
+impl<S, B> Service<ServiceRequest> for MetricsService<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = ActixWebError>,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = ActixWebError;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let started = Instant::now();
+        let fut = self.service.call(req);
+
+        Box::pin(async move {
+            let res = fut.await?;
+
+            let req = res.request();
+            if let Some(data) = req.app_data::<web::Data<ServiceRegister>>() {
+                let route = req.match_pattern();
+                data.metrics.observe_request(
+                    req.method().as_str(),
+                    route.as_deref().unwrap_or(UNMATCHED_ROUTE),
+                    res.status().as_u16(),
+                    started.elapsed().as_secs_f64(),
+                );
+            }
+
+            Ok(res)
+        })
+    }
+}
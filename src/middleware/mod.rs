@@ -1,3 +1,25 @@
+mod access_log;
 mod auth;
+mod client_info;
+mod cors;
+mod error_handler;
+mod metrics;
+mod rate_limit;
+mod read_consistency;
+mod request_id;
+mod request_span;
+mod validated_json;
 
-pub use auth::JwtMiddleware;
+pub use access_log::AccessLogMiddleware;
+pub use auth::{AdminUser, JwtMiddleware};
+pub use client_info::ClientInfo;
+pub use cors::cors;
+pub use error_handler::{
+    error_handlers, json_error_handler, path_error_handler, query_error_handler,
+};
+pub use metrics::MetricsMiddleware;
+pub use rate_limit::RateLimitMiddleware;
+pub use read_consistency::ReadConsistencyMiddleware;
+pub use request_id::{RequestId, RequestIdMiddleware};
+pub use request_span::TracingMiddleware;
+pub use validated_json::ValidatedJson;
@@ -0,0 +1,296 @@
+use std::future::{ready, Ready};
+use std::rc::Rc;
+
+use actix_web::body::EitherBody;
+use actix_web::dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform};
+use actix_web::http::header::{self, HeaderMap, HeaderName, HeaderValue};
+use actix_web::{web, Error as ActixWebError};
+use blake2::{Blake2s256, Digest};
+use futures_util::future::LocalBoxFuture;
+
+use crate::config::{RateLimitKey, RateLimitPolicyConfig};
+use crate::error::AppError;
+use crate::service::RateLimitDecision;
+use crate::service_register::ServiceRegister;
+
+use super::auth::authenticated_user;
+use super::ClientInfo;
+
+/// Counts requests against the first matching rate limit policy and rejects
+/// them with 429 once the bucket is empty. Responses to limited routes carry
+/// `RateLimit-Limit`, `RateLimit-Remaining`, `RateLimit-Reset` and
+/// `RateLimit-Policy`, plus `Retry-After` on a 429. If the store fails the
+/// request is let through, so an outage of the limiter is not an outage of
+/// the API.
+pub struct RateLimitMiddleware;
+
+impl<S, B> Transform<S, ServiceRequest> for RateLimitMiddleware
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = ActixWebError> + 'static,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = ActixWebError;
+    type Transform = RateLimitService<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(RateLimitService {
+            service: Rc::new(service),
+        }))
+    }
+}
+
+pub struct RateLimitService<S> {
+    service: Rc<S>,
+}
+
+impl<S, B> Service<ServiceRequest> for RateLimitService<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = ActixWebError> + 'static,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = ActixWebError;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let service = self.service.clone();
+        let register = req.app_data::<web::Data<ServiceRegister>>().cloned();
+        let policy = register.as_ref().and_then(|register| {
+            register
+                .rate_limiter
+                .policy_for(req.method(), req.path())
+                .cloned()
+        });
+
+        let (Some(register), Some(policy)) = (register, policy) else {
+            return Box::pin(async move {
+                let res = service.call(req).await?;
+                Ok(res.map_into_left_body())
+            });
+        };
+
+        let subject = subject(&req, &policy, &register);
+
+        Box::pin(async move {
+            let decision = match register.rate_limiter.acquire(&policy, &subject).await {
+                Ok(decision) => Some(decision),
+                Err(err) => {
+                    log::warn!("Rate limit store unavailable, not limiting: {}", err);
+                    None
+                }
+            };
+
+            if let Some(decision) = decision.as_ref().filter(|decision| !decision.allowed) {
+                register.metrics.record_rate_limited(&policy.name);
+
+                let (req, _) = req.into_parts();
+                let error = AppError::TooManyRequests(format!(
+                    "Too many requests, retry in {} seconds",
+                    decision.retry_after_secs
+                ));
+                let mut res = ServiceResponse::from_err(error, req);
+                insert_headers(res.headers_mut(), decision);
+                res.headers_mut()
+                    .insert(header::RETRY_AFTER, decision.retry_after_secs.into());
+
+                return Ok(res.map_into_right_body());
+            }
+
+            let mut res = service.call(req).await?;
+            if let Some(decision) = &decision {
+                insert_headers(res.headers_mut(), decision);
+            }
+
+            Ok(res.map_into_left_body())
+        })
+    }
+}
+
+/// Who the request is counted for. API keys are hashed so they are never
+/// stored as is.
+fn subject(
+    req: &ServiceRequest,
+    policy: &RateLimitPolicyConfig,
+    register: &ServiceRegister,
+) -> String {
+    match policy.key {
+        RateLimitKey::User => return caller(req, register),
+        RateLimitKey::ApiKey => {
+            let api_key = req
+                .headers()
+                .get(register.env.rate_limit.api_key_header.as_str())
+                .filter(|value| !value.is_empty());
+            if let Some(api_key) = api_key {
+                return format!("key:{:x}", Blake2s256::digest(api_key.as_bytes()));
+            }
+        }
+        RateLimitKey::Ip => {}
+    }
+
+    client_ip(req)
+}
+
+/// The logged in user, or the client IP for anonymous requests.
+pub(super) fn caller(req: &ServiceRequest, register: &ServiceRegister) -> String {
+    let jwt_secret = register.env.auth.jwt_secret.expose();
+    match authenticated_user(req.request(), jwt_secret) {
+        Ok(user_id) => format!("user:{}", user_id),
+        Err(_) => client_ip(req),
+    }
+}
+
+fn client_ip(req: &ServiceRequest) -> String {
+    let ip = ClientInfo::of(req.request())
+        .ip
+        .map_or_else(|| "unknown".to_string(), |ip| ip.to_string());
+    format!("ip:{}", ip)
+}
+
+fn insert_headers(headers: &mut HeaderMap, decision: &RateLimitDecision) {
+    let values = [
+        ("ratelimit-limit", decision.limit.to_string()),
+        ("ratelimit-remaining", decision.remaining.to_string()),
+        ("ratelimit-reset", decision.reset_secs.to_string()),
+        (
+            "ratelimit-policy",
+            format!("{};w={}", decision.limit, decision.window_secs),
+        ),
+    ];
+
+    for (name, value) in values {
+        if let Ok(value) = HeaderValue::from_str(&value) {
+            headers.insert(HeaderName::from_static(name), value);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::http::{header, StatusCode};
+    use actix_web::test::{self, TestRequest};
+    use serde_json::{json, Value};
+
+    use crate::config::{RateLimitKey, RateLimitPolicyConfig};
+    use crate::handler::test_support::{init_app, register};
+    use crate::service_register::ServiceRegister;
+
+    /// One request per minute for everything under `path_prefix`.
+    fn limited(path_prefix: &str, key: RateLimitKey) -> ServiceRegister {
+        register(|config| {
+            config.rate_limit.enabled = true;
+            config.rate_limit.policies = vec![RateLimitPolicyConfig {
+                name: "limited".to_string(),
+                path_prefix: path_prefix.to_string(),
+                methods: Vec::new(),
+                key,
+                requests: 1,
+                per_secs: 60,
+            }];
+        })
+    }
+
+    fn from(ip: &str, uri: &str) -> TestRequest {
+        TestRequest::get()
+            .uri(uri)
+            .peer_addr(format!("{}:40000", ip).parse().unwrap())
+    }
+
+    #[actix_web::test]
+    async fn rejects_with_retry_after_once_the_bucket_is_empty() {
+        let app = init_app!(limited("/api/notes", RateLimitKey::Ip));
+
+        let res = test::call_service(&app, from("10.0.0.1", "/api/notes").to_request()).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(res.headers().get("ratelimit-policy").unwrap(), "1;w=60");
+        assert!(!res.headers().contains_key(header::RETRY_AFTER));
+
+        let res = test::call_service(&app, from("10.0.0.1", "/api/notes").to_request()).await;
+        assert_eq!(res.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(res.headers().get(header::RETRY_AFTER).unwrap(), "60");
+        assert_eq!(res.headers().get("ratelimit-remaining").unwrap(), "0");
+
+        // Other clients have their own bucket, other routes are not limited.
+        let res = test::call_service(&app, from("10.0.0.2", "/api/notes").to_request()).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        let res = test::call_service(&app, from("10.0.0.1", "/health/live").to_request()).await;
+        assert_eq!(res.status(), StatusCode::OK);
+    }
+
+    #[actix_web::test]
+    async fn user_policies_count_logged_in_users_separately() {
+        let app = init_app!(limited("/api/users", RateLimitKey::User));
+
+        let mut tokens = Vec::new();
+        for email in ["ada@example.com", "grace@example.com"] {
+            let credentials = json!({ "email": email, "password": "Tr0ubadour-Horse" });
+            let mut user = credentials.clone();
+            user["firstname"] = "Ada".into();
+            user["lastname"] = "Lovelace".into();
+            let req = TestRequest::post()
+                .uri("/api/auth/register")
+                .set_json(user)
+                .to_request();
+            assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK);
+
+            let req = TestRequest::post()
+                .uri("/api/auth/login")
+                .set_json(credentials)
+                .to_request();
+            let body: Value = test::call_and_read_body_json(&app, req).await;
+            tokens.push(body["token"].as_str().unwrap().to_string());
+        }
+        let me = |token: &str| {
+            from("10.0.0.1", "/api/users/me")
+                .insert_header((header::AUTHORIZATION, format!("Bearer {}", token)))
+                .to_request()
+        };
+
+        let res = test::call_service(&app, me(&tokens[0])).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        let res = test::call_service(&app, me(&tokens[0])).await;
+        assert_eq!(res.status(), StatusCode::TOO_MANY_REQUESTS);
+
+        // Same address, different user.
+        let res = test::call_service(&app, me(&tokens[1])).await;
+        assert_eq!(res.status(), StatusCode::OK);
+
+        // Anonymous requests fall back to the address.
+        let res = test::call_service(&app, from("10.0.0.1", "/api/users/me").to_request()).await;
+        assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+        let res = test::call_service(&app, from("10.0.0.1", "/api/users/me").to_request()).await;
+        assert_eq!(res.status(), StatusCode::TOO_MANY_REQUESTS);
+    }
+
+    #[actix_web::test]
+    async fn api_key_policies_count_keys_separately() {
+        let app = init_app!(limited("/api/notes", RateLimitKey::ApiKey));
+        let with_key = |key: &str| {
+            from("10.0.0.1", "/api/notes")
+                .insert_header(("x-api-key", key))
+                .to_request()
+        };
+
+        assert_eq!(
+            test::call_service(&app, with_key("a")).await.status(),
+            StatusCode::OK
+        );
+        assert_eq!(
+            test::call_service(&app, with_key("a")).await.status(),
+            StatusCode::TOO_MANY_REQUESTS
+        );
+        assert_eq!(
+            test::call_service(&app, with_key("b")).await.status(),
+            StatusCode::OK
+        );
+
+        let res = test::call_service(&app, from("10.0.0.1", "/api/notes").to_request()).await;
+        assert_eq!(res.status(), StatusCode::OK);
+    }
+}
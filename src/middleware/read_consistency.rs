@@ -0,0 +1,79 @@
+use std::future::{ready, Ready};
+use std::rc::Rc;
+
+use actix_web::dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform};
+use actix_web::{web, Error as ActixWebError};
+use futures_util::future::LocalBoxFuture;
+
+use crate::repository::pin_reads;
+use crate::service_register::ServiceRegister;
+
+use super::rate_limit::caller;
+
+/// Keeps reads on the primary database for callers that wrote recently, so
+/// they see their own changes while the replicas catch up. Writes are
+/// requests with an unsafe method (`POST`, `PATCH`, ...) that succeeded;
+/// the caller is the logged in user, or the client IP when anonymous. Does
+/// nothing without read replicas.
+pub struct ReadConsistencyMiddleware;
+
+impl<S, B> Transform<S, ServiceRequest> for ReadConsistencyMiddleware
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = ActixWebError> + 'static,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = ActixWebError;
+    type Transform = ReadConsistencyService<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(ReadConsistencyService {
+            service: Rc::new(service),
+        }))
+    }
+}
+
+pub struct ReadConsistencyService<S> {
+    service: Rc<S>,
+}
+
+impl<S, B> Service<ServiceRequest> for ReadConsistencyService<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = ActixWebError> + 'static,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = ActixWebError;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let service = self.service.clone();
+        let tracked = req
+            .app_data::<web::Data<ServiceRegister>>()
+            .and_then(|register| {
+                let read_your_writes = register.read_your_writes.clone()?;
+                Some((caller(&req, register), read_your_writes))
+            });
+        let Some((caller, read_your_writes)) = tracked else {
+            return Box::pin(service.call(req));
+        };
+
+        let writes = !req.method().is_safe();
+        let pinned = writes || read_your_writes.is_pinned(&caller);
+
+        Box::pin(async move {
+            let res = pin_reads(pinned, service.call(req)).await?;
+            if writes && res.status().is_success() {
+                read_your_writes.record_write(&caller);
+            }
+
+            Ok(res)
+        })
+    }
+}
@@ -0,0 +1,119 @@
+use std::future::{ready, Ready};
+
+use actix_web::dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform};
+use actix_web::http::header::HeaderMap;
+use actix_web::{Error as ActixWebError, HttpMessage};
+use futures_util::future::LocalBoxFuture;
+use opentelemetry::global;
+use opentelemetry::propagation::Extractor;
+use tracing::field::Empty;
+use tracing::Instrument;
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+
+use super::{ClientInfo, RequestId};
+
+/// Opens a server span for every request, continuing the trace from an
+/// incoming W3C `traceparent` header when there is one. Spans created by
+/// handlers, services and repositories nest under it.
+pub struct TracingMiddleware;
+
+impl<S, B> Transform<S, ServiceRequest> for TracingMiddleware
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = ActixWebError>,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = ActixWebError;
+    type Transform = TracingService<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(TracingService { service }))
+    }
+}
+
+pub struct TracingService<S> {
+    service: S,
+}
+
+impl<S, B> Service<ServiceRequest> for TracingService<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = ActixWebError>,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = ActixWebError;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let request_id = req
+            .extensions()
+            .get::<RequestId>()
+            .map(|id| id.0.clone())
+            .unwrap_or_default();
+
+        let client_ip = ClientInfo::of(req.request())
+            .ip
+            .map(|ip| ip.to_string())
+            .unwrap_or_default();
+
+        // The route is only known once the request has been matched, so the
+        // span starts out named after the method and is renamed below.
+        let span = tracing::info_span!(
+            "HTTP request",
+            otel.name = req.method().as_str(),
+            otel.kind = "server",
+            otel.status_code = Empty,
+            http.method = req.method().as_str(),
+            http.route = Empty,
+            http.target = req.path(),
+            http.client_ip = client_ip.as_str(),
+            http.status_code = Empty,
+            request_id = request_id.as_str(),
+        );
+
+        let parent = global::get_text_map_propagator(|propagator| {
+            propagator.extract(&HeaderExtractor(req.headers()))
+        });
+        span.set_parent(parent);
+
+        let fut = self.service.call(req);
+        let request_span = span.clone();
+
+        Box::pin(
+            async move {
+                let res = fut.await?;
+
+                if let Some(route) = res.request().match_pattern() {
+                    let name = format!("{} {}", res.request().method(), route);
+                    request_span.record("otel.name", name.as_str());
+                    request_span.record("http.route", route.as_str());
+                }
+                request_span.record("http.status_code", res.status().as_u16());
+                if res.status().is_server_error() {
+                    request_span.record("otel.status_code", "ERROR");
+                }
+
+                Ok(res)
+            }
+            .instrument(span),
+        )
+    }
+}
+
+struct HeaderExtractor<'a>(&'a HeaderMap);
+
+impl Extractor for HeaderExtractor<'_> {
+    fn get(&self, key: &str) -> Option<&str> {
+        self.0.get(key).and_then(|value| value.to_str().ok())
+    }
+
+    fn keys(&self) -> Vec<&str> {
+        self.0.keys().map(|name| name.as_str()).collect()
+    }
+}
@@ -0,0 +1,17 @@
+use serde::Deserialize;
+use uuid::Uuid;
+
+/// A row updated or deleted by any instance, as broadcast by the
+/// `notify_entity_change` trigger.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct ChangeEvent {
+    pub table: ChangedTable,
+    pub id: Uuid,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChangedTable {
+    Notes,
+    Users,
+}
@@ -1,5 +1,9 @@
+mod change_event;
 mod note_model;
+mod rate_limit_model;
 mod user_model;
 
+pub use change_event::{ChangeEvent, ChangedTable};
 pub use note_model::NoteModel;
-pub use user_model::UserModel;
+pub use rate_limit_model::RateLimitBucketModel;
+pub use user_model::{UserModel, ADMIN_ROLE, USER_ROLES};
@@ -0,0 +1,10 @@
+use sqlx::FromRow;
+
+/// A token bucket right after a request tried to take a token from it.
+#[derive(Debug, Clone, Copy, FromRow)]
+pub struct RateLimitBucketModel {
+    /// Tokens left, including partially refilled ones.
+    pub tokens: f64,
+    /// Whether the request got a token.
+    pub allowed: bool,
+}
@@ -1,6 +1,10 @@
 use chrono::prelude::*;
 use serde::{Deserialize, Serialize};
 
+/// Roles a user can have. New users get `user`.
+pub const USER_ROLES: [&str; 2] = ["user", ADMIN_ROLE];
+pub const ADMIN_ROLE: &str = "admin";
+
 #[allow(non_snake_case)]
 #[derive(Debug, Deserialize, sqlx::FromRow, Serialize, Clone)]
 pub struct UserModel {
@@ -14,4 +18,7 @@ pub struct UserModel {
     pub created_at: Option<DateTime<Utc>>,
     #[serde(rename = "updatedAt")]
     pub updated_at: Option<DateTime<Utc>>,
+    /// Set while the user is disabled and cannot log in.
+    #[serde(rename = "disabledAt")]
+    pub disabled_at: Option<DateTime<Utc>>,
 }
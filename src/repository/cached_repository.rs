@@ -0,0 +1,893 @@
+use std::hash::Hash;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use async_trait::async_trait;
+use hashlink::LruCache;
+use sqlx::Error;
+use uuid::Uuid;
+
+use crate::{
+    abstract_trait::{
+        BoxUnitOfWork, DynNoteRepository, DynUnitOfWorkFactory, DynUserRepository,
+        NoteRepositoryTrait, UnitOfWorkFactoryTrait, UnitOfWorkTrait, UserRepositoryTrait,
+    },
+    config::CacheConfig,
+    metrics::Metrics,
+    models::{ChangeEvent, ChangedTable, NoteModel, UserModel},
+};
+
+use super::pin_reads;
+
+/// The caches behind [`CachedNoteRepository`] and [`CachedUserRepository`],
+/// shared with whatever learns about changes made elsewhere.
+#[derive(Clone)]
+pub struct RepositoryCaches {
+    notes: Arc<LookupCache<Uuid, NoteModel>>,
+    users: Arc<LookupCache<Uuid, UserModel>>,
+}
+
+impl RepositoryCaches {
+    pub fn new(config: &CacheConfig) -> Self {
+        Self {
+            notes: Arc::new(LookupCache::new(config)),
+            users: Arc::new(LookupCache::new(config)),
+        }
+    }
+
+    /// Drops the changed row.
+    pub fn invalidate(&self, event: &ChangeEvent) {
+        match event.table {
+            ChangedTable::Notes => self.notes.invalidate(&event.id),
+            ChangedTable::Users => self.users.invalidate(&event.id),
+        }
+    }
+
+    /// Drops everything, for when changes may have been missed.
+    pub fn clear(&self) {
+        self.notes.clear();
+        self.users.clear();
+    }
+}
+
+/// Bounded map evicting the least recently used entry when full, and
+/// ignoring entries older than `ttl`.
+struct LookupCache<K, V> {
+    ttl: Duration,
+    state: Mutex<CacheState<K, V>>,
+}
+
+struct CacheState<K, V> {
+    entries: LruCache<K, (Instant, V)>,
+    /// Bumped by every invalidation, so a lookup that raced with one does
+    /// not store what it read before the change.
+    generation: u64,
+}
+
+impl<K: Eq + Hash + Clone, V: Clone> LookupCache<K, V> {
+    fn new(config: &CacheConfig) -> Self {
+        Self {
+            ttl: Duration::from_secs(config.ttl_secs),
+            state: Mutex::new(CacheState {
+                entries: LruCache::new(config.capacity),
+                generation: 0,
+            }),
+        }
+    }
+
+    /// The cached value, or the generation to pass to [`Self::insert`]
+    /// after looking it up.
+    fn get(&self, key: &K) -> Result<V, u64> {
+        let mut state = self.state.lock().unwrap();
+        match state.entries.get(key) {
+            Some((stored, value)) if stored.elapsed() < self.ttl => Ok(value.clone()),
+            Some(_) => {
+                state.entries.remove(key);
+                Err(state.generation)
+            }
+            None => Err(state.generation),
+        }
+    }
+
+    /// Stores `value` unless something was invalidated since `generation`.
+    fn insert(&self, key: K, value: V, generation: u64) {
+        let mut state = self.state.lock().unwrap();
+        if state.generation == generation {
+            state.entries.insert(key, (Instant::now(), value));
+        }
+    }
+
+    fn invalidate(&self, key: &K) {
+        let mut state = self.state.lock().unwrap();
+        state.generation += 1;
+        state.entries.remove(key);
+    }
+
+    /// Drops every entry whose value matches `predicate`.
+    fn invalidate_where(&self, predicate: impl Fn(&V) -> bool) {
+        let mut state = self.state.lock().unwrap();
+        state.generation += 1;
+        let keys: Vec<K> = state
+            .entries
+            .iter()
+            .filter(|(_, (_, value))| predicate(value))
+            .map(|(key, _)| key.clone())
+            .collect();
+        for key in keys {
+            state.entries.remove(&key);
+        }
+    }
+
+    fn clear(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.generation += 1;
+        state.entries.clear();
+    }
+}
+
+/// Serves `get_note_id` from an LRU cache with a TTL, see [`CacheConfig`],
+/// filling it from the primary even with read replicas. Everything else is
+/// forwarded to the wrapped repository, dropping the entry of a note when
+/// it is updated or deleted. Units of work drop it
+/// through [`CachedUnitOfWorkFactory`]; writes from other instances are
+/// picked up when the entry expires or is dropped through
+/// [`RepositoryCaches`].
+pub struct CachedNoteRepository {
+    inner: DynNoteRepository,
+    cache: Arc<LookupCache<Uuid, NoteModel>>,
+    metrics: Arc<Metrics>,
+}
+
+impl CachedNoteRepository {
+    pub fn new(inner: DynNoteRepository, caches: &RepositoryCaches, metrics: Arc<Metrics>) -> Self {
+        Self {
+            inner,
+            cache: caches.notes.clone(),
+            metrics,
+        }
+    }
+}
+
+#[async_trait]
+impl NoteRepositoryTrait for CachedNoteRepository {
+    async fn get_notes(&self) -> Result<Vec<NoteModel>, Error> {
+        self.inner.get_notes().await
+    }
+
+    async fn get_note_id(&self, id: Uuid) -> Result<Option<NoteModel>, Error> {
+        let generation = match self.cache.get(&id) {
+            Ok(note) => {
+                self.metrics.record_cache_lookup("note", true);
+                return Ok(Some(note));
+            }
+            Err(generation) => generation,
+        };
+        self.metrics.record_cache_lookup("note", false);
+
+        // A lagging replica's row would be served until it expires.
+        let note = pin_reads(true, self.inner.get_note_id(id)).await?;
+        if let Some(note) = &note {
+            self.cache.insert(id, note.clone(), generation);
+        }
+        Ok(note)
+    }
+
+    async fn create_note(&self, title: &str, content: &str) -> Result<NoteModel, Error> {
+        self.inner.create_note(title, content).await
+    }
+
+    async fn update_note(
+        &self,
+        id: Uuid,
+        title: &str,
+        content: &str,
+    ) -> Result<Option<NoteModel>, Error> {
+        let result = self.inner.update_note(id, title, content).await;
+        self.cache.invalidate(&id);
+        result
+    }
+
+    async fn delete(&self, id: Uuid) -> Result<(), Error> {
+        let result = self.inner.delete(id).await;
+        self.cache.invalidate(&id);
+        result
+    }
+
+    async fn count(&self) -> Result<i64, Error> {
+        self.inner.count().await
+    }
+}
+
+/// Serves `find_by_id` from an LRU cache with a TTL, like
+/// [`CachedNoteRepository`]. Users are updated and deleted by email, so
+/// those drop the entries with that email.
+pub struct CachedUserRepository {
+    inner: DynUserRepository,
+    cache: Arc<LookupCache<Uuid, UserModel>>,
+    metrics: Arc<Metrics>,
+}
+
+impl CachedUserRepository {
+    pub fn new(inner: DynUserRepository, caches: &RepositoryCaches, metrics: Arc<Metrics>) -> Self {
+        Self {
+            inner,
+            cache: caches.users.clone(),
+            metrics,
+        }
+    }
+
+    fn invalidate_email(&self, email: &str) {
+        self.cache.invalidate_where(|user| user.email == email);
+    }
+}
+
+#[async_trait]
+impl UserRepositoryTrait for CachedUserRepository {
+    async fn find_by_email_exists(&self, email: &str) -> Result<bool, Error> {
+        self.inner.find_by_email_exists(email).await
+    }
+
+    async fn create_user(
+        &self,
+        firstname: &str,
+        lastname: &str,
+        email: &str,
+        password: &str,
+    ) -> Result<UserModel, Error> {
+        self.inner
+            .create_user(firstname, lastname, email, password)
+            .await
+    }
+
+    async fn find_by_email(&self, email: &str) -> Result<Option<UserModel>, Error> {
+        self.inner.find_by_email(email).await
+    }
+
+    async fn find_by_id(&self, id: Uuid) -> Result<Option<UserModel>, Error> {
+        let generation = match self.cache.get(&id) {
+            Ok(user) => {
+                self.metrics.record_cache_lookup("user", true);
+                return Ok(Some(user));
+            }
+            Err(generation) => generation,
+        };
+        self.metrics.record_cache_lookup("user", false);
+
+        let user = pin_reads(true, self.inner.find_by_id(id)).await?;
+        if let Some(user) = &user {
+            self.cache.insert(id, user.clone(), generation);
+        }
+        Ok(user)
+    }
+
+    async fn update_user(
+        &self,
+        email: &str,
+        firstname: &str,
+        lastname: &str,
+        password: &str,
+    ) -> Result<Option<UserModel>, Error> {
+        let result = self
+            .inner
+            .update_user(email, firstname, lastname, password)
+            .await;
+        self.invalidate_email(email);
+        result
+    }
+
+    async fn delete_user(&self, email: &str) -> Result<bool, Error> {
+        let result = self.inner.delete_user(email).await;
+        self.invalidate_email(email);
+        result
+    }
+
+    async fn update_role(&self, email: &str, role: &str) -> Result<Option<UserModel>, Error> {
+        let result = self.inner.update_role(email, role).await;
+        self.invalidate_email(email);
+        result
+    }
+
+    async fn set_disabled(&self, email: &str, disabled: bool) -> Result<Option<UserModel>, Error> {
+        let result = self.inner.set_disabled(email, disabled).await;
+        self.invalidate_email(email);
+        result
+    }
+
+    async fn count(&self) -> Result<i64, Error> {
+        self.inner.count().await
+    }
+}
+
+/// Drops the cache entries a unit of work changed once it commits. Reads
+/// inside a unit of work skip the caches, which must not see uncommitted
+/// rows.
+pub struct CachedUnitOfWorkFactory {
+    inner: DynUnitOfWorkFactory,
+    caches: RepositoryCaches,
+}
+
+impl CachedUnitOfWorkFactory {
+    pub fn new(inner: DynUnitOfWorkFactory, caches: &RepositoryCaches) -> Self {
+        Self {
+            inner,
+            caches: caches.clone(),
+        }
+    }
+}
+
+#[async_trait]
+impl UnitOfWorkFactoryTrait for CachedUnitOfWorkFactory {
+    async fn begin(&self) -> Result<BoxUnitOfWork, Error> {
+        let inner = self.inner.begin().await?;
+        let changes = Arc::new(Mutex::new(Vec::new()));
+
+        Ok(Box::new(CachedUnitOfWork {
+            notes: Arc::new(ChangeTrackingNoteRepository {
+                inner: inner.notes().clone(),
+                changes: changes.clone(),
+            }),
+            users: Arc::new(ChangeTrackingUserRepository {
+                inner: inner.users().clone(),
+                changes: changes.clone(),
+            }),
+            caches: self.caches.clone(),
+            changes,
+            inner,
+        }))
+    }
+}
+
+/// A row a unit of work may have changed.
+enum Change {
+    Note(Uuid),
+    UserEmail(String),
+}
+
+type Changes = Arc<Mutex<Vec<Change>>>;
+
+struct CachedUnitOfWork {
+    inner: BoxUnitOfWork,
+    notes: DynNoteRepository,
+    users: DynUserRepository,
+    caches: RepositoryCaches,
+    changes: Changes,
+}
+
+#[async_trait]
+impl UnitOfWorkTrait for CachedUnitOfWork {
+    fn notes(&self) -> &DynNoteRepository {
+        &self.notes
+    }
+
+    fn users(&self) -> &DynUserRepository {
+        &self.users
+    }
+
+    async fn commit(self: Box<Self>) -> Result<(), Error> {
+        self.inner.commit().await?;
+
+        for change in self.changes.lock().unwrap().drain(..) {
+            match change {
+                Change::Note(id) => self.caches.notes.invalidate(&id),
+                Change::UserEmail(email) => self
+                    .caches
+                    .users
+                    .invalidate_where(|user| user.email == email),
+            }
+        }
+        Ok(())
+    }
+}
+
+struct ChangeTrackingNoteRepository {
+    inner: DynNoteRepository,
+    changes: Changes,
+}
+
+impl ChangeTrackingNoteRepository {
+    fn record(&self, id: Uuid) {
+        self.changes.lock().unwrap().push(Change::Note(id));
+    }
+}
+
+#[async_trait]
+impl NoteRepositoryTrait for ChangeTrackingNoteRepository {
+    async fn get_notes(&self) -> Result<Vec<NoteModel>, Error> {
+        self.inner.get_notes().await
+    }
+
+    async fn get_note_id(&self, id: Uuid) -> Result<Option<NoteModel>, Error> {
+        self.inner.get_note_id(id).await
+    }
+
+    async fn create_note(&self, title: &str, content: &str) -> Result<NoteModel, Error> {
+        self.inner.create_note(title, content).await
+    }
+
+    async fn update_note(
+        &self,
+        id: Uuid,
+        title: &str,
+        content: &str,
+    ) -> Result<Option<NoteModel>, Error> {
+        self.record(id);
+        self.inner.update_note(id, title, content).await
+    }
+
+    async fn delete(&self, id: Uuid) -> Result<(), Error> {
+        self.record(id);
+        self.inner.delete(id).await
+    }
+
+    async fn count(&self) -> Result<i64, Error> {
+        self.inner.count().await
+    }
+}
+
+struct ChangeTrackingUserRepository {
+    inner: DynUserRepository,
+    changes: Changes,
+}
+
+impl ChangeTrackingUserRepository {
+    fn record(&self, email: &str) {
+        self.changes
+            .lock()
+            .unwrap()
+            .push(Change::UserEmail(email.to_owned()));
+    }
+}
+
+#[async_trait]
+impl UserRepositoryTrait for ChangeTrackingUserRepository {
+    async fn find_by_email_exists(&self, email: &str) -> Result<bool, Error> {
+        self.inner.find_by_email_exists(email).await
+    }
+
+    async fn create_user(
+        &self,
+        firstname: &str,
+        lastname: &str,
+        email: &str,
+        password: &str,
+    ) -> Result<UserModel, Error> {
+        self.inner
+            .create_user(firstname, lastname, email, password)
+            .await
+    }
+
+    async fn find_by_email(&self, email: &str) -> Result<Option<UserModel>, Error> {
+        self.inner.find_by_email(email).await
+    }
+
+    async fn find_by_id(&self, id: Uuid) -> Result<Option<UserModel>, Error> {
+        self.inner.find_by_id(id).await
+    }
+
+    async fn update_user(
+        &self,
+        email: &str,
+        firstname: &str,
+        lastname: &str,
+        password: &str,
+    ) -> Result<Option<UserModel>, Error> {
+        self.record(email);
+        self.inner
+            .update_user(email, firstname, lastname, password)
+            .await
+    }
+
+    async fn delete_user(&self, email: &str) -> Result<bool, Error> {
+        self.record(email);
+        self.inner.delete_user(email).await
+    }
+
+    async fn update_role(&self, email: &str, role: &str) -> Result<Option<UserModel>, Error> {
+        self.record(email);
+        self.inner.update_role(email, role).await
+    }
+
+    async fn set_disabled(&self, email: &str, disabled: bool) -> Result<Option<UserModel>, Error> {
+        self.record(email);
+        self.inner.set_disabled(email, disabled).await
+    }
+
+    async fn count(&self) -> Result<i64, Error> {
+        self.inner.count().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repository::replica_router::reads_pinned;
+    use crate::repository::{
+        InMemoryDatabase, InMemoryNoteRepository, InMemoryUnitOfWorkFactory, InMemoryUserRepository,
+    };
+
+    fn config(capacity: usize, ttl_secs: u64) -> CacheConfig {
+        CacheConfig {
+            enabled: true,
+            capacity,
+            ttl_secs,
+            ..CacheConfig::default()
+        }
+    }
+
+    /// A cached repository and the one behind it, sharing their data.
+    fn notes(config: &CacheConfig) -> (CachedNoteRepository, DynNoteRepository, Arc<Metrics>) {
+        let database = InMemoryDatabase::default();
+        let metrics = Arc::new(Metrics::new().unwrap());
+        let inner: DynNoteRepository = Arc::new(InMemoryNoteRepository::new(database.clone()));
+        let cached = CachedNoteRepository::new(
+            Arc::new(InMemoryNoteRepository::new(database)),
+            &RepositoryCaches::new(config),
+            metrics.clone(),
+        );
+        (cached, inner, metrics)
+    }
+
+    fn lookups(metrics: &Metrics, cache: &str, result: &str) -> u64 {
+        let line = format!(
+            "cache_lookups_total{{cache=\"{}\",result=\"{}\"}} ",
+            cache, result
+        );
+        metrics
+            .render()
+            .unwrap()
+            .lines()
+            .find_map(|l| l.strip_prefix(&line))
+            .map_or(0, |count| count.parse().unwrap())
+    }
+
+    #[tokio::test]
+    async fn serves_repeated_lookups_from_the_cache() {
+        let (cached, inner, metrics) = notes(&config(10, 60));
+        let note = inner.create_note("Title", "Content").await.unwrap();
+
+        cached.get_note_id(note.id).await.unwrap().unwrap();
+        // Changed behind the cache's back: still served from it.
+        inner
+            .update_note(note.id, "Other", "Content")
+            .await
+            .unwrap();
+        let found = cached.get_note_id(note.id).await.unwrap().unwrap();
+        assert_eq!(found.title, "Title");
+
+        assert_eq!(lookups(&metrics, "note", "miss"), 1);
+        assert_eq!(lookups(&metrics, "note", "hit"), 1);
+    }
+
+    #[tokio::test]
+    async fn does_not_cache_missing_notes() {
+        let (cached, inner, _) = notes(&config(10, 60));
+
+        assert!(cached.get_note_id(Uuid::new_v4()).await.unwrap().is_none());
+        let note = inner.create_note("Title", "Content").await.unwrap();
+        assert!(cached.get_note_id(note.id).await.unwrap().is_some());
+    }
+
+    #[tokio::test]
+    async fn updates_and_deletes_invalidate() {
+        let (cached, _, _) = notes(&config(10, 60));
+        let note = cached.create_note("Title", "Content").await.unwrap();
+        cached.get_note_id(note.id).await.unwrap();
+
+        cached
+            .update_note(note.id, "Renamed", "Content")
+            .await
+            .unwrap();
+        let found = cached.get_note_id(note.id).await.unwrap().unwrap();
+        assert_eq!(found.title, "Renamed");
+
+        cached.delete(note.id).await.unwrap();
+        assert!(cached.get_note_id(note.id).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn evicts_the_least_recently_used() {
+        let (cached, inner, metrics) = notes(&config(2, 60));
+        let mut ids = Vec::new();
+        for title in ["First", "Second", "Third"] {
+            ids.push(inner.create_note(title, "Content").await.unwrap().id);
+        }
+
+        cached.get_note_id(ids[0]).await.unwrap();
+        cached.get_note_id(ids[1]).await.unwrap();
+        cached.get_note_id(ids[0]).await.unwrap();
+        cached.get_note_id(ids[2]).await.unwrap();
+        assert_eq!(lookups(&metrics, "note", "hit"), 1);
+
+        // The second was used least recently.
+        cached.get_note_id(ids[0]).await.unwrap();
+        cached.get_note_id(ids[1]).await.unwrap();
+        assert_eq!(lookups(&metrics, "note", "hit"), 2);
+    }
+
+    #[tokio::test]
+    async fn shared_caches_drop_changes_made_elsewhere() {
+        let database = InMemoryDatabase::default();
+        let inner: DynNoteRepository = Arc::new(InMemoryNoteRepository::new(database.clone()));
+        let caches = RepositoryCaches::new(&config(10, 60));
+        let cached = CachedNoteRepository::new(
+            Arc::new(InMemoryNoteRepository::new(database)),
+            &caches,
+            Arc::new(Metrics::new().unwrap()),
+        );
+        let note = inner.create_note("Title", "Content").await.unwrap();
+        cached.get_note_id(note.id).await.unwrap();
+
+        inner
+            .update_note(note.id, "Second", "Content")
+            .await
+            .unwrap();
+        caches.invalidate(&ChangeEvent {
+            table: ChangedTable::Notes,
+            id: note.id,
+        });
+        let found = cached.get_note_id(note.id).await.unwrap().unwrap();
+        assert_eq!(found.title, "Second");
+
+        inner
+            .update_note(note.id, "Third", "Content")
+            .await
+            .unwrap();
+        caches.clear();
+        let found = cached.get_note_id(note.id).await.unwrap().unwrap();
+        assert_eq!(found.title, "Third");
+    }
+
+    #[tokio::test]
+    async fn committed_units_of_work_invalidate_what_they_changed() {
+        let database = InMemoryDatabase::default();
+        let caches = RepositoryCaches::new(&config(10, 60));
+        let metrics = Arc::new(Metrics::new().unwrap());
+        let notes = CachedNoteRepository::new(
+            Arc::new(InMemoryNoteRepository::new(database.clone())),
+            &caches,
+            metrics.clone(),
+        );
+        let users = CachedUserRepository::new(
+            Arc::new(InMemoryUserRepository::new(database.clone())),
+            &caches,
+            metrics,
+        );
+        let factory = CachedUnitOfWorkFactory::new(
+            Arc::new(InMemoryUnitOfWorkFactory::new(database)),
+            &caches,
+        );
+        let note = notes.create_note("Title", "Content").await.unwrap();
+        let user = users
+            .create_user("Ada", "Lovelace", "ada@example.com", "hash")
+            .await
+            .unwrap();
+        notes.get_note_id(note.id).await.unwrap();
+        users.find_by_id(user.id).await.unwrap();
+
+        let unit_of_work = factory.begin().await.unwrap();
+        unit_of_work
+            .notes()
+            .update_note(note.id, "Renamed", "Content")
+            .await
+            .unwrap();
+        unit_of_work
+            .users()
+            .update_role("ada@example.com", "admin")
+            .await
+            .unwrap();
+        // Uncommitted changes neither reach nor evict the cache.
+        let found = notes.get_note_id(note.id).await.unwrap().unwrap();
+        assert_eq!(found.title, "Title");
+
+        unit_of_work.commit().await.unwrap();
+        let found = notes.get_note_id(note.id).await.unwrap().unwrap();
+        assert_eq!(found.title, "Renamed");
+        let found = users.find_by_id(user.id).await.unwrap().unwrap();
+        assert_eq!(found.role, "admin");
+    }
+
+    /// Answers lookups from a replica still serving `stale` unless reads
+    /// are pinned to the primary, `inner`.
+    struct LaggingReplica<R, M> {
+        inner: R,
+        stale: M,
+    }
+
+    #[async_trait]
+    impl NoteRepositoryTrait for LaggingReplica<DynNoteRepository, NoteModel> {
+        async fn get_notes(&self) -> Result<Vec<NoteModel>, Error> {
+            self.inner.get_notes().await
+        }
+
+        async fn get_note_id(&self, id: Uuid) -> Result<Option<NoteModel>, Error> {
+            match reads_pinned() {
+                true => self.inner.get_note_id(id).await,
+                false => Ok(Some(self.stale.clone())),
+            }
+        }
+
+        async fn create_note(&self, title: &str, content: &str) -> Result<NoteModel, Error> {
+            self.inner.create_note(title, content).await
+        }
+
+        async fn update_note(
+            &self,
+            id: Uuid,
+            title: &str,
+            content: &str,
+        ) -> Result<Option<NoteModel>, Error> {
+            self.inner.update_note(id, title, content).await
+        }
+
+        async fn delete(&self, id: Uuid) -> Result<(), Error> {
+            self.inner.delete(id).await
+        }
+
+        async fn count(&self) -> Result<i64, Error> {
+            self.inner.count().await
+        }
+    }
+
+    #[async_trait]
+    impl UserRepositoryTrait for LaggingReplica<DynUserRepository, UserModel> {
+        async fn find_by_email_exists(&self, email: &str) -> Result<bool, Error> {
+            self.inner.find_by_email_exists(email).await
+        }
+
+        async fn create_user(
+            &self,
+            firstname: &str,
+            lastname: &str,
+            email: &str,
+            password: &str,
+        ) -> Result<UserModel, Error> {
+            self.inner
+                .create_user(firstname, lastname, email, password)
+                .await
+        }
+
+        async fn find_by_email(&self, email: &str) -> Result<Option<UserModel>, Error> {
+            self.inner.find_by_email(email).await
+        }
+
+        async fn find_by_id(&self, id: Uuid) -> Result<Option<UserModel>, Error> {
+            match reads_pinned() {
+                true => self.inner.find_by_id(id).await,
+                false => Ok(Some(self.stale.clone())),
+            }
+        }
+
+        async fn update_user(
+            &self,
+            email: &str,
+            firstname: &str,
+            lastname: &str,
+            password: &str,
+        ) -> Result<Option<UserModel>, Error> {
+            self.inner
+                .update_user(email, firstname, lastname, password)
+                .await
+        }
+
+        async fn delete_user(&self, email: &str) -> Result<bool, Error> {
+            self.inner.delete_user(email).await
+        }
+
+        async fn update_role(&self, email: &str, role: &str) -> Result<Option<UserModel>, Error> {
+            self.inner.update_role(email, role).await
+        }
+
+        async fn set_disabled(
+            &self,
+            email: &str,
+            disabled: bool,
+        ) -> Result<Option<UserModel>, Error> {
+            self.inner.set_disabled(email, disabled).await
+        }
+
+        async fn count(&self) -> Result<i64, Error> {
+            self.inner.count().await
+        }
+    }
+
+    #[tokio::test]
+    async fn fills_from_the_primary_not_a_lagging_replica() {
+        let database = InMemoryDatabase::default();
+        let caches = RepositoryCaches::new(&config(10, 60));
+        let metrics = Arc::new(Metrics::new().unwrap());
+        let primary_notes: DynNoteRepository =
+            Arc::new(InMemoryNoteRepository::new(database.clone()));
+        let primary_users: DynUserRepository = Arc::new(InMemoryUserRepository::new(database));
+
+        let stale = primary_notes.create_note("Title", "Content").await.unwrap();
+        let note = primary_notes
+            .update_note(stale.id, "Renamed", "Content")
+            .await
+            .unwrap()
+            .unwrap();
+        let stale_user = primary_users
+            .create_user("Ada", "Lovelace", "ada@example.com", "hash")
+            .await
+            .unwrap();
+        primary_users
+            .set_disabled("ada@example.com", true)
+            .await
+            .unwrap();
+
+        let notes = CachedNoteRepository::new(
+            Arc::new(LaggingReplica {
+                inner: primary_notes,
+                stale,
+            }),
+            &caches,
+            metrics.clone(),
+        );
+        let users = CachedUserRepository::new(
+            Arc::new(LaggingReplica {
+                inner: primary_users,
+                stale: stale_user.clone(),
+            }),
+            &caches,
+            metrics,
+        );
+
+        for _ in 0..2 {
+            let found = notes.get_note_id(note.id).await.unwrap().unwrap();
+            assert_eq!(found.title, "Renamed");
+            let found = users.find_by_id(stale_user.id).await.unwrap().unwrap();
+            assert!(found.disabled_at.is_some());
+        }
+    }
+
+    #[test]
+    fn entries_expire() {
+        let cache = LookupCache::new(&config(10, 60));
+        let cache = LookupCache {
+            ttl: Duration::from_millis(20),
+            ..cache
+        };
+        let generation = cache.get(&1).unwrap_err();
+        cache.insert(1, "one", generation);
+        assert_eq!(cache.get(&1), Ok("one"));
+
+        std::thread::sleep(Duration::from_millis(30));
+        assert!(cache.get(&1).is_err());
+    }
+
+    #[test]
+    fn lookups_racing_an_invalidation_are_not_stored() {
+        let cache = LookupCache::new(&config(10, 60));
+
+        let generation = cache.get(&1).unwrap_err();
+        cache.invalidate(&1);
+        cache.insert(1, "stale", generation);
+        assert!(cache.get(&1).is_err());
+    }
+
+    #[tokio::test]
+    async fn user_changes_invalidate_by_email() {
+        let metrics = Arc::new(Metrics::new().unwrap());
+        let cached = CachedUserRepository::new(
+            Arc::new(InMemoryUserRepository::default()),
+            &RepositoryCaches::new(&config(10, 60)),
+            metrics.clone(),
+        );
+        let user = cached
+            .create_user("Ada", "Lovelace", "ada@example.com", "hash")
+            .await
+            .unwrap();
+        cached.find_by_id(user.id).await.unwrap();
+
+        cached
+            .update_user("ada@example.com", "Augusta", "Lovelace", "hash")
+            .await
+            .unwrap();
+        let found = cached.find_by_id(user.id).await.unwrap().unwrap();
+        assert_eq!(found.firstname, "Augusta");
+
+        cached.delete_user("ada@example.com").await.unwrap();
+        assert!(cached.find_by_id(user.id).await.unwrap().is_none());
+        assert_eq!(lookups(&metrics, "user", "hit"), 0);
+        assert_eq!(lookups(&metrics, "user", "miss"), 3);
+    }
+}
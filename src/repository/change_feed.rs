@@ -0,0 +1,226 @@
+use std::time::Duration;
+
+use sqlx::postgres::{PgListener, PgPool};
+
+use crate::models::ChangeEvent;
+
+/// Channel the `notify_entity_change` trigger publishes on.
+const CHANGES_CHANNEL: &str = "entity_changes";
+
+const MIN_RECONNECT_BACKOFF: Duration = Duration::from_millis(500);
+const MAX_RECONNECT_BACKOFF: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ChangeFeedEvent {
+    Changed(ChangeEvent),
+    /// Now listening, after starting or after the connection was lost.
+    /// Changes made in the meantime were missed.
+    Resumed,
+}
+
+/// Changes to notes and users made by any instance, received with Postgres
+/// `LISTEN`. Holds one connection of the pool while listening and
+/// reconnects with exponential backoff when it is lost.
+pub struct PgChangeFeed {
+    pool: PgPool,
+    listener: Option<PgListener>,
+    backoff: Duration,
+}
+
+impl PgChangeFeed {
+    pub fn new(pool: PgPool) -> Self {
+        Self {
+            pool,
+            listener: None,
+            backoff: MIN_RECONNECT_BACKOFF,
+        }
+    }
+
+    /// Waits for the next change, connecting first if needed.
+    pub async fn next(&mut self) -> ChangeFeedEvent {
+        loop {
+            let Some(listener) = self.listener.as_mut() else {
+                match self.listen().await {
+                    Ok(listener) => {
+                        self.listener = Some(listener);
+                        self.backoff = MIN_RECONNECT_BACKOFF;
+                        return ChangeFeedEvent::Resumed;
+                    }
+                    Err(err) => {
+                        log::warn!(
+                            "Cannot listen for changes ({}), retry in {:?}",
+                            err,
+                            self.backoff
+                        );
+                        actix_web::rt::time::sleep(self.backoff).await;
+                        self.backoff = (self.backoff * 2).min(MAX_RECONNECT_BACKOFF);
+                        continue;
+                    }
+                }
+            };
+
+            match listener.try_recv().await {
+                Ok(Some(notification)) => match serde_json::from_str(notification.payload()) {
+                    Ok(event) => return ChangeFeedEvent::Changed(event),
+                    Err(err) => log::warn!(
+                        "Ignoring change notification '{}': {}",
+                        notification.payload(),
+                        err
+                    ),
+                },
+                Ok(None) => {
+                    log::warn!("Lost the change listener connection");
+                    self.listener = None;
+                }
+                Err(err) => {
+                    log::warn!("Change listener failed: {}", err);
+                    self.listener = None;
+                }
+            }
+        }
+    }
+
+    async fn listen(&self) -> Result<PgListener, sqlx::Error> {
+        let mut listener = PgListener::connect_with(&self.pool).await?;
+        listener.listen(CHANGES_CHANNEL).await?;
+        Ok(listener)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use uuid::Uuid;
+
+    use super::*;
+    use crate::abstract_trait::NoteRepositoryTrait;
+    use crate::config::CacheConfig;
+    use crate::metrics::Metrics;
+    use crate::models::ChangedTable;
+    use crate::repository::contract_tests::with_postgres;
+    use crate::repository::{
+        CachedNoteRepository, NoteRepository, ReplicaRouter, RepositoryCaches,
+    };
+
+    async fn next(feed: &mut PgChangeFeed) -> ChangeFeedEvent {
+        tokio::time::timeout(Duration::from_secs(10), feed.next())
+            .await
+            .expect("no change feed event within 10s")
+    }
+
+    async fn insert_note(pool: &PgPool, title: &str) -> Uuid {
+        sqlx::query_scalar("INSERT INTO notes (title, content) VALUES ($1, '') RETURNING id")
+            .bind(title)
+            .fetch_one(pool)
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn reports_changes_made_on_other_connections() {
+        with_postgres(|pool| async move {
+            let mut feed = PgChangeFeed::new(pool.clone());
+            assert_eq!(next(&mut feed).await, ChangeFeedEvent::Resumed);
+
+            // Inserts are not reported, nothing can have cached them yet.
+            let id = insert_note(&pool, "watched").await;
+            sqlx::query("UPDATE notes SET content = 'changed' WHERE id = $1")
+                .bind(id)
+                .execute(&pool)
+                .await
+                .unwrap();
+            sqlx::query("DELETE FROM notes WHERE id = $1")
+                .bind(id)
+                .execute(&pool)
+                .await
+                .unwrap();
+
+            let changed = ChangeFeedEvent::Changed(ChangeEvent {
+                table: ChangedTable::Notes,
+                id,
+            });
+            assert_eq!(next(&mut feed).await, changed);
+            assert_eq!(next(&mut feed).await, changed);
+        })
+        .await;
+    }
+
+    #[tokio::test]
+    async fn resumes_after_losing_the_connection() {
+        with_postgres(|pool| async move {
+            let mut feed = PgChangeFeed::new(pool.clone());
+            assert_eq!(next(&mut feed).await, ChangeFeedEvent::Resumed);
+
+            sqlx::query(
+                "SELECT pg_terminate_backend(pid) FROM pg_stat_activity \
+                 WHERE datname = current_database() AND pid <> pg_backend_pid()",
+            )
+            .execute(&pool)
+            .await
+            .unwrap();
+            assert_eq!(next(&mut feed).await, ChangeFeedEvent::Resumed);
+
+            let id = insert_note(&pool, "after reconnect").await;
+            sqlx::query("DELETE FROM notes WHERE id = $1")
+                .bind(id)
+                .execute(&pool)
+                .await
+                .unwrap();
+            assert_eq!(
+                next(&mut feed).await,
+                ChangeFeedEvent::Changed(ChangeEvent {
+                    table: ChangedTable::Notes,
+                    id,
+                })
+            );
+        })
+        .await;
+    }
+
+    #[tokio::test]
+    async fn invalidated_entries_refill_from_the_primary() {
+        with_postgres(|primary| async move {
+            with_postgres(|replica| async move {
+                let caches = RepositoryCaches::new(&CacheConfig {
+                    enabled: true,
+                    ..CacheConfig::default()
+                });
+                let router = Arc::new(ReplicaRouter::new(primary.clone(), vec![replica.clone()]));
+                let notes = CachedNoteRepository::new(
+                    Arc::new(NoteRepository::with_replicas(router)),
+                    &caches,
+                    Arc::new(Metrics::new().unwrap()),
+                );
+                let mut feed = PgChangeFeed::new(primary.clone());
+                assert_eq!(next(&mut feed).await, ChangeFeedEvent::Resumed);
+
+                // The replica never gets past the note's first version.
+                let id = insert_note(&primary, "First").await;
+                sqlx::query("INSERT INTO notes (id, title, content) VALUES ($1, 'First', '')")
+                    .bind(id)
+                    .execute(&replica)
+                    .await
+                    .unwrap();
+                let note = notes.get_note_id(id).await.unwrap().unwrap();
+                assert_eq!(note.title, "First");
+
+                // Renamed by another instance.
+                sqlx::query("UPDATE notes SET title = 'Second' WHERE id = $1")
+                    .bind(id)
+                    .execute(&primary)
+                    .await
+                    .unwrap();
+                let ChangeFeedEvent::Changed(change) = next(&mut feed).await else {
+                    panic!("expected a change");
+                };
+                caches.invalidate(&change);
+
+                let note = notes.get_note_id(id).await.unwrap().unwrap();
+                assert_eq!(note.title, "Second");
+            })
+            .await;
+        })
+        .await;
+    }
+}
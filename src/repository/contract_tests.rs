@@ -0,0 +1,895 @@
+//! Behaviour every repository backend must share. Each case takes a fresh,
+//! empty repository; `contract_tests!` runs all of them against every
+//! backend. Postgres cases get their own database, created from
+//! `DATABASE_URL` and dropped afterwards, and are skipped when it is unset
+//! unless `REQUIRE_POSTGRES_TESTS` is set, as it should be in CI. SQLite
+//! cases get their own temporary file.
+//!
+//! Unit of work cases get a [`unit_of_work_cases::Storage`] instead: the
+//! factory plus plain repositories on the same data, to look at it from
+//! outside the unit of work.
+
+use std::future::Future;
+use std::panic::AssertUnwindSafe;
+use std::str::FromStr;
+
+use futures_util::FutureExt;
+use sqlx::postgres::{PgConnectOptions, PgPool, PgPoolOptions};
+use sqlx::sqlite::{SqliteConnectOptions, SqliteJournalMode, SqlitePool, SqlitePoolOptions};
+use sqlx::{ConnectOptions, Connection, Executor};
+use uuid::Uuid;
+
+use super::{
+    InMemoryNoteRepository, InMemoryUserRepository, NoteRepository, SqliteNoteRepository,
+    SqliteUserRepository, UserRepository,
+};
+
+/// Runs `case` against a newly created and migrated Postgres database,
+/// dropping it afterwards even if the case panics. Without `DATABASE_URL`
+/// the case is skipped, or fails when `REQUIRE_POSTGRES_TESTS` is set.
+pub(crate) async fn with_postgres<F, Fut>(case: F)
+where
+    F: FnOnce(PgPool) -> Fut,
+    Fut: Future<Output = ()>,
+{
+    dotenv::dotenv().ok();
+    let Ok(url) = std::env::var("DATABASE_URL") else {
+        assert!(
+            std::env::var_os("REQUIRE_POSTGRES_TESTS").is_none(),
+            "DATABASE_URL must be set when REQUIRE_POSTGRES_TESTS is"
+        );
+        eprintln!("DATABASE_URL is not set, skipping Postgres contract test");
+        return;
+    };
+
+    let admin_options = PgConnectOptions::from_str(&url).unwrap();
+    let mut admin = admin_options.connect().await.unwrap();
+    let name = format!("crudsqlx_test_{}", Uuid::new_v4().simple());
+    admin
+        .execute(format!("CREATE DATABASE {}", name).as_str())
+        .await
+        .unwrap();
+
+    let pool = PgPoolOptions::new()
+        .connect_with(admin_options.database(&name))
+        .await
+        .unwrap();
+    let result = AssertUnwindSafe(async {
+        sqlx::migrate!().run(&pool).await.unwrap();
+        case(pool.clone()).await;
+    })
+    .catch_unwind()
+    .await;
+
+    pool.close().await;
+    admin
+        .execute(format!("DROP DATABASE {} WITH (FORCE)", name).as_str())
+        .await
+        .unwrap();
+    admin.close().await.unwrap();
+
+    if let Err(panic) = result {
+        std::panic::resume_unwind(panic);
+    }
+}
+
+/// Runs `case` against a newly created and migrated SQLite file, removing
+/// it afterwards even if the case panics.
+pub(crate) async fn with_sqlite<F, Fut>(case: F)
+where
+    F: FnOnce(SqlitePool) -> Fut,
+    Fut: Future<Output = ()>,
+{
+    let path = std::env::temp_dir().join(format!("crudsqlx_test_{}.db", Uuid::new_v4().simple()));
+    let options = SqliteConnectOptions::new()
+        .filename(&path)
+        .create_if_missing(true)
+        .journal_mode(SqliteJournalMode::Wal);
+    let pool = SqlitePoolOptions::new()
+        .connect_with(options)
+        .await
+        .unwrap();
+
+    let result = AssertUnwindSafe(async {
+        sqlx::migrate!("./migrations_sqlite")
+            .run(&pool)
+            .await
+            .unwrap();
+        case(pool.clone()).await;
+    })
+    .catch_unwind()
+    .await;
+
+    pool.close().await;
+    for suffix in ["", "-wal", "-shm"] {
+        let mut file = path.clone().into_os_string();
+        file.push(suffix);
+        let _ = std::fs::remove_file(file);
+    }
+
+    if let Err(panic) = result {
+        std::panic::resume_unwind(panic);
+    }
+}
+
+/// One `#[tokio::test]` per case and backend, e.g. `notes::postgres::create_and_get`.
+macro_rules! contract_tests {
+    (
+        $suite:ident,
+        $cases:ident,
+        memory: $memory:expr,
+        postgres: $postgres:expr,
+        sqlite: $sqlite:expr,
+        [$($case:ident),* $(,)?]
+    ) => {
+        mod $suite {
+            mod memory {
+                use super::super::*;
+
+                $(
+                    #[tokio::test]
+                    async fn $case() {
+                        let repository = $memory;
+                        $cases::$case(&repository).await;
+                    }
+                )*
+            }
+
+            mod postgres {
+                use super::super::*;
+
+                $(
+                    #[tokio::test]
+                    async fn $case() {
+                        with_postgres(|pool| async move {
+                            let repository = $postgres(pool);
+                            $cases::$case(&repository).await;
+                        })
+                        .await;
+                    }
+                )*
+            }
+
+            mod sqlite {
+                use super::super::*;
+
+                $(
+                    #[tokio::test]
+                    async fn $case() {
+                        with_sqlite(|pool| async move {
+                            let repository = $sqlite(pool);
+                            $cases::$case(&repository).await;
+                        })
+                        .await;
+                    }
+                )*
+            }
+        }
+    };
+}
+
+mod note_cases {
+    use futures_util::future::join_all;
+    use sqlx::error::ErrorKind;
+    use uuid::Uuid;
+
+    use crate::abstract_trait::NoteRepositoryTrait;
+
+    use super::{assert_database_error, assert_unique_violation};
+
+    type Repository<'a> = &'a (dyn NoteRepositoryTrait + Send + Sync);
+
+    pub async fn create_and_get(repository: Repository<'_>) {
+        let created = repository.create_note("Title", "Content").await.unwrap();
+        assert_eq!(created.title, "Title");
+        assert_eq!(created.content, "Content");
+        assert!(created.created_at.is_some());
+        assert_eq!(created.created_at, created.updated_at);
+
+        let found = repository.get_note_id(created.id).await.unwrap().unwrap();
+        assert_eq!(found.id, created.id);
+        assert_eq!(found.title, created.title);
+        assert_eq!(found.created_at, created.created_at);
+    }
+
+    pub async fn get_missing_is_none(repository: Repository<'_>) {
+        let found = repository.get_note_id(Uuid::new_v4()).await.unwrap();
+        assert!(found.is_none());
+    }
+
+    pub async fn lists_oldest_first(repository: Repository<'_>) {
+        assert!(repository.get_notes().await.unwrap().is_empty());
+
+        for title in ["First", "Second", "Third"] {
+            repository.create_note(title, "Content").await.unwrap();
+        }
+
+        let titles: Vec<String> = repository
+            .get_notes()
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|note| note.title)
+            .collect();
+        assert_eq!(titles, ["First", "Second", "Third"]);
+        assert_eq!(repository.count().await.unwrap(), 3);
+    }
+
+    pub async fn update_changes_fields(repository: Repository<'_>) {
+        let created = repository.create_note("Title", "Content").await.unwrap();
+
+        let updated = repository
+            .update_note(created.id, "New title", "New content")
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(updated.id, created.id);
+        assert_eq!(updated.title, "New title");
+        assert_eq!(updated.content, "New content");
+        assert_eq!(updated.created_at, created.created_at);
+        assert!(updated.updated_at > created.updated_at);
+
+        let found = repository.get_note_id(created.id).await.unwrap().unwrap();
+        assert_eq!(found.title, "New title");
+    }
+
+    pub async fn update_keeping_the_title(repository: Repository<'_>) {
+        let created = repository.create_note("Title", "Content").await.unwrap();
+
+        let updated = repository
+            .update_note(created.id, "Title", "New content")
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(updated.content, "New content");
+    }
+
+    pub async fn update_missing_is_none(repository: Repository<'_>) {
+        let updated = repository
+            .update_note(Uuid::new_v4(), "Title", "Content")
+            .await
+            .unwrap();
+        assert!(updated.is_none());
+        assert_eq!(repository.count().await.unwrap(), 0);
+    }
+
+    pub async fn duplicate_title_conflicts(repository: Repository<'_>) {
+        let first = repository.create_note("Title", "Content").await.unwrap();
+        let second = repository.create_note("Other", "Content").await.unwrap();
+
+        let err = repository
+            .create_note("Title", "Content")
+            .await
+            .unwrap_err();
+        assert_unique_violation(&err, "notes_title_key");
+
+        let err = repository
+            .update_note(second.id, "Title", "Content")
+            .await
+            .unwrap_err();
+        assert_unique_violation(&err, "notes_title_key");
+
+        assert_eq!(repository.count().await.unwrap(), 2);
+        let unchanged = repository.get_note_id(second.id).await.unwrap().unwrap();
+        assert_eq!(unchanged.title, "Other");
+        assert!(repository.get_note_id(first.id).await.unwrap().is_some());
+    }
+
+    pub async fn title_too_long(repository: Repository<'_>) {
+        let title = "x".repeat(256);
+
+        let err = repository.create_note(&title, "Content").await.unwrap_err();
+        assert_database_error(&err, "22001", ErrorKind::Other);
+
+        let created = repository.create_note(&"x".repeat(255), "Content").await;
+        assert!(created.is_ok());
+    }
+
+    pub async fn delete_removes(repository: Repository<'_>) {
+        let kept = repository.create_note("Kept", "Content").await.unwrap();
+        let deleted = repository.create_note("Deleted", "Content").await.unwrap();
+
+        repository.delete(deleted.id).await.unwrap();
+        assert!(repository.get_note_id(deleted.id).await.unwrap().is_none());
+        assert!(repository.get_note_id(kept.id).await.unwrap().is_some());
+        assert_eq!(repository.count().await.unwrap(), 1);
+
+        // Deleting again, or something that never existed, is not an error.
+        repository.delete(deleted.id).await.unwrap();
+        repository.delete(Uuid::new_v4()).await.unwrap();
+    }
+
+    pub async fn concurrent_updates_keep_one_write(repository: Repository<'_>) {
+        let created = repository.create_note("Title", "Content").await.unwrap();
+
+        let titles: Vec<String> = (0..10).map(|n| format!("Title {}", n)).collect();
+        let results = join_all(
+            titles
+                .iter()
+                .map(|title| repository.update_note(created.id, title, "Content")),
+        )
+        .await;
+        for result in results {
+            assert!(result.unwrap().is_some());
+        }
+
+        let found = repository.get_note_id(created.id).await.unwrap().unwrap();
+        assert!(titles.contains(&found.title));
+        assert_eq!(repository.count().await.unwrap(), 1);
+    }
+
+    pub async fn concurrent_creates_keep_titles_unique(repository: Repository<'_>) {
+        let results = join_all((0..10).map(|_| repository.create_note("Title", "Content"))).await;
+
+        let created = results.iter().filter(|result| result.is_ok()).count();
+        assert_eq!(created, 1);
+        for err in results.iter().filter_map(|result| result.as_ref().err()) {
+            assert_unique_violation(err, "notes_title_key");
+        }
+        assert_eq!(repository.count().await.unwrap(), 1);
+    }
+}
+
+mod user_cases {
+    use futures_util::future::join_all;
+    use sqlx::error::ErrorKind;
+    use uuid::Uuid;
+
+    use crate::abstract_trait::UserRepositoryTrait;
+
+    use super::{assert_database_error, assert_unique_violation};
+
+    type Repository<'a> = &'a (dyn UserRepositoryTrait + Send + Sync);
+
+    pub async fn create_and_find(repository: Repository<'_>) {
+        assert!(!repository
+            .find_by_email_exists("ada@example.com")
+            .await
+            .unwrap());
+
+        let created = repository
+            .create_user("Ada", "Lovelace", "ada@example.com", "hash")
+            .await
+            .unwrap();
+        assert_eq!(created.firstname, "Ada");
+        assert_eq!(created.lastname, "Lovelace");
+        assert_eq!(created.email, "ada@example.com");
+        assert_eq!(created.password, "hash");
+        assert_eq!(created.role, "user");
+        assert!(created.created_at.is_some());
+        assert!(created.disabled_at.is_none());
+
+        assert!(repository
+            .find_by_email_exists("ada@example.com")
+            .await
+            .unwrap());
+        let by_email = repository
+            .find_by_email("ada@example.com")
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(by_email.id, created.id);
+        let by_id = repository.find_by_id(created.id).await.unwrap().unwrap();
+        assert_eq!(by_id.email, created.email);
+        assert_eq!(repository.count().await.unwrap(), 1);
+    }
+
+    pub async fn find_missing_is_none(repository: Repository<'_>) {
+        assert!(repository
+            .find_by_email("nobody@example.com")
+            .await
+            .unwrap()
+            .is_none());
+        assert!(repository
+            .find_by_id(Uuid::new_v4())
+            .await
+            .unwrap()
+            .is_none());
+    }
+
+    pub async fn emails_are_case_sensitive(repository: Repository<'_>) {
+        repository
+            .create_user("Ada", "Lovelace", "ada@example.com", "hash")
+            .await
+            .unwrap();
+
+        assert!(repository
+            .find_by_email("ADA@example.com")
+            .await
+            .unwrap()
+            .is_none());
+    }
+
+    pub async fn duplicate_email_conflicts(repository: Repository<'_>) {
+        repository
+            .create_user("Ada", "Lovelace", "ada@example.com", "hash")
+            .await
+            .unwrap();
+
+        let err = repository
+            .create_user("Other", "Person", "ada@example.com", "hash")
+            .await
+            .unwrap_err();
+        assert_unique_violation(&err, "users_email_key");
+        assert_eq!(repository.count().await.unwrap(), 1);
+    }
+
+    pub async fn emails_differing_in_case_conflict(repository: Repository<'_>) {
+        repository
+            .create_user("Ada", "Lovelace", "ada@example.com", "hash")
+            .await
+            .unwrap();
+
+        let err = repository
+            .create_user("Other", "Person", "ADA@example.com", "hash")
+            .await
+            .unwrap_err();
+        assert_unique_violation(&err, "users_email_lower_key");
+        assert_eq!(repository.count().await.unwrap(), 1);
+    }
+
+    pub async fn values_too_long(repository: Repository<'_>) {
+        let long = "x".repeat(101);
+
+        let err = repository
+            .create_user(&long, "Lovelace", "ada@example.com", "hash")
+            .await
+            .unwrap_err();
+        assert_database_error(&err, "22001", ErrorKind::Other);
+
+        let email = format!("{}@example.com", "x".repeat(250));
+        let err = repository
+            .create_user("Ada", "Lovelace", &email, "hash")
+            .await
+            .unwrap_err();
+        assert_database_error(&err, "22001", ErrorKind::Other);
+        assert_eq!(repository.count().await.unwrap(), 0);
+    }
+
+    pub async fn update_changes_fields(repository: Repository<'_>) {
+        let created = repository
+            .create_user("Ada", "Lovelace", "ada@example.com", "hash")
+            .await
+            .unwrap();
+
+        let updated = repository
+            .update_user("ada@example.com", "Augusta", "King", "new-hash")
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(updated.id, created.id);
+        assert_eq!(updated.firstname, "Augusta");
+        assert_eq!(updated.lastname, "King");
+        assert_eq!(updated.password, "new-hash");
+        assert_eq!(updated.email, "ada@example.com");
+        assert_eq!(updated.created_at, created.created_at);
+
+        let found = repository.find_by_id(created.id).await.unwrap().unwrap();
+        assert_eq!(found.password, "new-hash");
+    }
+
+    pub async fn update_missing_is_none(repository: Repository<'_>) {
+        let updated = repository
+            .update_user("nobody@example.com", "Ada", "Lovelace", "hash")
+            .await
+            .unwrap();
+        assert!(updated.is_none());
+    }
+
+    pub async fn update_role_changes_the_role(repository: Repository<'_>) {
+        let created = repository
+            .create_user("Ada", "Lovelace", "ada@example.com", "hash")
+            .await
+            .unwrap();
+
+        let updated = repository
+            .update_role("ada@example.com", "admin")
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(updated.id, created.id);
+        assert_eq!(updated.role, "admin");
+        assert_eq!(updated.password, "hash");
+
+        let found = repository.find_by_id(created.id).await.unwrap().unwrap();
+        assert_eq!(found.role, "admin");
+        assert!(repository
+            .update_role("nobody@example.com", "admin")
+            .await
+            .unwrap()
+            .is_none());
+    }
+
+    pub async fn disabling_keeps_the_first_time(repository: Repository<'_>) {
+        let created = repository
+            .create_user("Ada", "Lovelace", "ada@example.com", "hash")
+            .await
+            .unwrap();
+
+        let disabled = repository
+            .set_disabled("ada@example.com", true)
+            .await
+            .unwrap()
+            .unwrap();
+        let disabled_at = disabled.disabled_at.expect("disabled_at is set");
+        let again = repository
+            .set_disabled("ada@example.com", true)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(again.disabled_at, Some(disabled_at));
+
+        let enabled = repository
+            .set_disabled("ada@example.com", false)
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(enabled.disabled_at.is_none());
+        let found = repository.find_by_id(created.id).await.unwrap().unwrap();
+        assert!(found.disabled_at.is_none());
+        assert!(repository
+            .set_disabled("nobody@example.com", true)
+            .await
+            .unwrap()
+            .is_none());
+    }
+
+    pub async fn delete_reports_whether_removed(repository: Repository<'_>) {
+        repository
+            .create_user("Ada", "Lovelace", "ada@example.com", "hash")
+            .await
+            .unwrap();
+
+        assert!(repository.delete_user("ada@example.com").await.unwrap());
+        assert!(!repository.delete_user("ada@example.com").await.unwrap());
+        assert!(repository
+            .find_by_email("ada@example.com")
+            .await
+            .unwrap()
+            .is_none());
+        assert_eq!(repository.count().await.unwrap(), 0);
+    }
+
+    pub async fn concurrent_updates_keep_one_write(repository: Repository<'_>) {
+        repository
+            .create_user("Ada", "Lovelace", "ada@example.com", "hash")
+            .await
+            .unwrap();
+
+        let hashes: Vec<String> = (0..10).map(|n| format!("hash-{}", n)).collect();
+        let results = join_all(
+            hashes
+                .iter()
+                .map(|hash| repository.update_user("ada@example.com", "Ada", "Lovelace", hash)),
+        )
+        .await;
+        for result in results {
+            assert!(result.unwrap().is_some());
+        }
+
+        let found = repository
+            .find_by_email("ada@example.com")
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(hashes.contains(&found.password));
+    }
+
+    pub async fn concurrent_creates_keep_emails_unique(repository: Repository<'_>) {
+        let results = join_all(
+            (0..10).map(|_| repository.create_user("Ada", "Lovelace", "ada@example.com", "hash")),
+        )
+        .await;
+
+        let created = results.iter().filter(|result| result.is_ok()).count();
+        assert_eq!(created, 1);
+        for err in results.iter().filter_map(|result| result.as_ref().err()) {
+            assert_unique_violation(err, "users_email_key");
+        }
+        assert_eq!(repository.count().await.unwrap(), 1);
+    }
+}
+
+mod unit_of_work_cases {
+    use sqlx::postgres::PgPool;
+    use sqlx::sqlite::SqlitePool;
+
+    use crate::abstract_trait::{NoteRepositoryTrait, UnitOfWorkFactoryTrait, UserRepositoryTrait};
+    use crate::config::ConnectionPool;
+    use crate::repository::{
+        InMemoryDatabase, InMemoryNoteRepository, InMemoryUnitOfWorkFactory,
+        InMemoryUserRepository, NoteRepository, SqlUnitOfWorkFactory, SqliteNoteRepository,
+        SqliteUserRepository, UserRepository,
+    };
+
+    use super::{assert_database_error, assert_unique_violation};
+
+    pub struct Storage {
+        unit_of_work: Box<dyn UnitOfWorkFactoryTrait + Send + Sync>,
+        notes: Box<dyn NoteRepositoryTrait + Send + Sync>,
+        users: Box<dyn UserRepositoryTrait + Send + Sync>,
+    }
+
+    impl Storage {
+        pub fn in_memory() -> Self {
+            let database = InMemoryDatabase::default();
+            Storage {
+                unit_of_work: Box::new(InMemoryUnitOfWorkFactory::new(database.clone())),
+                notes: Box::new(InMemoryNoteRepository::new(database.clone())),
+                users: Box::new(InMemoryUserRepository::new(database)),
+            }
+        }
+
+        pub fn postgres(pool: PgPool) -> Self {
+            Storage {
+                unit_of_work: Box::new(SqlUnitOfWorkFactory::new(ConnectionPool::Postgres(
+                    pool.clone(),
+                ))),
+                notes: Box::new(NoteRepository::new(pool.clone())),
+                users: Box::new(UserRepository::new(pool)),
+            }
+        }
+
+        pub fn sqlite(pool: SqlitePool) -> Self {
+            Storage {
+                unit_of_work: Box::new(SqlUnitOfWorkFactory::new(ConnectionPool::Sqlite(
+                    pool.clone(),
+                ))),
+                notes: Box::new(SqliteNoteRepository::new(pool.clone())),
+                users: Box::new(SqliteUserRepository::new(pool)),
+            }
+        }
+    }
+
+    pub async fn commit_makes_changes_visible(storage: &Storage) {
+        let unit_of_work = storage.unit_of_work.begin().await.unwrap();
+        let note = unit_of_work
+            .notes()
+            .create_note("Title", "Content")
+            .await
+            .unwrap();
+        unit_of_work
+            .users()
+            .create_user("Ada", "Lovelace", "ada@example.com", "hash")
+            .await
+            .unwrap();
+        unit_of_work.commit().await.unwrap();
+
+        let found = storage.notes.get_note_id(note.id).await.unwrap().unwrap();
+        assert_eq!(found.title, "Title");
+        assert!(storage
+            .users
+            .find_by_email_exists("ada@example.com")
+            .await
+            .unwrap());
+    }
+
+    pub async fn changes_are_hidden_until_committed(storage: &Storage) {
+        let unit_of_work = storage.unit_of_work.begin().await.unwrap();
+        let note = unit_of_work
+            .notes()
+            .create_note("Title", "Content")
+            .await
+            .unwrap();
+
+        // Visible inside, not outside.
+        let own = unit_of_work.notes().get_note_id(note.id).await.unwrap();
+        assert!(own.is_some());
+        assert_eq!(unit_of_work.notes().count().await.unwrap(), 1);
+        assert!(storage.notes.get_note_id(note.id).await.unwrap().is_none());
+        assert_eq!(storage.notes.count().await.unwrap(), 0);
+
+        unit_of_work.commit().await.unwrap();
+        assert_eq!(storage.notes.count().await.unwrap(), 1);
+    }
+
+    pub async fn dropping_discards_every_repository(storage: &Storage) {
+        let existing = storage
+            .notes
+            .create_note("Existing", "Content")
+            .await
+            .unwrap();
+
+        let unit_of_work = storage.unit_of_work.begin().await.unwrap();
+        unit_of_work
+            .notes()
+            .create_note("New", "Content")
+            .await
+            .unwrap();
+        unit_of_work
+            .notes()
+            .update_note(existing.id, "Renamed", "Changed")
+            .await
+            .unwrap()
+            .unwrap();
+        unit_of_work
+            .users()
+            .create_user("Ada", "Lovelace", "ada@example.com", "hash")
+            .await
+            .unwrap();
+        drop(unit_of_work);
+
+        let notes = storage.notes.get_notes().await.unwrap();
+        assert_eq!(notes.len(), 1);
+        assert_eq!(notes[0].title, "Existing");
+        assert_eq!(notes[0].content, "Content");
+        assert_eq!(storage.users.count().await.unwrap(), 0);
+    }
+
+    pub async fn dropping_rolls_back(storage: &Storage) {
+        let unit_of_work = storage.unit_of_work.begin().await.unwrap();
+        unit_of_work
+            .notes()
+            .create_note("Title", "Content")
+            .await
+            .unwrap();
+        drop(unit_of_work);
+
+        assert_eq!(storage.notes.count().await.unwrap(), 0);
+
+        // The data is still usable afterwards.
+        storage.notes.create_note("Title", "Content").await.unwrap();
+        assert_eq!(storage.notes.count().await.unwrap(), 1);
+    }
+
+    pub async fn sees_committed_data(storage: &Storage) {
+        storage.notes.create_note("Taken", "Content").await.unwrap();
+
+        let unit_of_work = storage.unit_of_work.begin().await.unwrap();
+        assert_eq!(unit_of_work.notes().count().await.unwrap(), 1);
+        let err = unit_of_work
+            .notes()
+            .create_note("Taken", "Content")
+            .await
+            .unwrap_err();
+        assert_unique_violation(&err, "notes_title_key");
+        drop(unit_of_work);
+
+        assert_eq!(storage.notes.count().await.unwrap(), 1);
+    }
+
+    pub async fn units_of_work_are_independent(storage: &Storage) {
+        let first = storage.unit_of_work.begin().await.unwrap();
+        first.notes().create_note("First", "Content").await.unwrap();
+        first.commit().await.unwrap();
+
+        let second = storage.unit_of_work.begin().await.unwrap();
+        second
+            .notes()
+            .create_note("Second", "Content")
+            .await
+            .unwrap();
+        drop(second);
+
+        let titles: Vec<String> = storage
+            .notes
+            .get_notes()
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|note| note.title)
+            .collect();
+        assert_eq!(titles, ["First"]);
+    }
+
+    pub async fn conflicting_changes_cannot_both_commit(storage: &Storage) {
+        let note = storage.notes.create_note("Title", "Content").await.unwrap();
+
+        // Both read the note before either changes it.
+        let first = storage.unit_of_work.begin().await.unwrap();
+        let second = storage.unit_of_work.begin().await.unwrap();
+        first.notes().get_note_id(note.id).await.unwrap().unwrap();
+        second.notes().get_note_id(note.id).await.unwrap().unwrap();
+
+        first
+            .notes()
+            .update_note(note.id, "First", "Content")
+            .await
+            .unwrap()
+            .unwrap();
+        first.commit().await.unwrap();
+
+        let result = match second
+            .notes()
+            .update_note(note.id, "Second", "Content")
+            .await
+        {
+            Ok(_) => second.commit().await,
+            Err(err) => Err(err),
+        };
+        assert_database_error(&result.unwrap_err(), "40001", sqlx::error::ErrorKind::Other);
+
+        let found = storage.notes.get_note_id(note.id).await.unwrap().unwrap();
+        assert_eq!(found.title, "First");
+    }
+
+    pub async fn read_only_commit_succeeds(storage: &Storage) {
+        let unit_of_work = storage.unit_of_work.begin().await.unwrap();
+        assert!(unit_of_work.notes().get_notes().await.unwrap().is_empty());
+
+        // Unrelated writes in between don't fail a unit of work that wrote
+        // nothing.
+        let note = storage.notes.create_note("Title", "Content").await.unwrap();
+        unit_of_work.commit().await.unwrap();
+
+        assert!(storage.notes.get_note_id(note.id).await.unwrap().is_some());
+    }
+}
+
+fn assert_database_error(err: &sqlx::Error, code: &str, kind: sqlx::error::ErrorKind) {
+    let sqlx::Error::Database(db_err) = err else {
+        panic!("expected a database error, got {:?}", err);
+    };
+    assert_eq!(db_err.code().as_deref(), Some(code), "{}", db_err);
+    assert_eq!(db_err.kind(), kind);
+}
+
+fn assert_unique_violation(err: &sqlx::Error, constraint: &str) {
+    assert_database_error(err, "23505", sqlx::error::ErrorKind::UniqueViolation);
+    let sqlx::Error::Database(db_err) = err else {
+        unreachable!()
+    };
+    assert_eq!(db_err.constraint(), Some(constraint));
+}
+
+contract_tests!(
+    notes,
+    note_cases,
+    memory: InMemoryNoteRepository::default(),
+    postgres: NoteRepository::new,
+    sqlite: SqliteNoteRepository::new,
+    [
+        create_and_get,
+        get_missing_is_none,
+        lists_oldest_first,
+        update_changes_fields,
+        update_keeping_the_title,
+        update_missing_is_none,
+        duplicate_title_conflicts,
+        title_too_long,
+        delete_removes,
+        concurrent_updates_keep_one_write,
+        concurrent_creates_keep_titles_unique,
+    ]
+);
+
+contract_tests!(
+    users,
+    user_cases,
+    memory: InMemoryUserRepository::default(),
+    postgres: UserRepository::new,
+    sqlite: SqliteUserRepository::new,
+    [
+        create_and_find,
+        find_missing_is_none,
+        emails_are_case_sensitive,
+        duplicate_email_conflicts,
+        emails_differing_in_case_conflict,
+        values_too_long,
+        update_changes_fields,
+        update_missing_is_none,
+        update_role_changes_the_role,
+        disabling_keeps_the_first_time,
+        delete_reports_whether_removed,
+        concurrent_updates_keep_one_write,
+        concurrent_creates_keep_emails_unique,
+    ]
+);
+
+contract_tests!(
+    units_of_work,
+    unit_of_work_cases,
+    memory: unit_of_work_cases::Storage::in_memory(),
+    postgres: unit_of_work_cases::Storage::postgres,
+    sqlite: unit_of_work_cases::Storage::sqlite,
+    [
+        commit_makes_changes_visible,
+        changes_are_hidden_until_committed,
+        dropping_discards_every_repository,
+        dropping_rolls_back,
+        sees_committed_data,
+        units_of_work_are_independent,
+        conflicting_changes_cannot_both_commit,
+        read_only_commit_succeeds,
+    ]
+);
@@ -0,0 +1,87 @@
+use std::borrow::Cow;
+use std::fmt;
+
+use sqlx::error::{DatabaseError, ErrorKind};
+use sqlx::Error;
+
+/// A unique constraint violation as Postgres reports it.
+pub(super) fn unique_violation(constraint: &str) -> Error {
+    Error::Database(Box::new(PgCompatibleError {
+        message: format!(
+            "duplicate key value violates unique constraint \"{}\"",
+            constraint
+        ),
+        code: "23505",
+        constraint: Some(constraint.to_string()),
+    }))
+}
+
+/// A value exceeding its column's length, as Postgres reports it.
+pub(super) fn value_too_long(message: String) -> Error {
+    Error::Database(Box::new(PgCompatibleError {
+        message,
+        code: "22001",
+        constraint: None,
+    }))
+}
+
+/// A transaction that cannot commit because of a concurrent write, as
+/// Postgres reports it for `SERIALIZABLE` transactions.
+pub(super) fn serialization_failure() -> Error {
+    Error::Database(Box::new(PgCompatibleError {
+        message: "could not serialize access due to concurrent update".to_string(),
+        code: "40001",
+        constraint: None,
+    }))
+}
+
+/// Database error carrying the SQLSTATE and constraint Postgres would
+/// report, so errors from the other backends are mapped exactly like a real
+/// one.
+#[derive(Debug)]
+struct PgCompatibleError {
+    message: String,
+    code: &'static str,
+    constraint: Option<String>,
+}
+
+impl fmt::Display for PgCompatibleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for PgCompatibleError {}
+
+impl DatabaseError for PgCompatibleError {
+    fn message(&self) -> &str {
+        &self.message
+    }
+
+    fn code(&self) -> Option<Cow<'_, str>> {
+        Some(Cow::Borrowed(self.code))
+    }
+
+    fn as_error(&self) -> &(dyn std::error::Error + Send + Sync + 'static) {
+        self
+    }
+
+    fn as_error_mut(&mut self) -> &mut (dyn std::error::Error + Send + Sync + 'static) {
+        self
+    }
+
+    fn into_error(self: Box<Self>) -> Box<dyn std::error::Error + Send + Sync + 'static> {
+        self
+    }
+
+    fn constraint(&self) -> Option<&str> {
+        self.constraint.as_deref()
+    }
+
+    fn kind(&self) -> ErrorKind {
+        match self.code {
+            "23505" => ErrorKind::UniqueViolation,
+            _ => ErrorKind::Other,
+        }
+    }
+}
@@ -0,0 +1,76 @@
+use std::ops::{Deref, DerefMut};
+use std::sync::Arc;
+
+use sqlx::pool::PoolConnection;
+use sqlx::{Database, Error, Pool, Transaction};
+use tokio::sync::{Mutex, MutexGuard};
+
+use super::ReplicaRouter;
+
+/// A transaction shared by the repositories of one unit of work. `None` once
+/// it has been committed or rolled back.
+pub type SharedTransaction<DB> = Arc<Mutex<Option<Transaction<'static, DB>>>>;
+
+/// Where a SQL repository runs its queries: straight on the pool, on the
+/// primary and its read replicas, or inside the transaction of a unit of
+/// work.
+pub enum Executor<DB: Database> {
+    Pool(Pool<DB>),
+    Replicated(Arc<ReplicaRouter<DB>>),
+    Transaction(SharedTransaction<DB>),
+}
+
+impl<DB: Database> Executor<DB> {
+    /// A connection for the next query. Inside a transaction this waits for
+    /// the previous query of the unit of work to finish.
+    pub async fn acquire(&self) -> Result<Connection<'_, DB>, Error> {
+        match self {
+            Executor::Pool(pool) => Ok(Connection::Pooled(pool.acquire().await?)),
+            Executor::Replicated(router) => {
+                Ok(Connection::Pooled(router.primary().acquire().await?))
+            }
+            Executor::Transaction(transaction) => {
+                let guard = transaction.lock().await;
+                if guard.is_none() {
+                    return Err(Error::Protocol("transaction already finished".to_string()));
+                }
+                Ok(Connection::Transaction(guard))
+            }
+        }
+    }
+
+    /// A connection for a read-only query that tolerates replication lag.
+    /// Only differs from [`Executor::acquire`] with replicas.
+    pub async fn acquire_read(&self) -> Result<Connection<'_, DB>, Error> {
+        match self {
+            Executor::Replicated(router) => Ok(Connection::Pooled(router.acquire_read().await?)),
+            _ => self.acquire().await,
+        }
+    }
+}
+
+pub enum Connection<'a, DB: Database> {
+    Pooled(PoolConnection<DB>),
+    Transaction(MutexGuard<'a, Option<Transaction<'static, DB>>>),
+}
+
+impl<DB: Database> Deref for Connection<'_, DB> {
+    type Target = DB::Connection;
+
+    fn deref(&self) -> &Self::Target {
+        match self {
+            Connection::Pooled(connection) => connection,
+            // Checked in `Executor::acquire`.
+            Connection::Transaction(transaction) => transaction.as_ref().unwrap(),
+        }
+    }
+}
+
+impl<DB: Database> DerefMut for Connection<'_, DB> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        match self {
+            Connection::Pooled(connection) => connection,
+            Connection::Transaction(transaction) => transaction.as_mut().unwrap(),
+        }
+    }
+}
@@ -0,0 +1,368 @@
+use std::sync::{Arc, RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+use async_trait::async_trait;
+use chrono::Utc;
+use sqlx::Error;
+use uuid::Uuid;
+
+use crate::abstract_trait::{
+    BoxUnitOfWork, DynNoteRepository, DynUserRepository, NoteRepositoryTrait,
+    UnitOfWorkFactoryTrait, UnitOfWorkTrait, UserRepositoryTrait,
+};
+use crate::models::{NoteModel, UserModel};
+
+use super::database_error::{serialization_failure, unique_violation, value_too_long};
+
+/// Tables shared by in-memory repositories and units of work. Cloning gives
+/// another handle to the same tables.
+#[derive(Clone, Default)]
+pub struct InMemoryDatabase {
+    tables: Arc<RwLock<Tables>>,
+}
+
+#[derive(Clone, Default)]
+struct Tables {
+    /// In creation order.
+    notes: Vec<NoteModel>,
+    users: Vec<UserModel>,
+    /// Bumped by every write, so a unit of work can tell whether the tables
+    /// changed since it began.
+    version: u64,
+}
+
+impl InMemoryDatabase {
+    fn read(&self) -> RwLockReadGuard<'_, Tables> {
+        self.tables.read().unwrap()
+    }
+
+    fn write(&self) -> RwLockWriteGuard<'_, Tables> {
+        self.tables.write().unwrap()
+    }
+
+    /// Independent copy of the current tables.
+    fn snapshot(&self) -> InMemoryDatabase {
+        InMemoryDatabase {
+            tables: Arc::new(RwLock::new(self.read().clone())),
+        }
+    }
+}
+
+/// Notes kept in memory, behaving like [`NoteRepository`] against the
+/// `notes` table: titles are unique, long values are rejected and the
+/// timestamps are maintained the same way.
+///
+/// [`NoteRepository`]: super::NoteRepository
+#[derive(Default)]
+pub struct InMemoryNoteRepository {
+    database: InMemoryDatabase,
+}
+
+impl InMemoryNoteRepository {
+    pub fn new(database: InMemoryDatabase) -> Self {
+        Self { database }
+    }
+}
+
+#[async_trait]
+impl NoteRepositoryTrait for InMemoryNoteRepository {
+    async fn get_notes(&self) -> Result<Vec<NoteModel>, Error> {
+        Ok(self.database.read().notes.clone())
+    }
+
+    async fn get_note_id(&self, id: Uuid) -> Result<Option<NoteModel>, Error> {
+        let tables = self.database.read();
+        Ok(tables.notes.iter().find(|note| note.id == id).cloned())
+    }
+
+    async fn create_note(&self, title: &str, content: &str) -> Result<NoteModel, Error> {
+        check_length(title, 255)?;
+
+        let mut tables = self.database.write();
+        if tables.notes.iter().any(|note| note.title == title) {
+            return Err(unique_violation("notes_title_key"));
+        }
+
+        let now = Utc::now();
+        let note = NoteModel {
+            id: Uuid::new_v4(),
+            title: title.to_string(),
+            content: content.to_string(),
+            created_at: Some(now),
+            updated_at: Some(now),
+        };
+        tables.notes.push(note.clone());
+        tables.version += 1;
+
+        Ok(note)
+    }
+
+    async fn update_note(
+        &self,
+        id: Uuid,
+        title: &str,
+        content: &str,
+    ) -> Result<Option<NoteModel>, Error> {
+        check_length(title, 255)?;
+
+        let mut tables = self.database.write();
+        if tables
+            .notes
+            .iter()
+            .any(|note| note.title == title && note.id != id)
+        {
+            return Err(unique_violation("notes_title_key"));
+        }
+
+        let Some(note) = tables.notes.iter_mut().find(|note| note.id == id) else {
+            return Ok(None);
+        };
+        note.title = title.to_string();
+        note.content = content.to_string();
+        note.updated_at = Some(Utc::now());
+        let note = note.clone();
+        tables.version += 1;
+
+        Ok(Some(note))
+    }
+
+    async fn delete(&self, id: Uuid) -> Result<(), Error> {
+        let mut tables = self.database.write();
+        let before = tables.notes.len();
+        tables.notes.retain(|note| note.id != id);
+        if tables.notes.len() < before {
+            tables.version += 1;
+        }
+        Ok(())
+    }
+
+    async fn count(&self) -> Result<i64, Error> {
+        Ok(self.database.read().notes.len() as i64)
+    }
+}
+
+/// Users kept in memory, behaving like [`UserRepository`] against the
+/// `users` table: emails are unique regardless of case, like the
+/// `users_email_lower_key` index, new users get the `user` role, and updates
+/// leave `updated_at` alone just like the SQL update does.
+///
+/// [`UserRepository`]: super::UserRepository
+#[derive(Default)]
+pub struct InMemoryUserRepository {
+    database: InMemoryDatabase,
+}
+
+impl InMemoryUserRepository {
+    pub fn new(database: InMemoryDatabase) -> Self {
+        Self { database }
+    }
+}
+
+#[async_trait]
+impl UserRepositoryTrait for InMemoryUserRepository {
+    async fn find_by_email_exists(&self, email: &str) -> Result<bool, Error> {
+        let tables = self.database.read();
+        Ok(tables.users.iter().any(|user| user.email == email))
+    }
+
+    async fn create_user(
+        &self,
+        firstname: &str,
+        lastname: &str,
+        email: &str,
+        password: &str,
+    ) -> Result<UserModel, Error> {
+        check_length(firstname, 100)?;
+        check_length(lastname, 100)?;
+        check_length(email, 255)?;
+        check_length(password, 100)?;
+
+        let mut tables = self.database.write();
+        if tables.users.iter().any(|user| user.email == email) {
+            return Err(unique_violation("users_email_key"));
+        }
+        if tables
+            .users
+            .iter()
+            .any(|user| user.email.to_lowercase() == email.to_lowercase())
+        {
+            return Err(unique_violation("users_email_lower_key"));
+        }
+
+        let now = Utc::now();
+        let user = UserModel {
+            id: Uuid::new_v4(),
+            firstname: firstname.to_string(),
+            lastname: lastname.to_string(),
+            email: email.to_string(),
+            password: password.to_string(),
+            role: "user".to_string(),
+            created_at: Some(now),
+            updated_at: Some(now),
+            disabled_at: None,
+        };
+        tables.users.push(user.clone());
+        tables.version += 1;
+
+        Ok(user)
+    }
+
+    async fn find_by_email(&self, email: &str) -> Result<Option<UserModel>, Error> {
+        let tables = self.database.read();
+        Ok(tables
+            .users
+            .iter()
+            .find(|user| user.email == email)
+            .cloned())
+    }
+
+    async fn find_by_id(&self, id: Uuid) -> Result<Option<UserModel>, Error> {
+        let tables = self.database.read();
+        Ok(tables.users.iter().find(|user| user.id == id).cloned())
+    }
+
+    async fn update_user(
+        &self,
+        email: &str,
+        firstname: &str,
+        lastname: &str,
+        password: &str,
+    ) -> Result<Option<UserModel>, Error> {
+        check_length(firstname, 100)?;
+        check_length(lastname, 100)?;
+        check_length(password, 100)?;
+
+        let mut tables = self.database.write();
+        let Some(user) = tables.users.iter_mut().find(|user| user.email == email) else {
+            return Ok(None);
+        };
+        user.firstname = firstname.to_string();
+        user.lastname = lastname.to_string();
+        user.password = password.to_string();
+        let user = user.clone();
+        tables.version += 1;
+
+        Ok(Some(user))
+    }
+
+    async fn delete_user(&self, email: &str) -> Result<bool, Error> {
+        let mut tables = self.database.write();
+        let before = tables.users.len();
+        tables.users.retain(|user| user.email != email);
+        let deleted = tables.users.len() < before;
+        if deleted {
+            tables.version += 1;
+        }
+        Ok(deleted)
+    }
+
+    async fn update_role(&self, email: &str, role: &str) -> Result<Option<UserModel>, Error> {
+        check_length(role, 50)?;
+
+        let mut tables = self.database.write();
+        let Some(user) = tables.users.iter_mut().find(|user| user.email == email) else {
+            return Ok(None);
+        };
+        user.role = role.to_string();
+        let user = user.clone();
+        tables.version += 1;
+
+        Ok(Some(user))
+    }
+
+    async fn set_disabled(&self, email: &str, disabled: bool) -> Result<Option<UserModel>, Error> {
+        let mut tables = self.database.write();
+        let Some(user) = tables.users.iter_mut().find(|user| user.email == email) else {
+            return Ok(None);
+        };
+        user.disabled_at = if disabled {
+            user.disabled_at.or_else(|| Some(Utc::now()))
+        } else {
+            None
+        };
+        let user = user.clone();
+        tables.version += 1;
+
+        Ok(Some(user))
+    }
+
+    async fn count(&self) -> Result<i64, Error> {
+        Ok(self.database.read().users.len() as i64)
+    }
+}
+
+/// Units of work over a snapshot of the tables, written back on commit.
+/// Commit fails with a serialization failure if anything else wrote to the
+/// tables in the meantime, like a Postgres `SERIALIZABLE` transaction.
+pub struct InMemoryUnitOfWorkFactory {
+    database: InMemoryDatabase,
+}
+
+impl InMemoryUnitOfWorkFactory {
+    pub fn new(database: InMemoryDatabase) -> Self {
+        Self { database }
+    }
+}
+
+#[async_trait]
+impl UnitOfWorkFactoryTrait for InMemoryUnitOfWorkFactory {
+    async fn begin(&self) -> Result<BoxUnitOfWork, Error> {
+        let working = self.database.snapshot();
+        let base_version = working.read().version;
+
+        Ok(Box::new(InMemoryUnitOfWork {
+            database: self.database.clone(),
+            base_version,
+            notes: Arc::new(InMemoryNoteRepository::new(working.clone())),
+            users: Arc::new(InMemoryUserRepository::new(working.clone())),
+            working,
+        }))
+    }
+}
+
+struct InMemoryUnitOfWork {
+    database: InMemoryDatabase,
+    base_version: u64,
+    working: InMemoryDatabase,
+    notes: DynNoteRepository,
+    users: DynUserRepository,
+}
+
+#[async_trait]
+impl UnitOfWorkTrait for InMemoryUnitOfWork {
+    fn notes(&self) -> &DynNoteRepository {
+        &self.notes
+    }
+
+    fn users(&self) -> &DynUserRepository {
+        &self.users
+    }
+
+    async fn commit(self: Box<Self>) -> Result<(), Error> {
+        let working = self.working.read().clone();
+        if working.version == self.base_version {
+            return Ok(());
+        }
+
+        let mut tables = self.database.write();
+        if tables.version != self.base_version {
+            return Err(serialization_failure());
+        }
+        *tables = Tables {
+            version: self.base_version + 1,
+            ..working
+        };
+
+        Ok(())
+    }
+}
+
+/// Mirrors a `VARCHAR(max)` column.
+fn check_length(value: &str, max: usize) -> Result<(), Error> {
+    if value.chars().count() > max {
+        return Err(value_too_long(format!(
+            "value too long for type character varying({})",
+            max
+        )));
+    }
+    Ok(())
+}
@@ -0,0 +1,235 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use sqlx::Error;
+use uuid::Uuid;
+
+use crate::{
+    abstract_trait::{
+        BoxUnitOfWork, DynNoteRepository, DynUnitOfWorkFactory, DynUserRepository,
+        NoteRepositoryTrait, UnitOfWorkFactoryTrait, UnitOfWorkTrait, UserRepositoryTrait,
+    },
+    metrics::Metrics,
+    models::{NoteModel, UserModel},
+};
+
+/// Records the latency of every call to the wrapped note repository.
+pub struct MeteredNoteRepository {
+    inner: DynNoteRepository,
+    metrics: Arc<Metrics>,
+}
+
+impl MeteredNoteRepository {
+    pub fn new(inner: DynNoteRepository, metrics: Arc<Metrics>) -> Self {
+        Self { inner, metrics }
+    }
+}
+
+#[async_trait]
+impl NoteRepositoryTrait for MeteredNoteRepository {
+    async fn get_notes(&self) -> Result<Vec<NoteModel>, Error> {
+        self.metrics
+            .time_query("note", "get_notes", self.inner.get_notes())
+            .await
+    }
+
+    async fn get_note_id(&self, id: Uuid) -> Result<Option<NoteModel>, Error> {
+        self.metrics
+            .time_query("note", "get_note_id", self.inner.get_note_id(id))
+            .await
+    }
+
+    async fn create_note(&self, title: &str, content: &str) -> Result<NoteModel, Error> {
+        self.metrics
+            .time_query(
+                "note",
+                "create_note",
+                self.inner.create_note(title, content),
+            )
+            .await
+    }
+
+    async fn update_note(
+        &self,
+        id: Uuid,
+        title: &str,
+        content: &str,
+    ) -> Result<Option<NoteModel>, Error> {
+        self.metrics
+            .time_query(
+                "note",
+                "update_note",
+                self.inner.update_note(id, title, content),
+            )
+            .await
+    }
+
+    async fn delete(&self, id: Uuid) -> Result<(), Error> {
+        self.metrics
+            .time_query("note", "delete", self.inner.delete(id))
+            .await
+    }
+
+    async fn count(&self) -> Result<i64, Error> {
+        self.metrics
+            .time_query("note", "count", self.inner.count())
+            .await
+    }
+}
+
+/// Records the latency of every call to the wrapped user repository.
+pub struct MeteredUserRepository {
+    inner: DynUserRepository,
+    metrics: Arc<Metrics>,
+}
+
+impl MeteredUserRepository {
+    pub fn new(inner: DynUserRepository, metrics: Arc<Metrics>) -> Self {
+        Self { inner, metrics }
+    }
+}
+
+#[async_trait]
+impl UserRepositoryTrait for MeteredUserRepository {
+    async fn find_by_email_exists(&self, email: &str) -> Result<bool, Error> {
+        self.metrics
+            .time_query(
+                "user",
+                "find_by_email_exists",
+                self.inner.find_by_email_exists(email),
+            )
+            .await
+    }
+
+    async fn create_user(
+        &self,
+        firstname: &str,
+        lastname: &str,
+        email: &str,
+        password: &str,
+    ) -> Result<UserModel, Error> {
+        self.metrics
+            .time_query(
+                "user",
+                "create_user",
+                self.inner.create_user(firstname, lastname, email, password),
+            )
+            .await
+    }
+
+    async fn find_by_email(&self, email: &str) -> Result<Option<UserModel>, Error> {
+        self.metrics
+            .time_query("user", "find_by_email", self.inner.find_by_email(email))
+            .await
+    }
+
+    async fn find_by_id(&self, id: Uuid) -> Result<Option<UserModel>, Error> {
+        self.metrics
+            .time_query("user", "find_by_id", self.inner.find_by_id(id))
+            .await
+    }
+
+    async fn update_user(
+        &self,
+        email: &str,
+        firstname: &str,
+        lastname: &str,
+        password: &str,
+    ) -> Result<Option<UserModel>, Error> {
+        self.metrics
+            .time_query(
+                "user",
+                "update_user",
+                self.inner.update_user(email, firstname, lastname, password),
+            )
+            .await
+    }
+
+    async fn delete_user(&self, email: &str) -> Result<bool, Error> {
+        self.metrics
+            .time_query("user", "delete_user", self.inner.delete_user(email))
+            .await
+    }
+
+    async fn update_role(&self, email: &str, role: &str) -> Result<Option<UserModel>, Error> {
+        self.metrics
+            .time_query("user", "update_role", self.inner.update_role(email, role))
+            .await
+    }
+
+    async fn set_disabled(&self, email: &str, disabled: bool) -> Result<Option<UserModel>, Error> {
+        self.metrics
+            .time_query(
+                "user",
+                "set_disabled",
+                self.inner.set_disabled(email, disabled),
+            )
+            .await
+    }
+
+    async fn count(&self) -> Result<i64, Error> {
+        self.metrics
+            .time_query("user", "count", self.inner.count())
+            .await
+    }
+}
+
+/// Meters the repositories of every unit of work, and times `begin` and
+/// `commit` like a repository call.
+pub struct MeteredUnitOfWorkFactory {
+    inner: DynUnitOfWorkFactory,
+    metrics: Arc<Metrics>,
+}
+
+impl MeteredUnitOfWorkFactory {
+    pub fn new(inner: DynUnitOfWorkFactory, metrics: Arc<Metrics>) -> Self {
+        Self { inner, metrics }
+    }
+}
+
+#[async_trait]
+impl UnitOfWorkFactoryTrait for MeteredUnitOfWorkFactory {
+    async fn begin(&self) -> Result<BoxUnitOfWork, Error> {
+        let inner = self
+            .metrics
+            .time_query("unit_of_work", "begin", self.inner.begin())
+            .await?;
+
+        Ok(Box::new(MeteredUnitOfWork {
+            notes: Arc::new(MeteredNoteRepository::new(
+                inner.notes().clone(),
+                self.metrics.clone(),
+            )),
+            users: Arc::new(MeteredUserRepository::new(
+                inner.users().clone(),
+                self.metrics.clone(),
+            )),
+            metrics: self.metrics.clone(),
+            inner,
+        }))
+    }
+}
+
+struct MeteredUnitOfWork {
+    inner: BoxUnitOfWork,
+    notes: DynNoteRepository,
+    users: DynUserRepository,
+    metrics: Arc<Metrics>,
+}
+
+#[async_trait]
+impl UnitOfWorkTrait for MeteredUnitOfWork {
+    fn notes(&self) -> &DynNoteRepository {
+        &self.notes
+    }
+
+    fn users(&self) -> &DynUserRepository {
+        &self.users
+    }
+
+    async fn commit(self: Box<Self>) -> Result<(), Error> {
+        self.metrics
+            .time_query("unit_of_work", "commit", self.inner.commit())
+            .await
+    }
+}
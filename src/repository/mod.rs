@@ -1,5 +1,31 @@
+mod cached_repository;
+mod change_feed;
+#[cfg(test)]
+pub(crate) mod contract_tests;
+mod database_error;
+mod executor;
+mod in_memory_repository;
+mod metered_repository;
 mod note_repository;
+mod rate_limit_store;
+mod replica_router;
+mod sqlite_repository;
+mod unit_of_work;
 mod user_repository;
 
+pub use cached_repository::{
+    CachedNoteRepository, CachedUnitOfWorkFactory, CachedUserRepository, RepositoryCaches,
+};
+pub use change_feed::{ChangeFeedEvent, PgChangeFeed};
+pub use in_memory_repository::{
+    InMemoryDatabase, InMemoryNoteRepository, InMemoryUnitOfWorkFactory, InMemoryUserRepository,
+};
+pub use metered_repository::{
+    MeteredNoteRepository, MeteredUnitOfWorkFactory, MeteredUserRepository,
+};
 pub use note_repository::NoteRepository;
+pub use rate_limit_store::{InMemoryRateLimitStore, PgRateLimitStore};
+pub use replica_router::{pin_reads, ReplicaRouter};
+pub use sqlite_repository::{SqliteNoteRepository, SqliteUserRepository};
+pub use unit_of_work::SqlUnitOfWorkFactory;
 pub use user_repository::UserRepository;
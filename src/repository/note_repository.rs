@@ -1,44 +1,82 @@
+use std::sync::Arc;
+
 use crate::abstract_trait::NoteRepositoryTrait;
-use crate::config::ConnectionPool;
 use async_trait::async_trait;
 use chrono::Utc;
-use sqlx::Error;
+use sqlx::{Error, PgPool, Postgres};
+use tracing::instrument;
 use uuid::Uuid;
 
 use crate::models::NoteModel;
 
+use super::executor::{Executor, SharedTransaction};
+use super::ReplicaRouter;
+
 pub struct NoteRepository {
-    pub db_pool: ConnectionPool,
+    executor: Executor<Postgres>,
 }
 
 impl NoteRepository {
-    pub fn new(db_pool: ConnectionPool) -> Self {
-        Self { db_pool }
+    pub fn new(db_pool: PgPool) -> Self {
+        Self {
+            executor: Executor::Pool(db_pool),
+        }
+    }
+
+    /// Sends `get_notes` and `get_note_id` to `router`'s replicas and everything else to its
+    /// primary.
+    pub fn with_replicas(router: Arc<ReplicaRouter<Postgres>>) -> Self {
+        Self {
+            executor: Executor::Replicated(router),
+        }
+    }
+
+    /// Runs every query in `transaction`, see [`SqlUnitOfWorkFactory`].
+    ///
+    /// [`SqlUnitOfWorkFactory`]: super::SqlUnitOfWorkFactory
+    pub fn in_transaction(transaction: SharedTransaction<Postgres>) -> Self {
+        Self {
+            executor: Executor::Transaction(transaction),
+        }
     }
 }
 
 #[async_trait]
 impl NoteRepositoryTrait for NoteRepository {
+    #[instrument(
+        name = "NoteRepository::get_notes",
+        skip_all,
+        fields(otel.kind = "client", db.operation = "SELECT", db.sql.table = "notes")
+    )]
     async fn get_notes(&self) -> Result<Vec<NoteModel>, Error> {
-        let notes = sqlx::query_as::<_, NoteModel>("SELECT * FROM notes")
-            .fetch_all(&self.db_pool)
+        let notes = sqlx::query_as::<_, NoteModel>("SELECT * FROM notes ORDER BY created_at")
+            .fetch_all(&mut *self.executor.acquire_read().await?)
             .await?;
 
         Ok(notes)
     }
 
+    #[instrument(
+        name = "NoteRepository::get_note_id",
+        skip_all,
+        fields(otel.kind = "client", db.operation = "SELECT", db.sql.table = "notes")
+    )]
     async fn get_note_id(&self, id: Uuid) -> Result<Option<NoteModel>, Error> {
         let todo = sqlx::query_as::<_, NoteModel>("SELECT * FROM notes WHERE id = $1")
             .bind(id)
-            .fetch_optional(&self.db_pool)
+            .fetch_optional(&mut *self.executor.acquire_read().await?)
             .await?;
 
         Ok(todo)
     }
 
+    #[instrument(
+        name = "NoteRepository::create_note",
+        skip_all,
+        fields(otel.kind = "client", db.operation = "INSERT", db.sql.table = "notes")
+    )]
     async fn create_note(&self, title: &str, content: &str) -> Result<NoteModel, Error> {
-        let created_at = Utc::now();
-        let updated_at = Utc::now();
+        let now = Utc::now();
 
         let note = sqlx::query_as::<_, NoteModel>(
             "INSERT INTO notes (id, title, content, created_at, updated_at) VALUES ($1, $2, $3, $4, $5) RETURNING *",
@@ -46,14 +84,19 @@ impl NoteRepositoryTrait for NoteRepository {
         .bind(Uuid::new_v4())
         .bind(title)
         .bind(content)
-        .bind(created_at)
-        .bind(updated_at)
-        .fetch_one(&self.db_pool)
+        .bind(now)
+        .bind(now)
+        .fetch_one(&mut *self.executor.acquire().await?)
         .await?;
 
         Ok(note)
     }
 
+    #[instrument(
+        name = "NoteRepository::update_note",
+        skip_all,
+        fields(otel.kind = "client", db.operation = "UPDATE", db.sql.table = "notes")
+    )]
     async fn update_note(
         &self,
         id: Uuid,
@@ -69,12 +112,17 @@ impl NoteRepositoryTrait for NoteRepository {
         .bind(content)
         .bind(updated_at)
         .bind(id)
-        .fetch_optional(&self.db_pool)
+        .fetch_optional(&mut *self.executor.acquire().await?)
         .await?;
 
         Ok(note)
     }
 
+    #[instrument(
+        name = "NoteRepository::delete",
+        skip_all,
+        fields(otel.kind = "client", db.operation = "DELETE", db.sql.table = "notes")
+    )]
     async fn delete(&self, id: Uuid) -> Result<(), Error> {
         sqlx::query!(
             r#"
@@ -83,9 +131,20 @@ impl NoteRepositoryTrait for NoteRepository {
             "#,
             id,
         )
-        .execute(&self.db_pool)
+        .execute(&mut *self.executor.acquire().await?)
         .await?;
 
         Ok(())
     }
+
+    #[instrument(
+        name = "NoteRepository::count",
+        skip_all,
+        fields(otel.kind = "client", db.operation = "SELECT", db.sql.table = "notes")
+    )]
+    async fn count(&self) -> Result<i64, Error> {
+        sqlx::query_scalar("SELECT COUNT(*) FROM notes")
+            .fetch_one(&mut *self.executor.acquire().await?)
+            .await
+    }
 }
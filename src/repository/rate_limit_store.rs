@@ -0,0 +1,191 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use async_trait::async_trait;
+use sqlx::{Error, PgPool};
+use tracing::instrument;
+
+use crate::abstract_trait::RateLimitStoreTrait;
+use crate::models::RateLimitBucketModel;
+
+struct Bucket {
+    tokens: f64,
+    updated_at: Instant,
+    full_at: Instant,
+}
+
+/// Keeps buckets in process memory. Limits are per instance.
+#[derive(Default)]
+pub struct InMemoryRateLimitStore {
+    buckets: Mutex<HashMap<String, Bucket>>,
+}
+
+#[async_trait]
+impl RateLimitStoreTrait for InMemoryRateLimitStore {
+    async fn acquire(
+        &self,
+        key: &str,
+        capacity: f64,
+        refill_per_sec: f64,
+    ) -> Result<RateLimitBucketModel, Error> {
+        let now = Instant::now();
+        let mut buckets = self.buckets.lock().unwrap();
+
+        let refilled = match buckets.get(key) {
+            Some(bucket) => {
+                let elapsed = now.duration_since(bucket.updated_at).as_secs_f64();
+                (bucket.tokens + elapsed * refill_per_sec).min(capacity)
+            }
+            None => capacity,
+        };
+        let allowed = refilled >= 1.0;
+        let tokens = if allowed { refilled - 1.0 } else { refilled };
+
+        buckets.insert(
+            key.to_string(),
+            Bucket {
+                tokens,
+                updated_at: now,
+                full_at: now + Duration::from_secs_f64((capacity - tokens) / refill_per_sec),
+            },
+        );
+
+        Ok(RateLimitBucketModel { tokens, allowed })
+    }
+
+    async fn purge_expired(&self) -> Result<u64, Error> {
+        let now = Instant::now();
+        let mut buckets = self.buckets.lock().unwrap();
+        let before = buckets.len();
+        buckets.retain(|_, bucket| bucket.full_at > now);
+
+        Ok((before - buckets.len()) as u64)
+    }
+}
+
+/// Keeps buckets in the `rate_limit_buckets` table so every instance using
+/// the database shares them. Refills use the database clock, so instances
+/// do not need synchronised clocks.
+pub struct PgRateLimitStore {
+    pub db_pool: PgPool,
+}
+
+impl PgRateLimitStore {
+    pub fn new(db_pool: PgPool) -> Self {
+        Self { db_pool }
+    }
+}
+
+#[async_trait]
+impl RateLimitStoreTrait for PgRateLimitStore {
+    #[instrument(
+        name = "PgRateLimitStore::acquire",
+        skip_all,
+        fields(otel.kind = "client", db.operation = "INSERT", db.sql.table = "rate_limit_buckets")
+    )]
+    async fn acquire(
+        &self,
+        key: &str,
+        capacity: f64,
+        refill_per_sec: f64,
+    ) -> Result<RateLimitBucketModel, Error> {
+        // A single upsert, so concurrent requests for the same key are
+        // serialised by the row lock.
+        sqlx::query_as::<_, RateLimitBucketModel>(
+            r#"
+            INSERT INTO rate_limit_buckets AS b (key, tokens, allowed, updated_at, expires_at)
+            VALUES ($1, $2 - 1, TRUE, NOW(), NOW() + make_interval(secs => 1 / $3))
+            ON CONFLICT (key) DO UPDATE SET (tokens, allowed, updated_at, expires_at) = (
+                SELECT r.tokens, r.allowed, NOW(), NOW() + make_interval(secs => ($2 - r.tokens) / $3)
+                FROM (
+                    SELECT
+                        CASE WHEN refilled >= 1 THEN refilled - 1 ELSE refilled END AS tokens,
+                        refilled >= 1 AS allowed
+                    FROM (
+                        SELECT LEAST(
+                            $2,
+                            b.tokens + EXTRACT(EPOCH FROM NOW() - b.updated_at)::float8 * $3
+                        ) AS refilled
+                    ) t
+                ) r
+            )
+            RETURNING tokens, allowed
+            "#,
+        )
+        .bind(key)
+        .bind(capacity)
+        .bind(refill_per_sec)
+        .fetch_one(&self.db_pool)
+        .await
+    }
+
+    #[instrument(
+        name = "PgRateLimitStore::purge_expired",
+        skip_all,
+        fields(otel.kind = "client", db.operation = "DELETE", db.sql.table = "rate_limit_buckets")
+    )]
+    async fn purge_expired(&self) -> Result<u64, Error> {
+        let result = sqlx::query("DELETE FROM rate_limit_buckets WHERE expires_at <= NOW()")
+            .execute(&self.db_pool)
+            .await?;
+
+        Ok(result.rows_affected())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repository::contract_tests::with_postgres;
+
+    async fn buckets_run_out_and_refill(store: &dyn RateLimitStoreTrait) {
+        // Two tokens, refilled at one every 100ms.
+        for tokens in [1.0, 0.0] {
+            let bucket = store.acquire("ip:1", 2.0, 10.0).await.unwrap();
+            assert!(bucket.allowed);
+            assert!(bucket.tokens - tokens < 0.1, "{}", bucket.tokens);
+        }
+        assert!(!store.acquire("ip:1", 2.0, 10.0).await.unwrap().allowed);
+        assert!(store.acquire("ip:2", 2.0, 10.0).await.unwrap().allowed);
+
+        tokio::time::sleep(Duration::from_millis(150)).await;
+        assert!(store.acquire("ip:1", 2.0, 10.0).await.unwrap().allowed);
+        assert!(!store.acquire("ip:1", 2.0, 10.0).await.unwrap().allowed);
+    }
+
+    async fn only_full_buckets_are_purged(store: &dyn RateLimitStoreTrait) {
+        store.acquire("fast", 1.0, 20.0).await.unwrap();
+        store.acquire("slow", 1.0, 0.001).await.unwrap();
+
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert_eq!(store.purge_expired().await.unwrap(), 1);
+        assert!(!store.acquire("slow", 1.0, 0.001).await.unwrap().allowed);
+    }
+
+    #[tokio::test]
+    async fn in_memory_buckets_run_out_and_refill() {
+        buckets_run_out_and_refill(&InMemoryRateLimitStore::default()).await;
+    }
+
+    #[tokio::test]
+    async fn in_memory_only_full_buckets_are_purged() {
+        only_full_buckets_are_purged(&InMemoryRateLimitStore::default()).await;
+    }
+
+    #[tokio::test]
+    async fn postgres_buckets_run_out_and_refill() {
+        with_postgres(|pool| async move {
+            buckets_run_out_and_refill(&PgRateLimitStore::new(pool)).await;
+        })
+        .await;
+    }
+
+    #[tokio::test]
+    async fn postgres_only_full_buckets_are_purged() {
+        with_postgres(|pool| async move {
+            only_full_buckets_are_purged(&PgRateLimitStore::new(pool)).await;
+        })
+        .await;
+    }
+}
@@ -0,0 +1,240 @@
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+use sqlx::pool::PoolConnection;
+use sqlx::{Connection, Database, Error, Pool};
+
+tokio::task_local! {
+    static PRIMARY_READS: bool;
+}
+
+/// Runs `fut` with every read of a [`ReplicaRouter`] going to the primary
+/// when `pinned` is set, e.g. for a caller who just wrote and must see their
+/// own changes.
+pub async fn pin_reads<F: Future>(pinned: bool, fut: F) -> F::Output {
+    PRIMARY_READS.scope(pinned, fut).await
+}
+
+pub(super) fn reads_pinned() -> bool {
+    PRIMARY_READS.try_with(|pinned| *pinned).unwrap_or(false)
+}
+
+/// A primary and its read replicas. Reads rotate over the replicas that are
+/// in rotation; a replica that fails to hand out a connection is taken out
+/// until [`ReplicaRouter::check_replicas`] reaches it again. Without a
+/// usable replica, reads go to the primary.
+pub struct ReplicaRouter<DB: Database> {
+    primary: Pool<DB>,
+    replicas: Vec<Replica<DB>>,
+    next: AtomicUsize,
+}
+
+struct Replica<DB: Database> {
+    name: String,
+    pool: Pool<DB>,
+    healthy: AtomicBool,
+}
+
+impl<DB: Database> Replica<DB> {
+    /// Updates the replica's health, logging when it changes.
+    fn set_healthy(&self, healthy: bool, err: Option<&Error>) {
+        if self.healthy.swap(healthy, Ordering::Relaxed) == healthy {
+            return;
+        }
+        match err {
+            Some(err) => log::warn!("Replica {} out of rotation: {}", self.name, err),
+            None => log::info!("Replica {} back in rotation", self.name),
+        }
+    }
+}
+
+impl<DB: Database> ReplicaRouter<DB> {
+    /// Replicas start in rotation. The logs name them by their position in
+    /// `replicas`, counting from 1.
+    pub fn new(primary: Pool<DB>, replicas: Vec<Pool<DB>>) -> Self {
+        Self {
+            primary,
+            replicas: replicas
+                .into_iter()
+                .enumerate()
+                .map(|(index, pool)| Replica {
+                    name: format!("#{}", index + 1),
+                    pool,
+                    healthy: AtomicBool::new(true),
+                })
+                .collect(),
+            next: AtomicUsize::new(0),
+        }
+    }
+
+    pub fn primary(&self) -> &Pool<DB> {
+        &self.primary
+    }
+
+    /// A connection for a read-only query: from the next replica in
+    /// rotation, or from the primary when reads are pinned to it or no
+    /// replica can serve.
+    pub async fn acquire_read(&self) -> Result<PoolConnection<DB>, Error> {
+        if !reads_pinned() && !self.replicas.is_empty() {
+            let start = self.next.fetch_add(1, Ordering::Relaxed);
+            for offset in 0..self.replicas.len() {
+                let replica = &self.replicas[(start + offset) % self.replicas.len()];
+                if !replica.healthy.load(Ordering::Relaxed) {
+                    continue;
+                }
+                match replica.pool.acquire().await {
+                    Ok(connection) => return Ok(connection),
+                    Err(err) => replica.set_healthy(false, Some(&err)),
+                }
+            }
+        }
+
+        self.primary.acquire().await
+    }
+
+    /// Pings every replica, taking unreachable ones out of rotation and
+    /// putting recovered ones back.
+    pub async fn check_replicas(&self) {
+        for replica in &self.replicas {
+            let result = match replica.pool.acquire().await {
+                Ok(mut connection) => connection.ping().await,
+                Err(err) => Err(err),
+            };
+            replica.set_healthy(result.is_ok(), result.as_ref().err());
+        }
+    }
+
+    /// How many replicas are in rotation.
+    pub fn healthy_replicas(&self) -> usize {
+        self.replicas
+            .iter()
+            .filter(|replica| replica.healthy.load(Ordering::Relaxed))
+            .count()
+    }
+
+    /// Closes the replica pools. The primary is closed by its owner.
+    pub async fn close_replicas(&self) {
+        for replica in &self.replicas {
+            replica.pool.close().await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+    use std::time::Duration;
+
+    use sqlx::sqlite::{SqliteConnectOptions, SqlitePool, SqlitePoolOptions};
+    use sqlx::Sqlite;
+
+    use super::*;
+
+    /// A single-connection database that answers `SELECT name FROM server`
+    /// with `name`.
+    async fn server(name: &str) -> SqlitePool {
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .idle_timeout(None)
+            .max_lifetime(None)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap();
+        sqlx::query("CREATE TABLE server (name TEXT)")
+            .execute(&pool)
+            .await
+            .unwrap();
+        sqlx::query("INSERT INTO server VALUES (?)")
+            .bind(name)
+            .execute(&pool)
+            .await
+            .unwrap();
+        pool
+    }
+
+    /// A database in `dir`, reachable once `dir` exists.
+    fn server_in(dir: &Path) -> SqlitePool {
+        SqlitePoolOptions::new()
+            .acquire_timeout(Duration::from_millis(200))
+            .connect_lazy_with(
+                SqliteConnectOptions::new()
+                    .filename(dir.join("replica.db"))
+                    .create_if_missing(true),
+            )
+    }
+
+    async fn read_from(router: &ReplicaRouter<Sqlite>) -> String {
+        let mut connection = router.acquire_read().await.unwrap();
+        sqlx::query_scalar("SELECT name FROM server")
+            .fetch_one(&mut *connection)
+            .await
+            .unwrap_or_else(|_| "empty".to_string())
+    }
+
+    #[tokio::test]
+    async fn reads_rotate_over_replicas() {
+        let router = ReplicaRouter::new(
+            server("primary").await,
+            vec![server("first").await, server("second").await],
+        );
+
+        let mut reads = Vec::new();
+        for _ in 0..4 {
+            reads.push(read_from(&router).await);
+        }
+        assert_eq!(reads, ["first", "second", "first", "second"]);
+    }
+
+    #[tokio::test]
+    async fn pinned_reads_go_to_the_primary() {
+        let router = ReplicaRouter::new(server("primary").await, vec![server("replica").await]);
+
+        assert_eq!(pin_reads(true, read_from(&router)).await, "primary");
+        assert_eq!(pin_reads(false, read_from(&router)).await, "replica");
+        assert_eq!(read_from(&router).await, "replica");
+    }
+
+    #[tokio::test]
+    async fn without_replicas_reads_go_to_the_primary() {
+        let router = ReplicaRouter::new(server("primary").await, Vec::new());
+
+        assert_eq!(read_from(&router).await, "primary");
+    }
+
+    #[tokio::test]
+    async fn unreachable_replicas_leave_and_rejoin_the_rotation() {
+        let dir = std::env::temp_dir().join(format!("crudsqlx_replica_{}", uuid::Uuid::new_v4()));
+        let router = ReplicaRouter::new(
+            server("primary").await,
+            vec![server("replica").await, server_in(&dir)],
+        );
+
+        // The failing replica is skipped for the read and taken out.
+        for _ in 0..4 {
+            assert_eq!(read_from(&router).await, "replica");
+        }
+        assert_eq!(router.healthy_replicas(), 1);
+
+        std::fs::create_dir_all(&dir).unwrap();
+        router.check_replicas().await;
+        assert_eq!(router.healthy_replicas(), 2);
+        let reads = [read_from(&router).await, read_from(&router).await];
+        assert!(reads.contains(&"empty".to_string()), "{:?}", reads);
+
+        router.close_replicas().await;
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn falls_back_to_the_primary_when_no_replica_can_serve() {
+        let replica = server("replica").await;
+        let router = ReplicaRouter::new(server("primary").await, vec![replica.clone()]);
+
+        replica.close().await;
+        assert_eq!(read_from(&router).await, "primary");
+        assert_eq!(router.healthy_replicas(), 0);
+
+        router.check_replicas().await;
+        assert_eq!(router.healthy_replicas(), 0);
+    }
+}
@@ -0,0 +1,425 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use sqlx::error::ErrorKind;
+use sqlx::{Error, FromRow, Sqlite, SqlitePool};
+use tracing::instrument;
+use uuid::fmt::Hyphenated;
+use uuid::Uuid;
+
+use crate::abstract_trait::{NoteRepositoryTrait, UserRepositoryTrait};
+use crate::models::{NoteModel, UserModel};
+
+use super::database_error::{serialization_failure, unique_violation, value_too_long};
+use super::executor::{Executor, SharedTransaction};
+
+/// Notes in SQLite, see `migrations_sqlite`. Ids are stored as hyphenated
+/// text, and constraint violations are reported like Postgres reports them.
+///
+/// Statements with `RETURNING` are read with `fetch_all`: SQLite only
+/// commits once the statement has run to completion, and stopping after the
+/// first row would hide the write from other connections until the
+/// statement is reset.
+pub struct SqliteNoteRepository {
+    executor: Executor<Sqlite>,
+}
+
+impl SqliteNoteRepository {
+    pub fn new(db_pool: SqlitePool) -> Self {
+        Self {
+            executor: Executor::Pool(db_pool),
+        }
+    }
+
+    /// Runs every query in `transaction`, see [`SqlUnitOfWorkFactory`].
+    ///
+    /// [`SqlUnitOfWorkFactory`]: super::SqlUnitOfWorkFactory
+    pub fn in_transaction(transaction: SharedTransaction<Sqlite>) -> Self {
+        Self {
+            executor: Executor::Transaction(transaction),
+        }
+    }
+}
+
+#[derive(FromRow)]
+struct NoteRow {
+    id: Hyphenated,
+    title: String,
+    content: String,
+    created_at: Option<DateTime<Utc>>,
+    updated_at: Option<DateTime<Utc>>,
+}
+
+impl From<NoteRow> for NoteModel {
+    fn from(row: NoteRow) -> Self {
+        NoteModel {
+            id: row.id.into_uuid(),
+            title: row.title,
+            content: row.content,
+            created_at: row.created_at,
+            updated_at: row.updated_at,
+        }
+    }
+}
+
+#[async_trait]
+impl NoteRepositoryTrait for SqliteNoteRepository {
+    #[instrument(
+        name = "SqliteNoteRepository::get_notes",
+        skip_all,
+        fields(otel.kind = "client", db.operation = "SELECT", db.sql.table = "notes")
+    )]
+    async fn get_notes(&self) -> Result<Vec<NoteModel>, Error> {
+        let notes = sqlx::query_as::<_, NoteRow>("SELECT * FROM notes ORDER BY created_at")
+            .fetch_all(&mut *self.executor.acquire_read().await?)
+            .await?;
+
+        Ok(notes.into_iter().map(NoteModel::from).collect())
+    }
+
+    #[instrument(
+        name = "SqliteNoteRepository::get_note_id",
+        skip_all,
+        fields(otel.kind = "client", db.operation = "SELECT", db.sql.table = "notes")
+    )]
+    async fn get_note_id(&self, id: Uuid) -> Result<Option<NoteModel>, Error> {
+        let note = sqlx::query_as::<_, NoteRow>("SELECT * FROM notes WHERE id = ?")
+            .bind(id.hyphenated())
+            .fetch_optional(&mut *self.executor.acquire_read().await?)
+            .await?;
+
+        Ok(note.map(NoteModel::from))
+    }
+
+    #[instrument(
+        name = "SqliteNoteRepository::create_note",
+        skip_all,
+        fields(otel.kind = "client", db.operation = "INSERT", db.sql.table = "notes")
+    )]
+    async fn create_note(&self, title: &str, content: &str) -> Result<NoteModel, Error> {
+        let now = Utc::now();
+
+        let note = sqlx::query_as::<_, NoteRow>(
+            "INSERT INTO notes (id, title, content, created_at, updated_at) VALUES (?, ?, ?, ?, ?) RETURNING *",
+        )
+        .bind(Uuid::new_v4().hyphenated())
+        .bind(title)
+        .bind(content)
+        .bind(now)
+        .bind(now)
+        .fetch_all(&mut *self.executor.acquire().await?)
+        .await
+        .map_err(pg_compatible)?
+        .pop()
+        .ok_or(Error::RowNotFound)?;
+
+        Ok(note.into())
+    }
+
+    #[instrument(
+        name = "SqliteNoteRepository::update_note",
+        skip_all,
+        fields(otel.kind = "client", db.operation = "UPDATE", db.sql.table = "notes")
+    )]
+    async fn update_note(
+        &self,
+        id: Uuid,
+        title: &str,
+        content: &str,
+    ) -> Result<Option<NoteModel>, Error> {
+        let note = sqlx::query_as::<_, NoteRow>(
+            "UPDATE notes SET title = ?, content = ?, updated_at = ? WHERE id = ? RETURNING *",
+        )
+        .bind(title)
+        .bind(content)
+        .bind(Utc::now())
+        .bind(id.hyphenated())
+        .fetch_all(&mut *self.executor.acquire().await?)
+        .await
+        .map_err(pg_compatible)?
+        .pop();
+
+        Ok(note.map(NoteModel::from))
+    }
+
+    #[instrument(
+        name = "SqliteNoteRepository::delete",
+        skip_all,
+        fields(otel.kind = "client", db.operation = "DELETE", db.sql.table = "notes")
+    )]
+    async fn delete(&self, id: Uuid) -> Result<(), Error> {
+        sqlx::query("DELETE FROM notes WHERE id = ?")
+            .bind(id.hyphenated())
+            .execute(&mut *self.executor.acquire().await?)
+            .await?;
+
+        Ok(())
+    }
+
+    #[instrument(
+        name = "SqliteNoteRepository::count",
+        skip_all,
+        fields(otel.kind = "client", db.operation = "SELECT", db.sql.table = "notes")
+    )]
+    async fn count(&self) -> Result<i64, Error> {
+        sqlx::query_scalar("SELECT COUNT(*) FROM notes")
+            .fetch_one(&mut *self.executor.acquire().await?)
+            .await
+    }
+}
+
+/// Users in SQLite, see `migrations_sqlite`. Like [`UserRepository`], new
+/// users get the `user` role and updates leave `updated_at` alone.
+///
+/// [`UserRepository`]: super::UserRepository
+pub struct SqliteUserRepository {
+    executor: Executor<Sqlite>,
+}
+
+impl SqliteUserRepository {
+    pub fn new(db_pool: SqlitePool) -> Self {
+        Self {
+            executor: Executor::Pool(db_pool),
+        }
+    }
+
+    /// Runs every query in `transaction`, see [`SqlUnitOfWorkFactory`].
+    ///
+    /// [`SqlUnitOfWorkFactory`]: super::SqlUnitOfWorkFactory
+    pub fn in_transaction(transaction: SharedTransaction<Sqlite>) -> Self {
+        Self {
+            executor: Executor::Transaction(transaction),
+        }
+    }
+}
+
+#[derive(FromRow)]
+struct UserRow {
+    id: Hyphenated,
+    firstname: String,
+    lastname: String,
+    email: String,
+    password: String,
+    role: String,
+    created_at: Option<DateTime<Utc>>,
+    updated_at: Option<DateTime<Utc>>,
+    disabled_at: Option<DateTime<Utc>>,
+}
+
+impl From<UserRow> for UserModel {
+    fn from(row: UserRow) -> Self {
+        UserModel {
+            id: row.id.into_uuid(),
+            firstname: row.firstname,
+            lastname: row.lastname,
+            email: row.email,
+            password: row.password,
+            role: row.role,
+            created_at: row.created_at,
+            updated_at: row.updated_at,
+            disabled_at: row.disabled_at,
+        }
+    }
+}
+
+#[async_trait]
+impl UserRepositoryTrait for SqliteUserRepository {
+    #[instrument(
+        name = "SqliteUserRepository::find_by_email_exists",
+        skip_all,
+        fields(otel.kind = "client", db.operation = "SELECT", db.sql.table = "users")
+    )]
+    async fn find_by_email_exists(&self, email: &str) -> Result<bool, Error> {
+        sqlx::query_scalar("SELECT EXISTS(SELECT 1 FROM users WHERE email = ?)")
+            .bind(email)
+            .fetch_one(&mut *self.executor.acquire().await?)
+            .await
+    }
+
+    #[instrument(
+        name = "SqliteUserRepository::create_user",
+        skip_all,
+        fields(otel.kind = "client", db.operation = "INSERT", db.sql.table = "users")
+    )]
+    async fn create_user(
+        &self,
+        firstname: &str,
+        lastname: &str,
+        email: &str,
+        password: &str,
+    ) -> Result<UserModel, Error> {
+        let now = Utc::now();
+
+        let user = sqlx::query_as::<_, UserRow>(
+            "INSERT INTO users (id, firstname, lastname, email, password, created_at, updated_at) VALUES (?, ?, ?, ?, ?, ?, ?) RETURNING *",
+        )
+        .bind(Uuid::new_v4().hyphenated())
+        .bind(firstname)
+        .bind(lastname)
+        .bind(email)
+        .bind(password)
+        .bind(now)
+        .bind(now)
+        .fetch_all(&mut *self.executor.acquire().await?)
+        .await
+        .map_err(pg_compatible)?
+        .pop()
+        .ok_or(Error::RowNotFound)?;
+
+        Ok(user.into())
+    }
+
+    #[instrument(
+        name = "SqliteUserRepository::find_by_email",
+        skip_all,
+        fields(otel.kind = "client", db.operation = "SELECT", db.sql.table = "users")
+    )]
+    async fn find_by_email(&self, email: &str) -> Result<Option<UserModel>, Error> {
+        let user = sqlx::query_as::<_, UserRow>("SELECT * FROM users WHERE email = ?")
+            .bind(email)
+            .fetch_optional(&mut *self.executor.acquire().await?)
+            .await?;
+
+        Ok(user.map(UserModel::from))
+    }
+
+    #[instrument(
+        name = "SqliteUserRepository::find_by_id",
+        skip_all,
+        fields(otel.kind = "client", db.operation = "SELECT", db.sql.table = "users")
+    )]
+    async fn find_by_id(&self, id: Uuid) -> Result<Option<UserModel>, Error> {
+        let user = sqlx::query_as::<_, UserRow>("SELECT * FROM users WHERE id = ?")
+            .bind(id.hyphenated())
+            .fetch_optional(&mut *self.executor.acquire_read().await?)
+            .await?;
+
+        Ok(user.map(UserModel::from))
+    }
+
+    #[instrument(
+        name = "SqliteUserRepository::update_user",
+        skip_all,
+        fields(otel.kind = "client", db.operation = "UPDATE", db.sql.table = "users")
+    )]
+    async fn update_user(
+        &self,
+        email: &str,
+        firstname: &str,
+        lastname: &str,
+        password: &str,
+    ) -> Result<Option<UserModel>, Error> {
+        let user = sqlx::query_as::<_, UserRow>(
+            "UPDATE users SET firstname = ?, lastname = ?, password = ? WHERE email = ? RETURNING *",
+        )
+        .bind(firstname)
+        .bind(lastname)
+        .bind(password)
+        .bind(email)
+        .fetch_all(&mut *self.executor.acquire().await?)
+        .await
+        .map_err(pg_compatible)?
+        .pop();
+
+        Ok(user.map(UserModel::from))
+    }
+
+    #[instrument(
+        name = "SqliteUserRepository::delete_user",
+        skip_all,
+        fields(otel.kind = "client", db.operation = "DELETE", db.sql.table = "users")
+    )]
+    async fn delete_user(&self, email: &str) -> Result<bool, Error> {
+        let result = sqlx::query("DELETE FROM users WHERE email = ?")
+            .bind(email)
+            .execute(&mut *self.executor.acquire().await?)
+            .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    #[instrument(
+        name = "SqliteUserRepository::update_role",
+        skip_all,
+        fields(otel.kind = "client", db.operation = "UPDATE", db.sql.table = "users")
+    )]
+    async fn update_role(&self, email: &str, role: &str) -> Result<Option<UserModel>, Error> {
+        let user =
+            sqlx::query_as::<_, UserRow>("UPDATE users SET role = ? WHERE email = ? RETURNING *")
+                .bind(role)
+                .bind(email)
+                .fetch_all(&mut *self.executor.acquire().await?)
+                .await
+                .map_err(pg_compatible)?
+                .pop();
+
+        Ok(user.map(UserModel::from))
+    }
+
+    #[instrument(
+        name = "SqliteUserRepository::set_disabled",
+        skip_all,
+        fields(otel.kind = "client", db.operation = "UPDATE", db.sql.table = "users")
+    )]
+    async fn set_disabled(&self, email: &str, disabled: bool) -> Result<Option<UserModel>, Error> {
+        let user = sqlx::query_as::<_, UserRow>(
+            "UPDATE users SET disabled_at = CASE WHEN ? THEN COALESCE(disabled_at, ?) END WHERE email = ? RETURNING *",
+        )
+        .bind(disabled)
+        .bind(Utc::now())
+        .bind(email)
+        .fetch_all(&mut *self.executor.acquire().await?)
+        .await?
+        .pop();
+
+        Ok(user.map(UserModel::from))
+    }
+
+    #[instrument(
+        name = "SqliteUserRepository::count",
+        skip_all,
+        fields(otel.kind = "client", db.operation = "SELECT", db.sql.table = "users")
+    )]
+    async fn count(&self) -> Result<i64, Error> {
+        sqlx::query_scalar("SELECT COUNT(*) FROM users")
+            .fetch_one(&mut *self.executor.acquire().await?)
+            .await
+    }
+}
+
+/// Extended result code of a write in a transaction with an outdated
+/// snapshot.
+const SQLITE_BUSY_SNAPSHOT: &str = "517";
+
+/// Reports SQLite constraint violations with the SQLSTATE and constraint
+/// name Postgres would use, so they are mapped to the same API errors.
+fn pg_compatible(err: Error) -> Error {
+    let Error::Database(db_err) = &err else {
+        return err;
+    };
+
+    let mapped = match db_err.kind() {
+        // SQLite names the column, e.g. `UNIQUE constraint failed:
+        // notes.title`; Postgres names the constraint after it.
+        ErrorKind::UniqueViolation => db_err
+            .message()
+            .strip_prefix("UNIQUE constraint failed: ")
+            .map(|column| unique_violation(&format!("{}_key", column.replace('.', "_")))),
+        // Triggers stand in for unique expression indexes and abort with
+        // the index name.
+        ErrorKind::Other if db_err.message().ends_with("_key") => {
+            Some(unique_violation(db_err.message()))
+        }
+        // The `*_length` checks stand in for `VARCHAR(n)`.
+        ErrorKind::CheckViolation if db_err.message().ends_with("_length") => {
+            Some(value_too_long(db_err.message().to_string()))
+        }
+        // A transaction can't write once another one has committed since it
+        // started reading.
+        _ if db_err.code().as_deref() == Some(SQLITE_BUSY_SNAPSHOT) => {
+            Some(serialization_failure())
+        }
+        _ => None,
+    };
+
+    mapped.unwrap_or(err)
+}
@@ -0,0 +1,80 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use sqlx::{Database, Error};
+use tokio::sync::Mutex;
+
+use crate::abstract_trait::{
+    BoxUnitOfWork, DynNoteRepository, DynUserRepository, UnitOfWorkFactoryTrait, UnitOfWorkTrait,
+};
+use crate::config::ConnectionPool;
+
+use super::executor::SharedTransaction;
+use super::{NoteRepository, SqliteNoteRepository, SqliteUserRepository, UserRepository};
+
+/// Opens a database transaction per unit of work, shared by repositories of
+/// the pool's driver. Postgres transactions are `SERIALIZABLE`, like SQLite
+/// ones always are.
+pub struct SqlUnitOfWorkFactory {
+    pool: ConnectionPool,
+}
+
+impl SqlUnitOfWorkFactory {
+    pub fn new(pool: ConnectionPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl UnitOfWorkFactoryTrait for SqlUnitOfWorkFactory {
+    async fn begin(&self) -> Result<BoxUnitOfWork, Error> {
+        let unit_of_work: BoxUnitOfWork = match &self.pool {
+            ConnectionPool::Postgres(pool) => {
+                let mut transaction = pool.begin().await?;
+                sqlx::query("SET TRANSACTION ISOLATION LEVEL SERIALIZABLE")
+                    .execute(&mut *transaction)
+                    .await?;
+                let transaction = Arc::new(Mutex::new(Some(transaction)));
+                Box::new(SqlUnitOfWork {
+                    notes: Arc::new(NoteRepository::in_transaction(transaction.clone())),
+                    users: Arc::new(UserRepository::in_transaction(transaction.clone())),
+                    transaction,
+                })
+            }
+            ConnectionPool::Sqlite(pool) => {
+                let transaction = Arc::new(Mutex::new(Some(pool.begin().await?)));
+                Box::new(SqlUnitOfWork {
+                    notes: Arc::new(SqliteNoteRepository::in_transaction(transaction.clone())),
+                    users: Arc::new(SqliteUserRepository::in_transaction(transaction.clone())),
+                    transaction,
+                })
+            }
+        };
+
+        Ok(unit_of_work)
+    }
+}
+
+struct SqlUnitOfWork<DB: Database> {
+    transaction: SharedTransaction<DB>,
+    notes: DynNoteRepository,
+    users: DynUserRepository,
+}
+
+#[async_trait]
+impl<DB: Database> UnitOfWorkTrait for SqlUnitOfWork<DB> {
+    fn notes(&self) -> &DynNoteRepository {
+        &self.notes
+    }
+
+    fn users(&self) -> &DynUserRepository {
+        &self.users
+    }
+
+    async fn commit(self: Box<Self>) -> Result<(), Error> {
+        match self.transaction.lock().await.take() {
+            Some(transaction) => transaction.commit().await,
+            None => Ok(()),
+        }
+    }
+}
@@ -1,30 +1,65 @@
+use std::sync::Arc;
+
+use crate::abstract_trait::UserRepositoryTrait;
 use crate::models::UserModel;
-use crate::{abstract_trait::UserRepositoryTrait, config::ConnectionPool};
 use async_trait::async_trait;
-use sqlx::{Error, Row};
+use sqlx::{Error, PgPool, Postgres, Row};
+use tracing::instrument;
 use uuid::Uuid;
 
+use super::executor::{Executor, SharedTransaction};
+use super::ReplicaRouter;
+
 pub struct UserRepository {
-    pub db_pool: ConnectionPool,
+    executor: Executor<Postgres>,
 }
 
 impl UserRepository {
-    pub fn new(db_pool: ConnectionPool) -> Self {
-        Self { db_pool }
+    pub fn new(db_pool: PgPool) -> Self {
+        Self {
+            executor: Executor::Pool(db_pool),
+        }
+    }
+
+    /// Sends `find_by_id` to `router`'s replicas and everything else to its
+    /// primary.
+    pub fn with_replicas(router: Arc<ReplicaRouter<Postgres>>) -> Self {
+        Self {
+            executor: Executor::Replicated(router),
+        }
+    }
+
+    /// Runs every query in `transaction`, see [`SqlUnitOfWorkFactory`].
+    ///
+    /// [`SqlUnitOfWorkFactory`]: super::SqlUnitOfWorkFactory
+    pub fn in_transaction(transaction: SharedTransaction<Postgres>) -> Self {
+        Self {
+            executor: Executor::Transaction(transaction),
+        }
     }
 }
 
 #[async_trait]
 impl UserRepositoryTrait for UserRepository {
+    #[instrument(
+        name = "UserRepository::find_by_email_exists",
+        skip_all,
+        fields(otel.kind = "client", db.operation = "SELECT", db.sql.table = "users")
+    )]
     async fn find_by_email_exists(&self, email: &str) -> Result<bool, Error> {
         let exists: bool = sqlx::query("SELECT EXISTS(SELECT 1 FROM users WHERE email = $1)")
             .bind(email)
-            .fetch_one(&self.db_pool)
+            .fetch_one(&mut *self.executor.acquire().await?)
             .await?
             .get(0);
         Ok(exists)
     }
 
+    #[instrument(
+        name = "UserRepository::create_user",
+        skip_all,
+        fields(otel.kind = "client", db.operation = "INSERT", db.sql.table = "users")
+    )]
     async fn create_user(
         &self,
         firstname: &str,
@@ -40,26 +75,41 @@ impl UserRepositoryTrait for UserRepository {
             email,
             password
         )
-        .fetch_one(&self.db_pool)
+        .fetch_one(&mut *self.executor.acquire().await?)
         .await?;
         Ok(query_result)
     }
 
+    #[instrument(
+        name = "UserRepository::find_by_email",
+        skip_all,
+        fields(otel.kind = "client", db.operation = "SELECT", db.sql.table = "users")
+    )]
     async fn find_by_email(&self, email: &str) -> Result<Option<UserModel>, Error> {
         let query_result =
             sqlx::query_as!(UserModel, "SELECT * FROM users WHERE email = $1", email)
-                .fetch_optional(&self.db_pool)
+                .fetch_optional(&mut *self.executor.acquire().await?)
                 .await?;
         Ok(query_result)
     }
 
+    #[instrument(
+        name = "UserRepository::find_by_id",
+        skip_all,
+        fields(otel.kind = "client", db.operation = "SELECT", db.sql.table = "users")
+    )]
     async fn find_by_id(&self, id: Uuid) -> Result<Option<UserModel>, Error> {
         let query_result = sqlx::query_as!(UserModel, "SELECT * FROM users WHERE id = $1", id)
-            .fetch_optional(&self.db_pool)
+            .fetch_optional(&mut *self.executor.acquire_read().await?)
             .await?;
         Ok(query_result)
     }
 
+    #[instrument(
+        name = "UserRepository::update_user",
+        skip_all,
+        fields(otel.kind = "client", db.operation = "UPDATE", db.sql.table = "users")
+    )]
     async fn update_user(
         &self,
         email: &str,
@@ -75,15 +125,65 @@ impl UserRepositoryTrait for UserRepository {
             password,
             email
         )
-        .fetch_optional(&self.db_pool)
+        .fetch_optional(&mut *self.executor.acquire().await?)
         .await?;
         Ok(query_result)
     }
 
+    #[instrument(
+        name = "UserRepository::delete_user",
+        skip_all,
+        fields(otel.kind = "client", db.operation = "DELETE", db.sql.table = "users")
+    )]
     async fn delete_user(&self, email: &str) -> Result<bool, Error> {
         let result = sqlx::query!("DELETE FROM users WHERE email = $1", email)
-            .execute(&self.db_pool)
+            .execute(&mut *self.executor.acquire().await?)
             .await?;
         Ok(result.rows_affected() > 0)
     }
+
+    #[instrument(
+        name = "UserRepository::update_role",
+        skip_all,
+        fields(otel.kind = "client", db.operation = "UPDATE", db.sql.table = "users")
+    )]
+    async fn update_role(&self, email: &str, role: &str) -> Result<Option<UserModel>, Error> {
+        let query_result = sqlx::query_as!(
+            UserModel,
+            "UPDATE users SET role = $1 WHERE email = $2 RETURNING *",
+            role,
+            email
+        )
+        .fetch_optional(&mut *self.executor.acquire().await?)
+        .await?;
+        Ok(query_result)
+    }
+
+    #[instrument(
+        name = "UserRepository::set_disabled",
+        skip_all,
+        fields(otel.kind = "client", db.operation = "UPDATE", db.sql.table = "users")
+    )]
+    async fn set_disabled(&self, email: &str, disabled: bool) -> Result<Option<UserModel>, Error> {
+        let query_result = sqlx::query_as!(
+            UserModel,
+            "UPDATE users SET disabled_at = CASE WHEN $1 THEN COALESCE(disabled_at, NOW()) END WHERE email = $2 RETURNING *",
+            disabled,
+            email
+        )
+        .fetch_optional(&mut *self.executor.acquire().await?)
+        .await?;
+        Ok(query_result)
+    }
+
+    #[instrument(
+        name = "UserRepository::count",
+        skip_all,
+        fields(otel.kind = "client", db.operation = "SELECT", db.sql.table = "users")
+    )]
+    async fn count(&self) -> Result<i64, Error> {
+        sqlx::query_scalar("SELECT COUNT(*) FROM users")
+            .fetch_one(&mut *self.executor.acquire().await?)
+            .await
+    }
 }
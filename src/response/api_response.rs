@@ -0,0 +1,135 @@
+use serde::Serialize;
+use utoipa::ToSchema;
+
+use super::{NoteResponse, UserSchema};
+use crate::config::{MigrationStatus, PoolStats};
+
+const SUCCESS: &str = "success";
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct StatusResponse {
+    #[schema(example = "success")]
+    pub status: String,
+}
+
+impl StatusResponse {
+    pub fn success() -> Self {
+        StatusResponse {
+            status: SUCCESS.to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct HealthResponse {
+    #[schema(example = "success")]
+    pub status: String,
+    pub message: String,
+    /// Absent with the in-memory repositories.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub database: Option<PoolStats>,
+}
+
+impl HealthResponse {
+    pub fn success(message: impl Into<String>, database: Option<PoolStats>) -> Self {
+        HealthResponse {
+            status: SUCCESS.to_string(),
+            message: message.into(),
+            database,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct NoteData {
+    pub note: NoteResponse,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct NoteDataResponse {
+    #[schema(example = "success")]
+    pub status: String,
+    pub data: NoteData,
+}
+
+impl NoteDataResponse {
+    pub fn success(note: NoteResponse) -> Self {
+        NoteDataResponse {
+            status: SUCCESS.to_string(),
+            data: NoteData { note },
+        }
+    }
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct NoteListResponse {
+    #[schema(example = "success")]
+    pub status: String,
+    pub results: usize,
+    pub notes: Vec<NoteResponse>,
+}
+
+impl NoteListResponse {
+    pub fn success(notes: Vec<NoteResponse>) -> Self {
+        NoteListResponse {
+            status: SUCCESS.to_string(),
+            results: notes.len(),
+            notes,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct UserData {
+    pub user: UserSchema,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct UserDataResponse {
+    #[schema(example = "success")]
+    pub status: String,
+    pub data: UserData,
+}
+
+impl UserDataResponse {
+    pub fn success(user: UserSchema) -> Self {
+        UserDataResponse {
+            status: SUCCESS.to_string(),
+            data: UserData { user },
+        }
+    }
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct TokenResponse {
+    #[schema(example = "success")]
+    pub status: String,
+    pub token: String,
+}
+
+impl TokenResponse {
+    pub fn success(token: String) -> Self {
+        TokenResponse {
+            status: SUCCESS.to_string(),
+            token,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct MigrationListResponse {
+    #[schema(example = "success")]
+    pub status: String,
+    pub results: usize,
+    pub migrations: Vec<MigrationStatus>,
+}
+
+impl MigrationListResponse {
+    pub fn success(migrations: Vec<MigrationStatus>) -> Self {
+        MigrationListResponse {
+            status: SUCCESS.to_string(),
+            results: migrations.len(),
+            migrations,
+        }
+    }
+}
@@ -1,11 +1,19 @@
 use core::fmt;
 
 use serde::Serialize;
+use utoipa::ToSchema;
 
-#[derive(Debug, Serialize)]
+use crate::error::FieldErrors;
+
+#[derive(Debug, Serialize, ToSchema)]
 pub struct ErrorResponse {
     pub status: String,
     pub message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(value_type = Option<Object>, example = json!({"title": ["Title must be 1 to 255 characters"]}))]
+    pub errors: Option<FieldErrors>,
+    #[serde(rename = "requestId", skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<String>,
 }
 
 impl fmt::Display for ErrorResponse {
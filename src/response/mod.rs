@@ -1,7 +1,16 @@
+mod api_response;
 mod error_response;
+mod health;
 mod note;
+mod problem_details;
 mod user;
 
+pub use api_response::{
+    HealthResponse, MigrationListResponse, NoteData, NoteDataResponse, NoteListResponse,
+    StatusResponse, TokenResponse, UserData, UserDataResponse,
+};
 pub use error_response::ErrorResponse;
+pub use health::{CheckResult, HealthReport, HealthStatus};
 pub use note::NoteResponse;
-pub use user::{UserData, UserSchema};
+pub use problem_details::{ProblemDetails, PROBLEM_JSON};
+pub use user::UserSchema;
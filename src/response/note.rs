@@ -1,10 +1,11 @@
 use chrono::DateTime;
 use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
 use uuid::Uuid;
 
 use crate::models::NoteModel;
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
 #[allow(non_snake_case)]
 pub struct NoteResponse {
     pub id: Uuid,
@@ -0,0 +1,64 @@
+use actix_web::http::StatusCode;
+use serde::Serialize;
+use utoipa::ToSchema;
+
+use crate::error::FieldErrors;
+
+use super::ErrorResponse;
+
+pub const PROBLEM_JSON: &str = "application/problem+json";
+
+/// Error body as described by RFC 7807.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ProblemDetails {
+    #[serde(rename = "type")]
+    pub problem_type: String,
+    pub title: String,
+    pub status: u16,
+    pub detail: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub instance: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(value_type = Option<Object>, example = json!({"title": ["Title must be 1 to 255 characters"]}))]
+    pub errors: Option<FieldErrors>,
+}
+
+impl ProblemDetails {
+    pub fn new(status: StatusCode, detail: impl Into<String>) -> Self {
+        ProblemDetails {
+            problem_type: "about:blank".to_string(),
+            title: status.canonical_reason().unwrap_or("Error").to_string(),
+            status: status.as_u16(),
+            detail: detail.into(),
+            instance: None,
+            request_id: None,
+            errors: None,
+        }
+    }
+
+    pub fn with_type(mut self, slug: &str) -> Self {
+        self.problem_type = format!("/problems/{}", slug);
+        self
+    }
+
+    pub fn with_errors(mut self, errors: FieldErrors) -> Self {
+        self.errors = Some(errors);
+        self
+    }
+
+    /// Converts to the `{"status": ..., "message": ...}` envelope used before
+    /// problem details were introduced.
+    pub fn into_legacy(self) -> ErrorResponse {
+        let status = if self.status >= 500 { "error" } else { "fail" };
+
+        ErrorResponse {
+            status: status.to_string(),
+            message: self.detail,
+            errors: self.errors,
+            request_id: self.request_id,
+        }
+    }
+}
@@ -1,10 +1,11 @@
 use chrono::prelude::*;
 use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
 use uuid::Uuid;
 
 use crate::models::UserModel;
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
 pub struct UserSchema {
     pub id: Uuid,
     pub firstname: String,
@@ -28,8 +29,3 @@ impl From<UserModel> for UserSchema {
         }
     }
 }
-
-#[derive(Serialize, Debug)]
-pub struct UserData {
-    pub user: UserSchema,
-}
@@ -1,4 +1,8 @@
 use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use validator::Validate;
+
+use super::normalize;
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct TokenClaims {
@@ -7,16 +11,36 @@ pub struct TokenClaims {
     pub exp: usize,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Validate, ToSchema)]
 pub struct RegisterUserSchema {
+    #[serde(deserialize_with = "normalize::trimmed")]
+    #[validate(length(min = 1, max = 100, message = "First name must be 1 to 100 characters"))]
     pub firstname: String,
+    #[serde(deserialize_with = "normalize::trimmed")]
+    #[validate(length(min = 1, max = 100, message = "Last name must be 1 to 100 characters"))]
     pub lastname: String,
+    #[serde(deserialize_with = "normalize::email")]
+    #[validate(
+        email(message = "Email must be a valid email address"),
+        length(max = 255, message = "Email must be at most 255 characters")
+    )]
     pub email: String,
     pub password: String,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Validate, ToSchema)]
 pub struct LoginUserSchema {
+    #[serde(deserialize_with = "normalize::email")]
+    #[validate(email(message = "Email must be a valid email address"))]
     pub email: String,
+    #[validate(length(min = 1, message = "Password is required"))]
     pub password: String,
 }
+
+#[derive(Debug, Deserialize, Validate, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ChangePasswordSchema {
+    #[validate(length(min = 1, message = "Current password is required"))]
+    pub current_password: String,
+    pub new_password: String,
+}
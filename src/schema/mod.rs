@@ -1,6 +1,6 @@
 mod auth_schema;
+mod normalize;
 mod note_schema;
 
-pub use auth_schema::{LoginUserSchema, RegisterUserSchema, TokenClaims};
-pub use note_schema::{CreateNoteSchema, UpdateNoteSchema};
-
+pub use auth_schema::{ChangePasswordSchema, LoginUserSchema, RegisterUserSchema, TokenClaims};
+pub use note_schema::{CreateNoteSchema, FilterOptions, ParamOptions, UpdateNoteSchema};
@@ -0,0 +1,18 @@
+use serde::{Deserialize, Deserializer};
+
+/// Deserializes a string with surrounding whitespace removed.
+pub fn trimmed<'de, D>(deserializer: D) -> Result<String, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    Ok(String::deserialize(deserializer)?.trim().to_string())
+}
+
+/// Deserializes an email address trimmed and lowercased, so lookups and the
+/// unique constraint are not case sensitive.
+pub fn email<'de, D>(deserializer: D) -> Result<String, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    Ok(String::deserialize(deserializer)?.trim().to_lowercase())
+}
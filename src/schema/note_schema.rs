@@ -1,4 +1,8 @@
 use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use validator::Validate;
+
+use super::normalize;
 
 #[derive(Deserialize, Debug)]
 pub struct FilterOptions {
@@ -11,14 +15,20 @@ pub struct ParamOptions {
     pub id: String,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Validate, ToSchema)]
 pub struct CreateNoteSchema {
+    #[serde(deserialize_with = "normalize::trimmed")]
+    #[validate(length(min = 1, max = 255, message = "Title must be 1 to 255 characters"))]
     pub title: String,
+    #[validate(length(min = 1, message = "Content must not be empty"))]
     pub content: String,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Validate, ToSchema)]
 pub struct UpdateNoteSchema {
+    #[serde(deserialize_with = "normalize::trimmed")]
+    #[validate(length(min = 1, max = 255, message = "Title must be 1 to 255 characters"))]
     pub title: String,
+    #[validate(length(min = 1, message = "Content must not be empty"))]
     pub content: String,
 }
@@ -0,0 +1,93 @@
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+
+/// Space-efficient probabilistic set. Lookups may return false positives
+/// (bounded by the rate chosen at construction) but never false negatives.
+pub struct BloomFilter {
+    bits: Vec<u64>,
+    num_bits: u64,
+    num_hashes: u32,
+}
+
+impl BloomFilter {
+    pub fn with_capacity(expected_items: usize, false_positive_rate: f64) -> Self {
+        let n = expected_items.max(1) as f64;
+        let ln2 = std::f64::consts::LN_2;
+
+        let num_bits = (-(n * false_positive_rate.ln()) / (ln2 * ln2))
+            .ceil()
+            .max(64.0) as u64;
+        let num_hashes = ((num_bits as f64 / n) * ln2).round().max(1.0) as u32;
+
+        Self {
+            bits: vec![0; num_bits.div_ceil(64) as usize],
+            num_bits,
+            num_hashes,
+        }
+    }
+
+    pub fn insert(&mut self, item: &str) {
+        let indexes: Vec<u64> = self.indexes(item).collect();
+        for index in indexes {
+            self.bits[(index / 64) as usize] |= 1 << (index % 64);
+        }
+    }
+
+    pub fn contains(&self, item: &str) -> bool {
+        self.indexes(item)
+            .all(|index| self.bits[(index / 64) as usize] & (1 << (index % 64)) != 0)
+    }
+
+    // Double hashing (Kirsch-Mitzenmacher): derive k indexes from two base hashes.
+    fn indexes(&self, item: &str) -> impl Iterator<Item = u64> + '_ {
+        let h1 = hash_with_seed(item, 0x51_7c_c1_b7);
+        let h2 = hash_with_seed(item, 0x27_22_0a_95) | 1;
+
+        (0..self.num_hashes as u64)
+            .map(move |i| h1.wrapping_add(i.wrapping_mul(h2)) % self.num_bits)
+    }
+}
+
+fn hash_with_seed(item: &str, seed: u64) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    seed.hash(&mut hasher);
+    item.hash(&mut hasher);
+    hasher.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn never_reports_false_negatives() {
+        let items: Vec<String> = (0..1_000).map(|i| format!("password{}", i)).collect();
+        let mut filter = BloomFilter::with_capacity(items.len(), 0.01);
+        for item in &items {
+            filter.insert(item);
+        }
+
+        assert!(items.iter().all(|item| filter.contains(item)));
+    }
+
+    #[test]
+    fn false_positive_rate_stays_near_the_target() {
+        let mut filter = BloomFilter::with_capacity(1_000, 0.01);
+        for i in 0..1_000 {
+            filter.insert(&format!("breached{}", i));
+        }
+
+        let false_positives = (0..10_000)
+            .filter(|i| filter.contains(&format!("unseen{}", i)))
+            .count();
+        assert!(false_positives < 300, "{} false positives", false_positives);
+    }
+
+    #[test]
+    fn empty_filter_contains_nothing() {
+        let filter = BloomFilter::with_capacity(0, 0.001);
+
+        assert!(!filter.contains(""));
+        assert!(!filter.contains("password"));
+    }
+}
@@ -0,0 +1,9 @@
+mod bloom_filter;
+mod password_hash;
+mod password_policy;
+mod tls;
+
+pub use bloom_filter::BloomFilter;
+pub use password_hash::{hash_password, verify_password};
+pub use password_policy::PasswordPolicy;
+pub use tls::CertificateResolver;
@@ -0,0 +1,235 @@
+use std::fs::File;
+use std::io::{self, BufRead, BufReader};
+
+use crate::config::PasswordPolicyConfig;
+
+use super::BloomFilter;
+
+const BREACHED_FALSE_POSITIVE_RATE: f64 = 0.001;
+const MIN_PERSONAL_INFO_LENGTH: usize = 3;
+
+pub struct PasswordPolicy {
+    config: PasswordPolicyConfig,
+    breached: Option<BloomFilter>,
+}
+
+impl PasswordPolicy {
+    pub fn new(config: PasswordPolicyConfig) -> io::Result<Self> {
+        let breached = match &config.breached_passwords_path {
+            Some(path) => Some(load_breached_passwords(path)?),
+            None => None,
+        };
+
+        Ok(Self { config, breached })
+    }
+
+    /// Checks `password` against the configured rules. `personal_info` holds
+    /// values (email, names) that must not appear inside the password.
+    /// Returns every violated rule so clients can show them all at once.
+    pub fn validate(&self, password: &str, personal_info: &[&str]) -> Result<(), Vec<String>> {
+        let mut errors = Vec::new();
+        let length = password.chars().count();
+
+        if length < self.config.min_length {
+            errors.push(format!(
+                "Password must be at least {} characters long",
+                self.config.min_length
+            ));
+        }
+        if length > self.config.max_length {
+            errors.push(format!(
+                "Password must be at most {} characters long",
+                self.config.max_length
+            ));
+        }
+        if self.config.require_lowercase && !password.chars().any(char::is_lowercase) {
+            errors.push("Password must contain a lowercase letter".to_string());
+        }
+        if self.config.require_uppercase && !password.chars().any(char::is_uppercase) {
+            errors.push("Password must contain an uppercase letter".to_string());
+        }
+        if self.config.require_digit && !password.chars().any(|c| c.is_ascii_digit()) {
+            errors.push("Password must contain a digit".to_string());
+        }
+        if self.config.require_symbol && password.chars().all(char::is_alphanumeric) {
+            errors.push("Password must contain a symbol".to_string());
+        }
+        if self.config.disallow_personal_info && contains_personal_info(password, personal_info) {
+            errors.push("Password must not contain your name or email".to_string());
+        }
+        if let Some(breached) = &self.breached {
+            if breached.contains(&password.to_lowercase()) {
+                errors.push(
+                    "Password has appeared in a data breach, please choose another".to_string(),
+                );
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+}
+
+fn contains_personal_info(password: &str, personal_info: &[&str]) -> bool {
+    let password = password.to_lowercase();
+
+    personal_info
+        .iter()
+        .flat_map(|value| {
+            // Check the local part of an email on its own too, since that is
+            // what people tend to reuse.
+            let value = value.trim().to_lowercase();
+            let local_part = value.split('@').next().map(str::to_string);
+            std::iter::once(value).chain(local_part)
+        })
+        .filter(|value| value.chars().count() >= MIN_PERSONAL_INFO_LENGTH)
+        .any(|value| password.contains(&value))
+}
+
+fn load_breached_passwords(path: &str) -> io::Result<BloomFilter> {
+    let lines = BufReader::new(File::open(path)?)
+        .lines()
+        .collect::<io::Result<Vec<String>>>()?;
+
+    let mut filter = BloomFilter::with_capacity(lines.len(), BREACHED_FALSE_POSITIVE_RATE);
+    for line in lines
+        .iter()
+        .map(|line| line.trim())
+        .filter(|line| !line.is_empty())
+    {
+        filter.insert(&line.to_lowercase());
+    }
+
+    log::info!("Loaded {} breached passwords from {}", lines.len(), path);
+
+    Ok(filter)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy(configure: impl FnOnce(&mut PasswordPolicyConfig)) -> PasswordPolicy {
+        let mut config = PasswordPolicyConfig::default();
+        configure(&mut config);
+        PasswordPolicy::new(config).unwrap()
+    }
+
+    #[test]
+    fn accepts_a_password_meeting_every_rule() {
+        let policy = policy(|config| config.require_symbol = true);
+
+        assert_eq!(policy.validate("Correct-Horse-42", &[]), Ok(()));
+    }
+
+    #[test]
+    fn enforces_length_bounds_in_characters() {
+        let policy = policy(|config| {
+            config.min_length = 10;
+            config.max_length = 12;
+        });
+
+        assert_eq!(
+            policy.validate("Short1a", &[]),
+            Err(vec![
+                "Password must be at least 10 characters long".to_string()
+            ])
+        );
+        assert_eq!(
+            policy.validate("WayTooLong12345", &[]),
+            Err(vec![
+                "Password must be at most 12 characters long".to_string()
+            ])
+        );
+        // Multi-byte characters count once each.
+        assert_eq!(policy.validate("Äpfelbaum1é", &[]), Ok(()));
+    }
+
+    #[test]
+    fn reports_every_missing_character_class() {
+        let policy = policy(|config| config.require_symbol = true);
+
+        assert_eq!(
+            policy.validate("        ", &[]),
+            Err(vec![
+                "Password must contain a lowercase letter".to_string(),
+                "Password must contain an uppercase letter".to_string(),
+                "Password must contain a digit".to_string(),
+            ])
+        );
+        assert_eq!(
+            policy.validate("Password123", &[]),
+            Err(vec!["Password must contain a symbol".to_string()])
+        );
+    }
+
+    #[test]
+    fn disabled_rules_are_skipped() {
+        let policy = policy(|config| {
+            config.require_lowercase = false;
+            config.require_uppercase = false;
+            config.require_digit = false;
+            config.disallow_personal_info = false;
+        });
+
+        assert_eq!(
+            policy.validate("jane.doe", &["jane.doe@example.com"]),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn rejects_names_and_email_local_parts() {
+        let policy = policy(|_| {});
+        let personal_info = ["Jane", "Doe", "JDOE@example.com"];
+        let error = Err(vec![
+            "Password must not contain your name or email".to_string()
+        ]);
+
+        assert_eq!(policy.validate("Xx9janeXx", &personal_info), error);
+        assert_eq!(policy.validate("Secret1JDoe", &personal_info), error);
+        // Values shorter than three characters are too common to reject.
+        assert_eq!(policy.validate("Alpha123Jo", &["Jo"]), Ok(()));
+    }
+
+    #[test]
+    fn rejects_breached_passwords_regardless_of_case() {
+        let path = std::env::temp_dir().join(format!(
+            "crudsqlx_breached_{}.txt",
+            uuid::Uuid::new_v4().simple()
+        ));
+        std::fs::write(&path, "password1\n\n  Summer2024  \n").unwrap();
+        let policy = policy(|config| {
+            config.breached_passwords_path = Some(path.to_str().unwrap().to_string())
+        });
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(
+            policy.validate("SUMMER2024", &[]),
+            Err(vec![
+                "Password must contain a lowercase letter".to_string(),
+                "Password has appeared in a data breach, please choose another".to_string(),
+            ])
+        );
+        assert_eq!(
+            policy.validate("Password1", &[]),
+            Err(vec![
+                "Password has appeared in a data breach, please choose another".to_string()
+            ])
+        );
+        assert_eq!(policy.validate("Winter2024", &[]), Ok(()));
+    }
+
+    #[test]
+    fn missing_breached_password_file_is_an_error() {
+        let config = PasswordPolicyConfig {
+            breached_passwords_path: Some("/nonexistent/breached.txt".to_string()),
+            ..PasswordPolicyConfig::default()
+        };
+
+        assert!(PasswordPolicy::new(config).is_err());
+    }
+}
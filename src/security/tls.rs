@@ -0,0 +1,117 @@
+use std::fs::File;
+use std::io::{self, BufReader};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::SystemTime;
+
+use rustls::server::{ClientHello, ResolvesServerCert};
+use rustls::sign::{self, CertifiedKey};
+use rustls::{Certificate, PrivateKey, ServerConfig};
+use rustls_pemfile::Item;
+
+use crate::config::TlsConfig;
+
+/// Serves the certificate and key from the configured PEM files and swaps
+/// them in place when the files change, so renewed certificates are picked
+/// up by new connections without a restart.
+pub struct CertificateResolver {
+    cert_path: PathBuf,
+    key_path: PathBuf,
+    current: RwLock<Arc<CertifiedKey>>,
+    modified: Mutex<(Option<SystemTime>, Option<SystemTime>)>,
+}
+
+impl CertificateResolver {
+    pub fn new(config: &TlsConfig) -> io::Result<Self> {
+        let cert_path = PathBuf::from(&config.cert_path);
+        let key_path = PathBuf::from(&config.key_path);
+        let modified = (modified_at(&cert_path), modified_at(&key_path));
+        let certified_key = load_certified_key(&cert_path, &key_path)?;
+
+        Ok(Self {
+            cert_path,
+            key_path,
+            current: RwLock::new(Arc::new(certified_key)),
+            modified: Mutex::new(modified),
+        })
+    }
+
+    /// rustls settings for the server, with HTTP/2 negotiated by actix.
+    pub fn server_config(self: &Arc<Self>) -> ServerConfig {
+        ServerConfig::builder()
+            .with_safe_defaults()
+            .with_no_client_auth()
+            .with_cert_resolver(self.clone())
+    }
+
+    /// Reloads the certificate and key if either file changed since the last
+    /// successful load. Returns whether a new certificate is now served. On
+    /// error the previous certificate stays in use and the next call tries
+    /// again, which covers the files being replaced one after the other.
+    pub fn reload_if_changed(&self) -> io::Result<bool> {
+        let modified = (modified_at(&self.cert_path), modified_at(&self.key_path));
+        let mut last_modified = self.modified.lock().unwrap();
+        if *last_modified == modified {
+            return Ok(false);
+        }
+
+        let certified_key = load_certified_key(&self.cert_path, &self.key_path)?;
+        *self.current.write().unwrap() = Arc::new(certified_key);
+        *last_modified = modified;
+
+        Ok(true)
+    }
+}
+
+impl ResolvesServerCert for CertificateResolver {
+    fn resolve(&self, _client_hello: ClientHello) -> Option<Arc<CertifiedKey>> {
+        Some(self.current.read().unwrap().clone())
+    }
+}
+
+fn modified_at(path: &Path) -> Option<SystemTime> {
+    path.metadata()
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}
+
+fn load_certified_key(cert_path: &Path, key_path: &Path) -> io::Result<CertifiedKey> {
+    let certs: Vec<Certificate> = read_pem(cert_path)?
+        .into_iter()
+        .filter_map(|item| match item {
+            Item::X509Certificate(der) => Some(Certificate(der)),
+            _ => None,
+        })
+        .collect();
+    if certs.is_empty() {
+        return Err(invalid_data(cert_path, "no certificate found"));
+    }
+
+    let key = read_pem(key_path)?
+        .into_iter()
+        .find_map(|item| match item {
+            Item::PKCS8Key(der) | Item::RSAKey(der) | Item::ECKey(der) => Some(PrivateKey(der)),
+            _ => None,
+        })
+        .ok_or_else(|| invalid_data(key_path, "no private key found"))?;
+    let signing_key =
+        sign::any_supported_type(&key).map_err(|err| invalid_data(key_path, &err.to_string()))?;
+
+    Ok(CertifiedKey::new(certs, signing_key))
+}
+
+fn read_pem(path: &Path) -> io::Result<Vec<Item>> {
+    let file = File::open(path).map_err(|err| with_path(path, err))?;
+    rustls_pemfile::read_all(&mut BufReader::new(file)).map_err(|err| with_path(path, err))
+}
+
+fn with_path(path: &Path, err: io::Error) -> io::Error {
+    io::Error::new(err.kind(), format!("{}: {}", path.display(), err))
+}
+
+fn invalid_data(path: &Path, message: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("{}: {}", path.display(), message),
+    )
+}
@@ -0,0 +1,70 @@
+use std::collections::HashSet;
+
+use async_trait::async_trait;
+
+use crate::{abstract_trait::HealthCheckTrait, config::ConnectionPool};
+
+/// Round-trips a query through the pool.
+pub struct DatabaseCheck {
+    pool: ConnectionPool,
+}
+
+impl DatabaseCheck {
+    pub fn new(pool: ConnectionPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl HealthCheckTrait for DatabaseCheck {
+    fn name(&self) -> &str {
+        "database"
+    }
+
+    async fn check(&self) -> Result<(), String> {
+        self.pool.ping().await.map_err(|err| err.to_string())
+    }
+}
+
+/// Fails while migrations embedded in the binary have not been applied.
+pub struct MigrationsCheck {
+    pool: ConnectionPool,
+}
+
+impl MigrationsCheck {
+    pub fn new(pool: ConnectionPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl HealthCheckTrait for MigrationsCheck {
+    fn name(&self) -> &str {
+        "migrations"
+    }
+
+    async fn check(&self) -> Result<(), String> {
+        let applied: HashSet<i64> = self
+            .pool
+            .applied_migrations()
+            .await
+            .map_err(|err| err.to_string())?
+            .into_iter()
+            .collect();
+
+        let pending: Vec<String> = self
+            .pool
+            .migrator()
+            .iter()
+            .filter(|migration| !migration.migration_type.is_down_migration())
+            .filter(|migration| !applied.contains(&migration.version))
+            .map(|migration| migration.version.to_string())
+            .collect();
+
+        if pending.is_empty() {
+            Ok(())
+        } else {
+            Err(format!("pending migrations: {}", pending.join(", ")))
+        }
+    }
+}
@@ -0,0 +1,66 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::RwLock;
+use std::time::{Duration, Instant};
+
+use actix_web::rt::time::timeout;
+use futures_util::future::join_all;
+
+use crate::{
+    abstract_trait::DynHealthCheck,
+    response::{CheckResult, HealthReport, HealthStatus},
+};
+
+/// A check that takes longer than this is reported as down.
+const CHECK_TIMEOUT: Duration = Duration::from_secs(2);
+
+#[derive(Default)]
+pub struct HealthService {
+    checks: RwLock<Vec<DynHealthCheck>>,
+    shutting_down: AtomicBool,
+}
+
+impl HealthService {
+    pub fn register(&self, check: DynHealthCheck) {
+        self.checks.write().unwrap().push(check);
+    }
+
+    /// Fails readiness from now on so no new traffic is routed here.
+    pub fn begin_shutdown(&self) {
+        self.shutting_down.store(true, Ordering::Relaxed);
+    }
+
+    /// Runs every registered check concurrently.
+    pub async fn readiness(&self) -> HealthReport {
+        if self.shutting_down.load(Ordering::Relaxed) {
+            return HealthReport::from_checks(vec![CheckResult {
+                name: "shutdown".to_string(),
+                status: HealthStatus::Down,
+                duration_ms: 0,
+                error: Some("server is shutting down".to_string()),
+            }]);
+        }
+
+        let checks = self.checks.read().unwrap().clone();
+        let results = join_all(checks.iter().map(run_check)).await;
+
+        HealthReport::from_checks(results)
+    }
+}
+
+async fn run_check(check: &DynHealthCheck) -> CheckResult {
+    let started = Instant::now();
+    let result = match timeout(CHECK_TIMEOUT, check.check()).await {
+        Ok(result) => result,
+        Err(_) => Err(format!("timed out after {:?}", CHECK_TIMEOUT)),
+    };
+
+    CheckResult {
+        name: check.name().to_string(),
+        status: match result {
+            Ok(()) => HealthStatus::Up,
+            Err(_) => HealthStatus::Down,
+        },
+        duration_ms: started.elapsed().as_millis() as u64,
+        error: result.err(),
+    }
+}
@@ -1,5 +1,17 @@
+mod health_checks;
+mod health_service;
 mod note_service;
+mod rate_limiter;
+mod read_your_writes;
+mod stats_service;
+mod task_supervisor;
 mod user_service;
 
+pub use health_checks::{DatabaseCheck, MigrationsCheck};
+pub use health_service::HealthService;
 pub use note_service::NoteService;
+pub use rate_limiter::{RateLimitDecision, RateLimiter};
+pub use read_your_writes::ReadYourWrites;
+pub use stats_service::{StatsService, Totals};
+pub use task_supervisor::{ShutdownSignal, TaskSupervisor};
 pub use user_service::UserService;
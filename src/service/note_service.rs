@@ -1,11 +1,11 @@
 use async_trait::async_trait;
-use std::sync::Arc;
+use tracing::instrument;
 
-use sqlx::Error;
 use uuid::Uuid;
 
 use crate::{
     abstract_trait::{DynNoteRepository, NoteServiceTrait},
+    error::AppError,
     response::NoteResponse,
 };
 
@@ -19,41 +19,51 @@ impl NoteService {
         Self { repository }
     }
 }
+
+fn note_not_found(id: Uuid) -> AppError {
+    AppError::NotFound(format!("Note with ID: {} not found", id))
+}
+
 #[async_trait]
 impl NoteServiceTrait for NoteService {
-    async fn get_notes(&self) -> anyhow::Result<Vec<NoteResponse>> {
+    #[instrument(name = "NoteService::get_notes", skip_all)]
+    async fn get_notes(&self) -> Result<Vec<NoteResponse>, AppError> {
         let notes = self.repository.get_notes().await?;
         let note_responses: Vec<NoteResponse> = notes.into_iter().map(|note| note.into()).collect();
         Ok(note_responses)
     }
 
-    async fn get_note_id(&self, id: Uuid) -> anyhow::Result<Option<NoteResponse>> {
+    #[instrument(name = "NoteService::get_note_id", skip_all, fields(note.id = %id))]
+    async fn get_note_id(&self, id: Uuid) -> Result<NoteResponse, AppError> {
         let note = self.repository.get_note_id(id).await?;
         match note {
-            Some(note) => Ok(Some(note.into())),
-            None => Ok(None),
+            Some(note) => Ok(note.into()),
+            None => Err(note_not_found(id)),
         }
     }
 
-    async fn create_note(&self, title: &str, content: &str) -> anyhow::Result<NoteResponse> {
+    #[instrument(name = "NoteService::create_note", skip_all)]
+    async fn create_note(&self, title: &str, content: &str) -> Result<NoteResponse, AppError> {
         let note = self.repository.create_note(title, content).await?;
         Ok(note.into())
     }
 
+    #[instrument(name = "NoteService::update_note", skip_all, fields(note.id = %id))]
     async fn update_note(
         &self,
         id: Uuid,
         title: &str,
         content: &str,
-    ) -> anyhow::Result<Option<NoteResponse>> {
+    ) -> Result<NoteResponse, AppError> {
         let note = self.repository.update_note(id, title, content).await?;
         match note {
-            Some(note) => Ok(Some(note.into())),
-            None => Ok(None),
+            Some(note) => Ok(note.into()),
+            None => Err(note_not_found(id)),
         }
     }
 
-    async fn delete_note(&self, id: Uuid) -> anyhow::Result<()> {
+    #[instrument(name = "NoteService::delete_note", skip_all, fields(note.id = %id))]
+    async fn delete_note(&self, id: Uuid) -> Result<(), AppError> {
         self.repository.delete(id).await?;
         Ok(())
     }
@@ -0,0 +1,179 @@
+use actix_web::http::Method;
+
+use crate::{
+    abstract_trait::DynRateLimitStore,
+    config::{RateLimitConfig, RateLimitPolicyConfig},
+    error::AppError,
+};
+
+/// Result of counting a request against a policy, with the values reported
+/// in the `RateLimit-*` headers.
+#[derive(Debug, Clone)]
+pub struct RateLimitDecision {
+    pub allowed: bool,
+    pub limit: u32,
+    pub window_secs: u64,
+    pub remaining: u32,
+    /// Seconds until the bucket is full again.
+    pub reset_secs: u64,
+    /// Seconds until a request would be allowed again; 0 when allowed.
+    pub retry_after_secs: u64,
+}
+
+pub struct RateLimiter {
+    policies: Vec<RateLimitPolicyConfig>,
+    store: DynRateLimitStore,
+}
+
+impl RateLimiter {
+    pub fn new(config: &RateLimitConfig, store: DynRateLimitStore) -> Self {
+        let policies = if config.enabled {
+            config.policies.clone()
+        } else {
+            Vec::new()
+        };
+
+        Self { policies, store }
+    }
+
+    /// The first policy covering `method` and `path`, if any.
+    pub fn policy_for(&self, method: &Method, path: &str) -> Option<&RateLimitPolicyConfig> {
+        self.policies.iter().find(|policy| {
+            let prefix = policy.path_prefix.trim_end_matches('/');
+            let path_matches = path
+                .strip_prefix(prefix)
+                .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'));
+            let method_matches = policy.methods.is_empty()
+                || policy
+                    .methods
+                    .iter()
+                    .any(|allowed| allowed.eq_ignore_ascii_case(method.as_str()));
+
+            path_matches && method_matches
+        })
+    }
+
+    /// Counts one request by `subject` against `policy`.
+    pub async fn acquire(
+        &self,
+        policy: &RateLimitPolicyConfig,
+        subject: &str,
+    ) -> Result<RateLimitDecision, AppError> {
+        let capacity = f64::from(policy.requests);
+        let refill_per_sec = capacity / policy.per_secs as f64;
+        let key = format!("{}:{}", policy.name, subject);
+
+        let bucket = self.store.acquire(&key, capacity, refill_per_sec).await?;
+        let seconds_until = |tokens: f64| ((tokens - bucket.tokens) / refill_per_sec).ceil() as u64;
+
+        Ok(RateLimitDecision {
+            allowed: bucket.allowed,
+            limit: policy.requests,
+            window_secs: policy.per_secs,
+            remaining: bucket.tokens.floor() as u32,
+            reset_secs: seconds_until(capacity),
+            retry_after_secs: if bucket.allowed {
+                0
+            } else {
+                seconds_until(1.0)
+            },
+        })
+    }
+
+    pub async fn purge_expired(&self) -> Result<u64, AppError> {
+        Ok(self.store.purge_expired().await?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::config::RateLimitKey;
+    use crate::repository::InMemoryRateLimitStore;
+
+    fn policy(name: &str, path_prefix: &str, methods: &[&str]) -> RateLimitPolicyConfig {
+        RateLimitPolicyConfig {
+            name: name.to_string(),
+            path_prefix: path_prefix.to_string(),
+            methods: methods.iter().map(|method| method.to_string()).collect(),
+            key: RateLimitKey::Ip,
+            requests: 2,
+            per_secs: 60,
+        }
+    }
+
+    fn limiter(enabled: bool, policies: Vec<RateLimitPolicyConfig>) -> RateLimiter {
+        let config = RateLimitConfig {
+            enabled,
+            policies,
+            ..RateLimitConfig::default()
+        };
+        RateLimiter::new(&config, Arc::new(InMemoryRateLimitStore::default()))
+    }
+
+    fn matching(limiter: &RateLimiter, method: Method, path: &str) -> Option<String> {
+        limiter
+            .policy_for(&method, path)
+            .map(|policy| policy.name.clone())
+    }
+
+    #[test]
+    fn policies_match_whole_path_segments_in_order() {
+        let limiter = limiter(
+            true,
+            vec![
+                policy("notes-read", "/api/notes/", &["get"]),
+                policy("api", "/api", &[]),
+            ],
+        );
+
+        assert_eq!(
+            matching(&limiter, Method::GET, "/api/notes").as_deref(),
+            Some("notes-read")
+        );
+        assert_eq!(
+            matching(&limiter, Method::GET, "/api/notes/1").as_deref(),
+            Some("notes-read")
+        );
+        assert_eq!(
+            matching(&limiter, Method::POST, "/api/notes").as_deref(),
+            Some("api")
+        );
+        assert_eq!(
+            matching(&limiter, Method::GET, "/api/notesx").as_deref(),
+            Some("api")
+        );
+        assert_eq!(matching(&limiter, Method::GET, "/apix"), None);
+        assert_eq!(matching(&limiter, Method::GET, "/health/live"), None);
+    }
+
+    #[test]
+    fn a_disabled_limiter_matches_nothing() {
+        let limiter = limiter(false, vec![policy("api", "/api", &[])]);
+
+        assert_eq!(matching(&limiter, Method::GET, "/api/notes"), None);
+    }
+
+    #[tokio::test]
+    async fn decisions_report_remaining_tokens_and_when_to_retry() {
+        let policy = policy("api", "/api", &[]);
+        let limiter = limiter(true, vec![policy.clone()]);
+
+        let decision = limiter.acquire(&policy, "ip:1").await.unwrap();
+        assert!(decision.allowed);
+        assert_eq!((decision.limit, decision.window_secs), (2, 60));
+        assert_eq!((decision.remaining, decision.reset_secs), (1, 30));
+        assert_eq!(decision.retry_after_secs, 0);
+
+        limiter.acquire(&policy, "ip:1").await.unwrap();
+        let decision = limiter.acquire(&policy, "ip:1").await.unwrap();
+        assert!(!decision.allowed);
+        assert_eq!((decision.remaining, decision.reset_secs), (0, 60));
+        assert_eq!(decision.retry_after_secs, 30);
+
+        let other = limiter.acquire(&policy, "ip:2").await.unwrap();
+        assert!(other.allowed);
+    }
+}
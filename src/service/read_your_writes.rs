@@ -0,0 +1,75 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Remembers who wrote recently, so their reads can be kept on the primary
+/// until the replicas have caught up. Kept per process: behind a load
+/// balancer without sticky sessions, a write on one instance does not pin
+/// reads on another.
+pub struct ReadYourWrites {
+    window: Duration,
+    last_writes: Mutex<HashMap<String, Instant>>,
+}
+
+impl ReadYourWrites {
+    pub fn new(window: Duration) -> Self {
+        Self {
+            window,
+            last_writes: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn record_write(&self, subject: &str) {
+        if self.window.is_zero() {
+            return;
+        }
+        self.last_writes
+            .lock()
+            .unwrap()
+            .insert(subject.to_string(), Instant::now());
+    }
+
+    /// Whether `subject` wrote within the window.
+    pub fn is_pinned(&self, subject: &str) -> bool {
+        self.last_writes
+            .lock()
+            .unwrap()
+            .get(subject)
+            .is_some_and(|written| written.elapsed() < self.window)
+    }
+
+    /// Forgets writes older than the window, returning how many.
+    pub fn purge_expired(&self) -> usize {
+        let mut last_writes = self.last_writes.lock().unwrap();
+        let before = last_writes.len();
+        last_writes.retain(|_, written| written.elapsed() < self.window);
+        before - last_writes.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pins_only_recent_writers() {
+        let read_your_writes = ReadYourWrites::new(Duration::from_millis(50));
+        read_your_writes.record_write("user:1");
+
+        assert!(read_your_writes.is_pinned("user:1"));
+        assert!(!read_your_writes.is_pinned("user:2"));
+        assert_eq!(read_your_writes.purge_expired(), 0);
+
+        std::thread::sleep(Duration::from_millis(60));
+        assert!(!read_your_writes.is_pinned("user:1"));
+        assert_eq!(read_your_writes.purge_expired(), 1);
+    }
+
+    #[test]
+    fn a_zero_window_pins_nobody() {
+        let read_your_writes = ReadYourWrites::new(Duration::ZERO);
+        read_your_writes.record_write("user:1");
+
+        assert!(!read_your_writes.is_pinned("user:1"));
+    }
+}
@@ -0,0 +1,67 @@
+use tracing::instrument;
+
+use crate::{abstract_trait::DynUnitOfWorkFactory, error::AppError};
+
+/// How many notes and users there are at one point in time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Totals {
+    pub notes: i64,
+    pub users: i64,
+}
+
+pub struct StatsService {
+    unit_of_work: DynUnitOfWorkFactory,
+}
+
+impl StatsService {
+    pub fn new(unit_of_work: DynUnitOfWorkFactory) -> Self {
+        Self { unit_of_work }
+    }
+
+    /// Counts both tables in one unit of work, so the totals are taken from
+    /// the same snapshot even while notes and users are being written.
+    #[instrument(name = "StatsService::totals", skip_all)]
+    pub async fn totals(&self) -> Result<Totals, AppError> {
+        let unit_of_work = self.unit_of_work.begin().await?;
+        let notes = unit_of_work.notes().count().await?;
+        let users = unit_of_work.users().count().await?;
+        unit_of_work.commit().await?;
+
+        Ok(Totals { notes, users })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::abstract_trait::{NoteRepositoryTrait, UserRepositoryTrait};
+    use crate::repository::{
+        InMemoryDatabase, InMemoryNoteRepository, InMemoryUnitOfWorkFactory, InMemoryUserRepository,
+    };
+
+    #[tokio::test]
+    async fn counts_notes_and_users() {
+        let database = InMemoryDatabase::default();
+        let notes = InMemoryNoteRepository::new(database.clone());
+        let users = InMemoryUserRepository::new(database.clone());
+        let service = StatsService::new(Arc::new(InMemoryUnitOfWorkFactory::new(database)));
+        assert_eq!(
+            service.totals().await.unwrap(),
+            Totals { notes: 0, users: 0 }
+        );
+
+        notes.create_note("One", "Content").await.unwrap();
+        notes.create_note("Two", "Content").await.unwrap();
+        users
+            .create_user("Ada", "Lovelace", "ada@example.com", "hash")
+            .await
+            .unwrap();
+
+        assert_eq!(
+            service.totals().await.unwrap(),
+            Totals { notes: 2, users: 1 }
+        );
+    }
+}
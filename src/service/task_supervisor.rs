@@ -0,0 +1,71 @@
+use std::future::Future;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use actix_web::rt::task::JoinHandle;
+use actix_web::rt::time::{timeout, Instant};
+use tokio::sync::watch;
+
+/// Handed to every supervised task. Resolves once shutdown has begun.
+#[derive(Clone)]
+pub struct ShutdownSignal(watch::Receiver<bool>);
+
+impl ShutdownSignal {
+    pub async fn cancelled(&mut self) {
+        // The sender lives as long as the supervisor; if it is gone there is
+        // nothing left to wait for either.
+        let _ = self.0.wait_for(|cancelled| *cancelled).await;
+    }
+}
+
+/// Owns long running background jobs so they can be told to stop, and
+/// waited for, when the server shuts down.
+pub struct TaskSupervisor {
+    shutdown: watch::Sender<bool>,
+    tasks: Mutex<Vec<(String, JoinHandle<()>)>>,
+}
+
+impl Default for TaskSupervisor {
+    fn default() -> Self {
+        TaskSupervisor {
+            shutdown: watch::channel(false).0,
+            tasks: Mutex::new(Vec::new()),
+        }
+    }
+}
+
+impl TaskSupervisor {
+    /// Spawns `task` on the current runtime. The task should return soon
+    /// after its [`ShutdownSignal`] resolves.
+    pub fn spawn<F, Fut>(&self, name: &str, task: F)
+    where
+        F: FnOnce(ShutdownSignal) -> Fut,
+        Fut: Future<Output = ()> + 'static,
+    {
+        let signal = ShutdownSignal(self.shutdown.subscribe());
+        let handle = actix_web::rt::spawn(task(signal));
+
+        self.tasks.lock().unwrap().push((name.to_string(), handle));
+    }
+
+    /// Signals every task to stop and waits up to `grace` in total for
+    /// them, aborting the ones that are still running after that.
+    pub async fn shutdown(&self, grace: Duration) {
+        self.shutdown.send_replace(true);
+
+        let deadline = Instant::now() + grace;
+        let tasks = std::mem::take(&mut *self.tasks.lock().unwrap());
+
+        for (name, mut handle) in tasks {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            match timeout(remaining, &mut handle).await {
+                Ok(Ok(())) => log::debug!("Background task {} stopped", name),
+                Ok(Err(err)) => log::error!("Background task {} failed: {}", name, err),
+                Err(_) => {
+                    log::warn!("Background task {} did not stop in time, aborting", name);
+                    handle.abort();
+                }
+            }
+        }
+    }
+}
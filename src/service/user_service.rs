@@ -1,32 +1,38 @@
 use async_trait::async_trait;
+use tracing::instrument;
 
-use crate::abstract_trait::{DynUserRepository, UserServiceTrait};
+use crate::abstract_trait::{DynUnitOfWorkFactory, DynUserRepository, UserServiceTrait};
+use crate::error::AppError;
 use crate::models::UserModel;
 use crate::response::UserSchema;
 
-use sqlx::Error;
 use uuid::Uuid;
 
 #[derive(Clone)]
 pub struct UserService {
     pub repository: DynUserRepository,
+    unit_of_work: DynUnitOfWorkFactory,
 }
 
 impl UserService {
-    pub fn new(repository: DynUserRepository) -> Self {
-        Self { repository }
+    pub fn new(repository: DynUserRepository, unit_of_work: DynUnitOfWorkFactory) -> Self {
+        Self {
+            repository,
+            unit_of_work,
+        }
     }
 }
 
 #[async_trait]
 impl UserServiceTrait for UserService {
+    #[instrument(name = "UserService::create_user", skip_all)]
     async fn create_user(
         &self,
         firstname: &str,
         lastname: &str,
         email: &str,
         password: &str,
-    ) -> anyhow::Result<UserSchema> {
+    ) -> Result<UserSchema, AppError> {
         let user = self
             .repository
             .create_user(firstname, lastname, email, password)
@@ -34,32 +40,65 @@ impl UserServiceTrait for UserService {
         Ok(user.into())
     }
 
-    async fn find_by_email_exists(&self, email: &str) -> anyhow::Result<bool> {
+    #[instrument(name = "UserService::create_user_with_role", skip_all)]
+    async fn create_user_with_role(
+        &self,
+        firstname: &str,
+        lastname: &str,
+        email: &str,
+        password: &str,
+        role: &str,
+    ) -> Result<UserModel, AppError> {
+        let unit_of_work = self.unit_of_work.begin().await?;
+        let user = unit_of_work
+            .users()
+            .create_user(firstname, lastname, email, password)
+            .await?;
+        let user = unit_of_work
+            .users()
+            .update_role(&user.email, role)
+            .await?
+            .ok_or_else(|| AppError::internal("created user disappeared"))?;
+        unit_of_work.commit().await?;
+
+        Ok(user)
+    }
+
+    #[instrument(name = "UserService::find_by_email_exists", skip_all)]
+    async fn find_by_email_exists(&self, email: &str) -> Result<bool, AppError> {
         self.repository
             .find_by_email_exists(email)
             .await
             .map_err(|err| err.into())
     }
 
-    async fn find_user_by_email(&self, email: &str) -> anyhow::Result<Option<UserModel>> {
+    #[instrument(name = "UserService::find_user_by_email", skip_all)]
+    async fn find_user_by_email(&self, email: &str) -> Result<Option<UserModel>, AppError> {
         self.repository
             .find_by_email(email)
             .await
             .map_err(|err| err.into())
     }
 
-    async fn find_by_id(&self, id: Uuid) -> anyhow::Result<Option<UserSchema>> {
+    #[instrument(name = "UserService::find_by_id", skip_all, fields(user.id = %id))]
+    async fn find_by_id(&self, id: Uuid) -> Result<Option<UserSchema>, AppError> {
         let user = self.repository.find_by_id(id).await?;
         Ok(user.map(|u| u.into()))
     }
 
+    #[instrument(name = "UserService::find_user_by_id", skip_all, fields(user.id = %id))]
+    async fn find_user_by_id(&self, id: Uuid) -> Result<Option<UserModel>, AppError> {
+        Ok(self.repository.find_by_id(id).await?)
+    }
+
+    #[instrument(name = "UserService::update_user", skip_all)]
     async fn update_user(
         &self,
         email: &str,
         firstname: &str,
         lastname: &str,
         password: &str,
-    ) -> anyhow::Result<Option<UserSchema>> {
+    ) -> Result<Option<UserSchema>, AppError> {
         let user = self
             .repository
             .update_user(email, firstname, lastname, password)
@@ -67,11 +106,61 @@ impl UserServiceTrait for UserService {
         Ok(user.map(|u| u.into()))
     }
 
-    async fn delete_user(&self, email: &str) -> anyhow::Result<bool> {
-        Ok(self
-            .repository
-            .delete_user(email)
+    #[instrument(name = "UserService::delete_user", skip_all)]
+    async fn delete_user(&self, email: &str) -> Result<bool, AppError> {
+        Ok(self.repository.delete_user(email).await?)
+    }
+
+    #[instrument(name = "UserService::update_role", skip_all)]
+    async fn update_role(&self, email: &str, role: &str) -> Result<Option<UserModel>, AppError> {
+        Ok(self.repository.update_role(email, role).await?)
+    }
+
+    #[instrument(name = "UserService::set_disabled", skip_all)]
+    async fn set_disabled(
+        &self,
+        email: &str,
+        disabled: bool,
+    ) -> Result<Option<UserModel>, AppError> {
+        Ok(self.repository.set_disabled(email, disabled).await?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::repository::{InMemoryDatabase, InMemoryUnitOfWorkFactory, InMemoryUserRepository};
+
+    fn service() -> UserService {
+        let database = InMemoryDatabase::default();
+        UserService::new(
+            Arc::new(InMemoryUserRepository::new(database.clone())),
+            Arc::new(InMemoryUnitOfWorkFactory::new(database)),
+        )
+    }
+
+    #[tokio::test]
+    async fn creates_users_with_a_role_or_not_at_all() {
+        let service = service();
+
+        let user = service
+            .create_user_with_role("Ada", "Lovelace", "ada@example.com", "hash", "admin")
+            .await
+            .unwrap();
+        assert_eq!(user.role, "admin");
+        let found = service.find_user_by_email("ada@example.com").await.unwrap();
+        assert_eq!(found.unwrap().role, "admin");
+
+        let too_long = "x".repeat(51);
+        service
+            .create_user_with_role("Grace", "Hopper", "grace@example.com", "hash", &too_long)
+            .await
+            .unwrap_err();
+        assert!(!service
+            .find_by_email_exists("grace@example.com")
             .await
-            .expect("Error Delete"))
+            .unwrap());
     }
 }
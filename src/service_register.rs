@@ -1,31 +1,232 @@
 use std::sync::Arc;
+use std::time::Duration;
+
+use sqlx::postgres::PgPool;
+use sqlx::Postgres;
 
 use crate::{
-    abstract_trait::{DynNoteRepository, DynNoteService, DynUserService},
-    config::{Config, ConnectionPool},
-    repository::{NoteRepository, UserRepository},
-    service::{NoteService, UserService},
+    abstract_trait::{
+        DynHealthCheck, DynNoteRepository, DynNoteService, DynRateLimitStore, DynUnitOfWorkFactory,
+        DynUserRepository, DynUserService,
+    },
+    config::{Config, ConnectionPool, DatabaseBackend, RateLimitStore},
+    metrics::Metrics,
+    repository::{
+        CachedNoteRepository, CachedUnitOfWorkFactory, CachedUserRepository, InMemoryDatabase,
+        InMemoryNoteRepository, InMemoryRateLimitStore, InMemoryUnitOfWorkFactory,
+        InMemoryUserRepository, MeteredNoteRepository, MeteredUnitOfWorkFactory,
+        MeteredUserRepository, NoteRepository, PgRateLimitStore, ReplicaRouter, RepositoryCaches,
+        SqlUnitOfWorkFactory, SqliteNoteRepository, SqliteUserRepository, UserRepository,
+    },
+    security::PasswordPolicy,
+    service::{
+        DatabaseCheck, HealthService, MigrationsCheck, NoteService, RateLimiter, ReadYourWrites,
+        StatsService, TaskSupervisor, UserService,
+    },
 };
 
 #[derive(Clone)]
 pub struct ServiceRegister {
     pub env: Config,
+    /// `None` with the in-memory repositories.
+    pub pool: Option<ConnectionPool>,
     pub note_service: DynNoteService,
     pub user_service: DynUserService,
+    pub stats_service: Arc<StatsService>,
+    pub password_policy: Arc<PasswordPolicy>,
+    pub health_service: Arc<HealthService>,
+    pub rate_limiter: Arc<RateLimiter>,
+    pub metrics: Arc<Metrics>,
+    pub task_supervisor: Arc<TaskSupervisor>,
+    /// Set when read replicas are configured.
+    pub replicas: Option<Arc<ReplicaRouter<Postgres>>>,
+    /// Set when read replicas are configured.
+    pub read_your_writes: Option<Arc<ReadYourWrites>>,
+    /// Set when `cache.enabled`.
+    pub caches: Option<RepositoryCaches>,
+}
+
+/// Where the services keep their data.
+struct Storage {
+    note_repository: DynNoteRepository,
+    user_repository: DynUserRepository,
+    unit_of_work: DynUnitOfWorkFactory,
+    rate_limit_store: DynRateLimitStore,
+    replicas: Option<Arc<ReplicaRouter<Postgres>>>,
 }
 
 impl ServiceRegister {
-    pub fn new(pool: ConnectionPool, config: Config) -> Self {
-        let note_repository = Arc::new(NoteRepository::new(pool.clone())) as DynNoteRepository;
+    /// `replicas` are read replicas of a Postgres `pool`, see
+    /// [`ReplicaRouter`].
+    pub fn new(
+        pool: ConnectionPool,
+        replicas: Vec<PgPool>,
+        config: Config,
+        password_policy: PasswordPolicy,
+        metrics: Metrics,
+    ) -> Self {
+        let router = match &pool {
+            ConnectionPool::Postgres(pg_pool) if !replicas.is_empty() => {
+                Some(Arc::new(ReplicaRouter::new(pg_pool.clone(), replicas)))
+            }
+            _ => None,
+        };
+        let (note_repository, user_repository) = match (&pool, &router) {
+            (_, Some(router)) => (
+                Arc::new(NoteRepository::with_replicas(router.clone())) as DynNoteRepository,
+                Arc::new(UserRepository::with_replicas(router.clone())) as DynUserRepository,
+            ),
+            (ConnectionPool::Postgres(pg_pool), None) => (
+                Arc::new(NoteRepository::new(pg_pool.clone())) as DynNoteRepository,
+                Arc::new(UserRepository::new(pg_pool.clone())) as DynUserRepository,
+            ),
+            (ConnectionPool::Sqlite(sqlite_pool), None) => (
+                Arc::new(SqliteNoteRepository::new(sqlite_pool.clone())) as DynNoteRepository,
+                Arc::new(SqliteUserRepository::new(sqlite_pool.clone())) as DynUserRepository,
+            ),
+        };
+        // Config validation only allows the Postgres store with a Postgres
+        // database.
+        let rate_limit_store = match (config.rate_limit.store, &pool) {
+            (RateLimitStore::Postgres, ConnectionPool::Postgres(pg_pool)) => {
+                Arc::new(PgRateLimitStore::new(pg_pool.clone())) as DynRateLimitStore
+            }
+            _ => Arc::new(InMemoryRateLimitStore::default()) as DynRateLimitStore,
+        };
+        let storage = Storage {
+            note_repository,
+            user_repository,
+            unit_of_work: Arc::new(SqlUnitOfWorkFactory::new(pool.clone())),
+            rate_limit_store,
+            replicas: router,
+        };
+
+        let register = Self::build(
+            Some(pool.clone()),
+            config,
+            password_policy,
+            metrics,
+            storage,
+        );
+
+        register.register_health_check(Arc::new(DatabaseCheck::new(pool.clone())));
+        register.register_health_check(Arc::new(MigrationsCheck::new(pool)));
+
+        register
+    }
+
+    /// Services backed by in-memory repositories, for tests and demos. Nothing
+    /// is persisted and no database is needed: there is no pool and no
+    /// database readiness checks. Rate limit buckets are
+    /// always kept in memory, and `database.backend` is set to `memory`.
+    pub fn in_memory(
+        mut config: Config,
+        password_policy: PasswordPolicy,
+        metrics: Metrics,
+    ) -> Self {
+        config.database.backend = DatabaseBackend::Memory;
+        let database = InMemoryDatabase::default();
+        let storage = Storage {
+            note_repository: Arc::new(InMemoryNoteRepository::new(database.clone())),
+            user_repository: Arc::new(InMemoryUserRepository::new(database.clone())),
+            unit_of_work: Arc::new(InMemoryUnitOfWorkFactory::new(database)),
+            rate_limit_store: Arc::new(InMemoryRateLimitStore::default()),
+            replicas: None,
+        };
+
+        Self::build(None, config, password_policy, metrics, storage)
+    }
+
+    fn build(
+        pool: Option<ConnectionPool>,
+        config: Config,
+        password_policy: PasswordPolicy,
+        metrics: Metrics,
+        storage: Storage,
+    ) -> Self {
+        let metrics = Arc::new(metrics);
+        let caches = config
+            .cache
+            .enabled
+            .then(|| RepositoryCaches::new(&config.cache));
+
+        let mut note_repository = Arc::new(MeteredNoteRepository::new(
+            storage.note_repository,
+            metrics.clone(),
+        )) as DynNoteRepository;
+        if let Some(caches) = &caches {
+            note_repository = Arc::new(CachedNoteRepository::new(
+                note_repository,
+                caches,
+                metrics.clone(),
+            ));
+        }
+        let mut unit_of_work = Arc::new(MeteredUnitOfWorkFactory::new(
+            storage.unit_of_work,
+            metrics.clone(),
+        )) as DynUnitOfWorkFactory;
+        if let Some(caches) = &caches {
+            unit_of_work = Arc::new(CachedUnitOfWorkFactory::new(unit_of_work, caches));
+        }
         let note_service = Arc::new(NoteService::new(note_repository)) as DynNoteService;
+        let stats_service = Arc::new(StatsService::new(unit_of_work.clone()));
+
+        let mut user_repository = Arc::new(MeteredUserRepository::new(
+            storage.user_repository,
+            metrics.clone(),
+        )) as DynUserRepository;
+        if let Some(caches) = &caches {
+            user_repository = Arc::new(CachedUserRepository::new(
+                user_repository,
+                caches,
+                metrics.clone(),
+            ));
+        }
+        let user_service =
+            Arc::new(UserService::new(user_repository, unit_of_work)) as DynUserService;
+
+        let rate_limiter = Arc::new(RateLimiter::new(
+            &config.rate_limit,
+            storage.rate_limit_store,
+        ));
 
-        let user_repository = Arc::new(UserRepository::new(pool.clone()));
-        let user_service = Arc::new(UserService::new(user_repository.clone()));
+        let read_your_writes = storage.replicas.as_ref().map(|_| {
+            Arc::new(ReadYourWrites::new(Duration::from_secs(
+                config.database.read_your_writes_secs,
+            )))
+        });
 
         ServiceRegister {
-            env: config.clone(),
+            env: config,
+            pool,
             note_service,
             user_service,
+            stats_service,
+            password_policy: Arc::new(password_policy),
+            health_service: Arc::new(HealthService::default()),
+            rate_limiter,
+            metrics,
+            task_supervisor: Arc::new(TaskSupervisor::default()),
+            replicas: storage.replicas,
+            read_your_writes,
+            caches,
         }
     }
+
+    /// Stops background tasks, giving them up to `grace` to finish, then
+    /// closes the database pools.
+    pub async fn shutdown(&self, grace: Duration) {
+        self.task_supervisor.shutdown(grace).await;
+        if let Some(replicas) = &self.replicas {
+            replicas.close_replicas().await;
+        }
+        if let Some(pool) = &self.pool {
+            pool.close().await;
+        }
+    }
+
+    /// Adds a dependency to the readiness probe.
+    pub fn register_health_check(&self, check: DynHealthCheck) {
+        self.health_service.register(check);
+    }
 }
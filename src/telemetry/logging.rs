@@ -0,0 +1,149 @@
+use std::fmt;
+use std::future::Future;
+
+use serde_json::{json, Map, Value};
+use tracing::field::{Field, Visit};
+use tracing::{Event, Subscriber};
+use tracing_log::NormalizeEvent;
+use tracing_subscriber::fmt::format::Writer;
+use tracing_subscriber::fmt::{FmtContext, FormatEvent, FormatFields};
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::{EnvFilter, Layer};
+
+use crate::config::{LogFormat, LoggingConfig};
+
+tokio::task_local! {
+    static REQUEST_ID: String;
+}
+
+/// Runs `fut` with `request_id` attached to every log line it emits.
+pub async fn with_request_id<F: Future>(request_id: String, fut: F) -> F::Output {
+    REQUEST_ID.scope(request_id, fut).await
+}
+
+fn current_request_id() -> Option<String> {
+    REQUEST_ID.try_with(|id| id.clone()).ok()
+}
+
+/// Writes log lines to stderr in the configured format. `RUST_LOG` takes
+/// precedence over `config.level`.
+pub fn log_layer<S>(config: &LoggingConfig) -> impl Layer<S>
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    let filter =
+        EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new(config.level.as_str()));
+
+    tracing_subscriber::fmt::layer()
+        .with_writer(std::io::stderr)
+        .event_format(LogLine(config.format))
+        .with_filter(filter)
+}
+
+struct LogLine(LogFormat);
+
+impl<S, N> FormatEvent<S, N> for LogLine
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+    N: for<'a> FormatFields<'a> + 'static,
+{
+    fn format_event(
+        &self,
+        _ctx: &FmtContext<'_, S, N>,
+        mut writer: Writer<'_>,
+        event: &Event<'_>,
+    ) -> fmt::Result {
+        // Records bridged from the `log` crate carry their real target and
+        // level in `log.*` fields.
+        let normalized = event.normalized_metadata();
+        let metadata = normalized.as_ref().unwrap_or_else(|| event.metadata());
+
+        let mut fields = FieldVisitor::default();
+        event.record(&mut fields);
+
+        let timestamp = chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Millis, true);
+        let request_id = current_request_id();
+
+        match self.0 {
+            LogFormat::Json => {
+                let mut line = Map::new();
+                line.insert("timestamp".into(), json!(timestamp));
+                line.insert("level".into(), json!(metadata.level().as_str()));
+                line.insert("target".into(), json!(metadata.target()));
+                line.insert("message".into(), json!(fields.message));
+                if let Some(request_id) = request_id {
+                    line.insert("requestId".into(), json!(request_id));
+                }
+                line.extend(fields.values);
+
+                writeln!(writer, "{}", Value::Object(line))
+            }
+            LogFormat::Text => {
+                write!(
+                    writer,
+                    "[{} {:<5} {}] ",
+                    timestamp,
+                    metadata.level().as_str(),
+                    metadata.target()
+                )?;
+                if let Some(request_id) = request_id {
+                    write!(writer, "[{}] ", request_id)?;
+                }
+                write!(writer, "{}", fields.message)?;
+                for (name, value) in fields.values {
+                    write!(writer, " {}={}", name, value)?;
+                }
+
+                writeln!(writer)
+            }
+        }
+    }
+}
+
+#[derive(Default)]
+struct FieldVisitor {
+    message: String,
+    values: Map<String, Value>,
+}
+
+impl FieldVisitor {
+    fn insert(&mut self, field: &Field, value: Value) {
+        if field.name().starts_with("log.") {
+            return;
+        }
+        if field.name() == "message" {
+            self.message = match value {
+                Value::String(message) => message,
+                value => value.to_string(),
+            };
+        } else {
+            self.values.insert(field.name().to_string(), value);
+        }
+    }
+}
+
+impl Visit for FieldVisitor {
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        self.insert(field, json!(value));
+    }
+
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        self.insert(field, json!(value));
+    }
+
+    fn record_f64(&mut self, field: &Field, value: f64) {
+        self.insert(field, json!(value));
+    }
+
+    fn record_bool(&mut self, field: &Field, value: bool) {
+        self.insert(field, json!(value));
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.insert(field, json!(value));
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        self.insert(field, Value::String(format!("{:?}", value)));
+    }
+}
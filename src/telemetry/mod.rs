@@ -0,0 +1,22 @@
+mod logging;
+mod stdout_exporter;
+mod tracer;
+
+use opentelemetry::trace::TraceError;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+
+use crate::config::{LoggingConfig, TracingConfig};
+
+pub use logging::with_request_id;
+pub use tracer::shutdown_tracing;
+
+/// Installs the global subscriber, which writes log lines (including
+/// records from the `log` crate) and, when enabled, exports spans.
+pub fn init(logging: &LoggingConfig, tracing: &TracingConfig) -> Result<(), TraceError> {
+    tracing_subscriber::registry()
+        .with(logging::log_layer(logging))
+        .with(tracer::trace_layer(tracing)?)
+        .try_init()
+        .map_err(|err| TraceError::Other(err.into()))
+}
@@ -0,0 +1,61 @@
+use std::io::Write;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use futures_util::future::BoxFuture;
+use opentelemetry::trace::Status;
+use opentelemetry_sdk::export::trace::{ExportResult, SpanData, SpanExporter};
+use serde_json::{json, Map, Value};
+
+/// Writes every finished span to stdout as a single JSON line.
+#[derive(Debug, Default)]
+pub struct StdoutExporter;
+
+impl SpanExporter for StdoutExporter {
+    fn export(&mut self, batch: Vec<SpanData>) -> BoxFuture<'static, ExportResult> {
+        let mut stdout = std::io::stdout().lock();
+        for span in batch {
+            // A closed stdout is not worth failing the exporter over.
+            let _ = writeln!(stdout, "{}", span_json(&span));
+        }
+
+        Box::pin(std::future::ready(Ok(())))
+    }
+}
+
+fn span_json(span: &SpanData) -> Value {
+    let attributes: Map<String, Value> = span
+        .attributes
+        .iter()
+        .map(|(key, value)| (key.to_string(), Value::String(value.to_string())))
+        .collect();
+
+    let duration = span
+        .end_time
+        .duration_since(span.start_time)
+        .unwrap_or_default();
+
+    let (status, status_message) = match &span.status {
+        Status::Unset => ("unset", None),
+        Status::Ok => ("ok", None),
+        Status::Error { description } => ("error", Some(description.to_string())),
+    };
+
+    json!({
+        "name": span.name,
+        "traceId": span.span_context.trace_id().to_string(),
+        "spanId": span.span_context.span_id().to_string(),
+        "parentSpanId": span.parent_span_id.to_string(),
+        "kind": format!("{:?}", span.span_kind).to_lowercase(),
+        "startTimeUnixNano": unix_nanos(span.start_time),
+        "durationMicros": duration.as_micros() as u64,
+        "status": status,
+        "statusMessage": status_message,
+        "attributes": attributes,
+    })
+}
+
+fn unix_nanos(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_nanos() as u64)
+        .unwrap_or_default()
+}
@@ -0,0 +1,74 @@
+use opentelemetry::trace::{TraceError, TracerProvider as _};
+use opentelemetry::{global, KeyValue};
+use opentelemetry_otlp::WithExportConfig;
+use opentelemetry_sdk::propagation::TraceContextPropagator;
+use opentelemetry_sdk::trace::{self, Sampler, Tracer, TracerProvider};
+use opentelemetry_sdk::{runtime, Resource};
+use tracing::Subscriber;
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::{EnvFilter, Layer};
+
+use crate::config::{TracingConfig, TracingExporter};
+
+use super::stdout_exporter::StdoutExporter;
+
+/// Only spans from this crate are exported. Dependencies such as sqlx emit
+/// their own spans and events, which would duplicate ours and may include
+/// query text.
+const SPAN_FILTER: &str = "crudsqlx=info";
+
+/// Builds the span exporting layer and installs the W3C trace context
+/// propagator, or returns `None` when tracing is disabled.
+pub fn trace_layer<S>(config: &TracingConfig) -> Result<Option<impl Layer<S>>, TraceError>
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    let tracer = match config.exporter {
+        TracingExporter::None => return Ok(None),
+        TracingExporter::Otlp => opentelemetry_otlp::new_pipeline()
+            .tracing()
+            .with_exporter(
+                opentelemetry_otlp::new_exporter()
+                    .tonic()
+                    .with_endpoint(&config.otlp_endpoint),
+            )
+            .with_trace_config(trace_config(config))
+            .install_batch(runtime::TokioCurrentThread)?,
+        TracingExporter::Stdout => stdout_tracer(config),
+    };
+
+    global::set_text_map_propagator(TraceContextPropagator::new());
+
+    Ok(Some(
+        tracing_opentelemetry::layer()
+            .with_tracer(tracer)
+            .with_filter(EnvFilter::new(SPAN_FILTER)),
+    ))
+}
+
+/// Flushes spans that have not been exported yet.
+pub fn shutdown_tracing() {
+    global::shutdown_tracer_provider();
+}
+
+fn trace_config(config: &TracingConfig) -> trace::Config {
+    trace::config()
+        .with_sampler(Sampler::ParentBased(Box::new(Sampler::TraceIdRatioBased(
+            config.sample_ratio,
+        ))))
+        .with_resource(Resource::new(vec![KeyValue::new(
+            "service.name",
+            config.service_name.clone(),
+        )]))
+}
+
+fn stdout_tracer(config: &TracingConfig) -> Tracer {
+    let provider = TracerProvider::builder()
+        .with_simple_exporter(StdoutExporter)
+        .with_config(trace_config(config))
+        .build();
+    let tracer = provider.tracer(env!("CARGO_PKG_NAME"));
+    global::set_tracer_provider(provider);
+
+    tracer
+}